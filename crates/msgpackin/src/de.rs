@@ -145,7 +145,7 @@ impl<'de> DeserializerSync<'de> {
     }
 }
 
-impl<'de, 'a> de::Deserializer<'de> for &'a mut DeserializerSync<'de> {
+impl<'de> de::Deserializer<'de> for &mut DeserializerSync<'de> {
     type Error = Error;
 
     fn is_human_readable(&self) -> bool {
@@ -0,0 +1,329 @@
+//! `feature=serde_json` conversions between `Value` and `serde_json::Value`
+//!
+//! Every JSON value has a msgpack equivalent, so `Value::from` a
+//! `serde_json::Value` never fails. The other direction is lossy -
+//! msgpack has bin, ext, non-string map keys, integers beyond the
+//! precision of JSON's doubles, and non-finite floats. How each of
+//! these is handled is controlled by a `JsonPolicy`. The `TryFrom`
+//! impls use the default policy, `to_json_with` takes an explicit one.
+//!
+//! ```
+//! use msgpackin::json::*;
+//! use msgpackin::Value;
+//!
+//! let value = Value::Map(vec![
+//!     ("bin".into(), Value::Bin(b"hi".to_vec().into())),
+//!     (Value::from(7), "seven".into()),
+//! ]);
+//!
+//! let json = serde_json::Value::try_from(&value).unwrap();
+//! assert_eq!(r#"{"7":"seven","bin":"aGk="}"#, json.to_string());
+//!
+//! let mut policy = JsonPolicy::default();
+//! policy.key = KeyPolicy::Error;
+//! assert!(value.to_json_with(&policy).is_err());
+//!
+//! let back = Value::from(serde_json::json!({ "a": [1, 2.5, null] }));
+//! assert_eq!(
+//!     Value::Map(vec![(
+//!         "a".into(),
+//!         Value::Arr(vec![1.into(), 2.5.into(), ().into()]),
+//!     )]),
+//!     back,
+//! );
+//! ```
+
+use crate::value::*;
+use crate::*;
+use msgpackin_core::json::{write_bin, BinEncoding};
+use serde_json::{Map, Number, Value as JsonValue};
+
+pub use msgpackin_core::json::NonFinite;
+
+/// The largest integer magnitude a JSON (f64) number holds exactly,
+/// `2^53 - 1`
+pub const MAX_SAFE_INTEGER: u64 = (1 << 53) - 1;
+
+/// How msgpack bin data is converted to JSON
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub enum BinPolicy {
+    /// a string of standard base64 with padding
+    #[default]
+    Base64,
+
+    /// a string of lowercase hex
+    Hex,
+
+    /// an array of byte numbers
+    Array,
+
+    /// fail the conversion
+    Error,
+}
+
+/// How msgpack ext data is converted to JSON
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub enum ExtPolicy {
+    /// an object `{"type": <i8>, "data": <bin>}`, with the data
+    /// converted according to the `BinPolicy` (`BinPolicy::Error`
+    /// falls back to base64 here)
+    #[default]
+    Object,
+
+    /// fail the conversion
+    Error,
+}
+
+/// How msgpack map keys that are not strings are converted to JSON
+/// object keys
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub enum KeyPolicy {
+    /// use the JSON text of the converted key, e.g. `7` -> `"7"`,
+    /// `[1,2]` -> `"[1,2]"`
+    #[default]
+    Stringify,
+
+    /// fail the conversion
+    Error,
+}
+
+/// How integers with a magnitude above `MAX_SAFE_INTEGER` are converted
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub enum BigIntPolicy {
+    /// keep the exact integer - `serde_json` round trips it, but many
+    /// other JSON readers will lose precision
+    #[default]
+    Number,
+
+    /// a string of the decimal digits
+    String,
+
+    /// the nearest f64 number
+    Float,
+
+    /// fail the conversion
+    Error,
+}
+
+/// Options for converting a msgpack `Value` into a `serde_json::Value`
+#[non_exhaustive]
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct JsonPolicy {
+    /// how bin data is converted
+    pub bin: BinPolicy,
+
+    /// how ext data is converted
+    pub ext: ExtPolicy,
+
+    /// how non-string map keys are converted
+    pub key: KeyPolicy,
+
+    /// how integers beyond `MAX_SAFE_INTEGER` are converted
+    pub big_int: BigIntPolicy,
+
+    /// how NaN and infinite floats are converted
+    pub non_finite: NonFinite,
+}
+
+impl From<JsonValue> for Value {
+    fn from(j: JsonValue) -> Self {
+        match j {
+            JsonValue::Null => Value::Nil,
+            JsonValue::Bool(b) => Value::Bool(b),
+            JsonValue::Number(n) => num_from_json(&n),
+            JsonValue::String(s) => Value::from(s),
+            JsonValue::Array(a) => {
+                Value::Arr(a.into_iter().map(Value::from).collect())
+            }
+            JsonValue::Object(o) => Value::Map(
+                o.into_iter()
+                    .map(|(k, v)| (Value::from(k), Value::from(v)))
+                    .collect(),
+            ),
+        }
+    }
+}
+
+impl From<&JsonValue> for Value {
+    fn from(j: &JsonValue) -> Self {
+        j.clone().into()
+    }
+}
+
+impl TryFrom<&Value> for JsonValue {
+    type Error = Error;
+
+    fn try_from(v: &Value) -> Result<Self> {
+        v.to_json_with(&JsonPolicy::default())
+    }
+}
+
+impl TryFrom<Value> for JsonValue {
+    type Error = Error;
+
+    fn try_from(v: Value) -> Result<Self> {
+        v.to_json_with(&JsonPolicy::default())
+    }
+}
+
+impl TryFrom<&ValueRef<'_>> for JsonValue {
+    type Error = Error;
+
+    fn try_from(v: &ValueRef<'_>) -> Result<Self> {
+        v.to_json_with(&JsonPolicy::default())
+    }
+}
+
+impl TryFrom<ValueRef<'_>> for JsonValue {
+    type Error = Error;
+
+    fn try_from(v: ValueRef<'_>) -> Result<Self> {
+        v.to_json_with(&JsonPolicy::default())
+    }
+}
+
+impl Value {
+    /// (`feature = "serde_json"`)
+    /// Convert to a `serde_json::Value` according to `policy`
+    pub fn to_json_with(&self, policy: &JsonPolicy) -> Result<JsonValue> {
+        self.as_ref().to_json_with(policy)
+    }
+}
+
+impl ValueRef<'_> {
+    /// (`feature = "serde_json"`)
+    /// Convert to a `serde_json::Value` according to `policy`
+    pub fn to_json_with(&self, policy: &JsonPolicy) -> Result<JsonValue> {
+        Ok(match self {
+            ValueRef::Nil => JsonValue::Null,
+            ValueRef::Bool(b) => JsonValue::Bool(*b),
+            ValueRef::Num(n) => num_to_json(*n, policy)?,
+            ValueRef::Str(s) => JsonValue::String(s.as_str()?.into()),
+            ValueRef::Bin(data) => match policy.bin {
+                BinPolicy::Error => {
+                    return Err("bin data is not allowed by JsonPolicy".into())
+                }
+                bin => bin_to_json(data, bin),
+            },
+            ValueRef::Ext(t, data) => match policy.ext {
+                ExtPolicy::Object => {
+                    let bin = match policy.bin {
+                        BinPolicy::Error => BinPolicy::Base64,
+                        bin => bin,
+                    };
+                    let mut o = Map::new();
+                    o.insert("type".into(), (*t).into());
+                    o.insert("data".into(), bin_to_json(data, bin));
+                    JsonValue::Object(o)
+                }
+                ExtPolicy::Error => {
+                    return Err("ext data is not allowed by JsonPolicy".into())
+                }
+            },
+            ValueRef::Arr(a) => JsonValue::Array(
+                a.iter()
+                    .map(|v| v.to_json_with(policy))
+                    .collect::<Result<_>>()?,
+            ),
+            ValueRef::Map(m) => {
+                let mut o = Map::new();
+                for (k, v) in m {
+                    let k = match k {
+                        ValueRef::Str(s) => s.as_str()?.into(),
+                        _ if policy.key == KeyPolicy::Error => {
+                            return Err("non-string map key is not allowed \
+                                by JsonPolicy"
+                                .into())
+                        }
+                        k => k.to_json_with(policy)?.to_string(),
+                    };
+                    // as in serde_json, a repeated key keeps the last value
+                    o.insert(k, v.to_json_with(policy)?);
+                }
+                JsonValue::Object(o)
+            }
+        })
+    }
+}
+
+fn num_from_json(n: &Number) -> Value {
+    if let Some(u) = n.as_u64() {
+        Value::from(u)
+    } else if let Some(i) = n.as_i64() {
+        Value::from(i)
+    } else {
+        // without serde_json "arbitrary_precision" this always succeeds
+        Value::from(n.as_f64().unwrap_or(f64::NAN))
+    }
+}
+
+fn num_to_json(n: Num, policy: &JsonPolicy) -> Result<JsonValue> {
+    let big = match n {
+        Num::Unsigned(u) => u > MAX_SAFE_INTEGER,
+        Num::Signed(i) => i.unsigned_abs() > MAX_SAFE_INTEGER,
+        Num::F32(_) | Num::F64(_) => false,
+    };
+    if big {
+        return match policy.big_int {
+            BigIntPolicy::Number => Ok(int_to_json(n)),
+            BigIntPolicy::String => Ok(JsonValue::String(match n {
+                Num::Unsigned(u) => u.to_string(),
+                _ => n.to::<i64>().to_string(),
+            })),
+            BigIntPolicy::Float => float_to_json(n.to::<f64>(), policy),
+            BigIntPolicy::Error => Err(Error::EOther(format!(
+                "integer {:?} exceeds MAX_SAFE_INTEGER",
+                n
+            ))),
+        };
+    }
+    match n {
+        Num::F32(f) => float_to_json(f as f64, policy),
+        Num::F64(f) => float_to_json(f, policy),
+        _ => Ok(int_to_json(n)),
+    }
+}
+
+fn int_to_json(n: Num) -> JsonValue {
+    match n {
+        Num::Unsigned(u) => u.into(),
+        _ => n.to::<i64>().into(),
+    }
+}
+
+fn float_to_json(f: f64, policy: &JsonPolicy) -> Result<JsonValue> {
+    if let Some(n) = Number::from_f64(f) {
+        return Ok(JsonValue::Number(n));
+    }
+    match policy.non_finite {
+        NonFinite::Null => Ok(JsonValue::Null),
+        NonFinite::Str => Ok(JsonValue::String(
+            if f.is_nan() {
+                "NaN"
+            } else if f > 0.0 {
+                "Infinity"
+            } else {
+                "-Infinity"
+            }
+            .into(),
+        )),
+        NonFinite::Error => Err(Error::EOther(format!(
+            "non-finite float {} is not allowed by JsonPolicy",
+            f
+        ))),
+    }
+}
+
+fn bin_to_json(data: &[u8], bin: BinPolicy) -> JsonValue {
+    let encoding = match bin {
+        BinPolicy::Array => {
+            return JsonValue::Array(data.iter().map(|b| (*b).into()).collect())
+        }
+        BinPolicy::Hex => BinEncoding::Hex,
+        BinPolicy::Base64 | BinPolicy::Error => BinEncoding::Base64,
+    };
+    let mut out = String::with_capacity(data.len() * 2);
+    // writing to a String cannot fail
+    let _ = write_bin(&mut out, data, encoding);
+    JsonValue::String(out)
+}
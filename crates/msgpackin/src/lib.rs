@@ -17,19 +17,21 @@
 //! - [x] Async IO support via `futures-io` or `tokio` features
 //! - [ ] recursion depth checking (the config is currently a stub)
 //! - [ ] hooks for managed encoding / decoding of ext types
-//!       (e.g. Timestamp (`-1`))
+//!   (e.g. Timestamp (`-1`))
 //! - [ ] benchmarking / optimization
 //!
 //! ### Features
 //!
 //! - `std` - enabled by default, pulls in the rust std library, enabling
-//!           encoding and decoding via `std::io::{Read, Write}` traits
+//!   encoding and decoding via `std::io::{Read, Write}` traits
 //! - `serde` - enables serialization / deserialization through the `serde`
-//!             crate
+//!   crate
 //! - `futures-io` - enables async encoding and decoding through the futures
-//!                  `io::{AsyncRead, AsyncWrite}` traits
+//!   `io::{AsyncRead, AsyncWrite}` traits
 //! - `tokio` - enables async encoding and decoding through the tokio
-//!             `io::{AsyncRead, AsyncWrite}` traits
+//!   `io::{AsyncRead, AsyncWrite}` traits
+//! - `serde_json` - enables conversions between `Value` and
+//!   `serde_json::Value` (see the `json` module)
 //!
 //! ### `no_std` Example
 //!
@@ -50,6 +52,24 @@
 //! assert_eq!(expect, decoded);
 //! ```
 //!
+//! ### `msgpack!` Example
+//!
+//! ```
+//! use msgpackin::*;
+//! let expect = msgpack!({
+//!     "nil": null,
+//!     "int": -42,
+//!     "bin": bin(b"bin-data"),
+//!     "ext": ext(-42, b"ext-data"),
+//!     "arr": ["one", "two", 3.5],
+//!     7: "int key",
+//! });
+//! let encoded = expect.to_bytes().unwrap();
+//! let decoded = ValueRef::from_ref(&encoded).unwrap();
+//! assert_eq!(expect, decoded);
+//! assert_eq!(Some("two"), decoded["arr"][1].as_str());
+//! ```
+//!
 //! ### `std` Example
 //!
 //! ```
@@ -150,7 +170,6 @@ mod lib {
 
     pub use self::core::fmt;
     pub use self::core::future::Future;
-    pub use self::core::iter;
     pub use self::core::mem;
     pub use self::core::pin;
     pub use self::core::result;
@@ -177,6 +196,7 @@ mod lib {
 }
 pub(crate) use lib::*;
 
+#[cfg(all(not(feature = "std"), not(feature = "serde")))]
 mod std_err {
     use crate::*;
 
@@ -201,6 +221,8 @@ pub mod types {
     #[cfg(all(not(feature = "std"), not(feature = "serde")))]
     pub use std_err::Error as StdError;
 
+    pub use msgpackin_core::encode::Compat;
+    pub use msgpackin_core::encode::FloatPolicy;
     pub use msgpackin_core::num::Num;
 
     mod config;
@@ -236,8 +258,27 @@ pub use de::{
     from_sync_config,
 };
 
+#[cfg(feature = "serde")]
+pub mod transcode;
+
+#[cfg(feature = "serde")]
+pub use transcode::{transcode, transcode_config};
+
 pub mod value;
 
+mod macros;
+
+#[doc(hidden)]
+pub mod __private {
+    #[cfg(not(feature = "std"))]
+    pub use alloc::vec;
+    #[cfg(feature = "std")]
+    pub use std::vec;
+}
+
+#[cfg(feature = "serde_json")]
+pub mod json;
+
 pub use value::Value;
 pub use value::ValueRef;
 
@@ -0,0 +1,306 @@
+//! `msgpack!` and `msgpack_ref!` literal construction macros
+
+/// Construct a `Value` from a JSON-like literal
+///
+/// - `null`, `true`, `false`, `[..]` arrays and `{..}` maps as in JSON
+/// - map keys may be any value, not only strings, e.g. `7: "int key"`
+/// - `bin(data)` and `ext(type, data)` for bin and ext data, where
+///   `data` is anything that can be sliced to `&[u8]`
+/// - any other Rust expression is converted with `Value::from`
+///
+/// Map entries keep the order they are written in. Each array item
+/// or map entry costs one level of macro recursion, so a single array
+/// or map with more than about 120 items needs a higher
+/// `#![recursion_limit]`.
+///
+/// ```
+/// use msgpackin::*;
+/// let name = "bob";
+/// let value = msgpack!({
+///     "a": 1,
+///     "b": [true, null, 3.5],
+///     "bin": bin(b"bin-data"),
+///     "ext": ext(-42, b"ext-data"),
+///     "name": name,
+///     7: "int key",
+/// });
+/// assert_eq!(
+///     Value::Map(vec![
+///         ("a".into(), 1.into()),
+///         (
+///             "b".into(),
+///             Value::Arr(vec![true.into(), ().into(), 3.5.into()]),
+///         ),
+///         ("bin".into(), Value::Bin(b"bin-data".to_vec().into())),
+///         ("ext".into(), Value::Ext(-42, b"ext-data".to_vec().into())),
+///         ("name".into(), "bob".into()),
+///         (7.into(), "int key".into()),
+///     ]),
+///     value,
+/// );
+/// ```
+#[macro_export]
+macro_rules! msgpack {
+    ($($t:tt)+) => {
+        $crate::__msgpack!(Value, $($t)+)
+    };
+}
+
+/// Construct a `ValueRef` from a JSON-like literal, borrowing any
+/// str / bin / ext data. See `msgpack!` for the syntax
+///
+/// ```
+/// use msgpackin::*;
+/// let data = vec![1, 2, 3];
+/// let value = msgpack_ref!({ "data": bin(data), "n": [1, -1] });
+/// assert_eq!(Some(&data[..]), value["data"].as_bytes());
+/// assert_eq!(value, msgpack!({ "data": bin(data), "n": [1, -1] }));
+/// ```
+#[macro_export]
+macro_rules! msgpack_ref {
+    ($($t:tt)+) => {
+        $crate::__msgpack!(ValueRef, $($t)+)
+    };
+}
+
+/// implementation of `msgpack!` / `msgpack_ref!`, parameterized
+/// by the value type name
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __msgpack {
+    // -- array items: (@arr $v [items,] rest..) -- //
+    // each item rule also consumes the following `,`, so an item
+    // costs one level of macro recursion
+
+    (@arr $v:ident [$($e:expr,)*]) => {
+        $crate::__private::vec![$($e,)*]
+    };
+
+    (@arr $v:ident [$($e:expr,)*] null $(, $($rest:tt)*)?) => {
+        $crate::__msgpack!(@arr $v [
+            $($e,)*
+            $crate::__msgpack!($v, null),
+        ] $($($rest)*)?)
+    };
+
+    (@arr $v:ident [$($e:expr,)*] true $(, $($rest:tt)*)?) => {
+        $crate::__msgpack!(@arr $v [
+            $($e,)*
+            $crate::__msgpack!($v, true),
+        ] $($($rest)*)?)
+    };
+
+    (@arr $v:ident [$($e:expr,)*] false $(, $($rest:tt)*)?) => {
+        $crate::__msgpack!(@arr $v [
+            $($e,)*
+            $crate::__msgpack!($v, false),
+        ] $($($rest)*)?)
+    };
+
+    (@arr $v:ident [$($e:expr,)*] [$($a:tt)*] $(, $($rest:tt)*)?) => {
+        $crate::__msgpack!(@arr $v [
+            $($e,)*
+            $crate::__msgpack!($v, [$($a)*]),
+        ] $($($rest)*)?)
+    };
+
+    (@arr $v:ident [$($e:expr,)*] {$($m:tt)*} $(, $($rest:tt)*)?) => {
+        $crate::__msgpack!(@arr $v [
+            $($e,)*
+            $crate::__msgpack!($v, {$($m)*}),
+        ] $($($rest)*)?)
+    };
+
+    (@arr $v:ident [$($e:expr,)*] bin($($b:tt)*) $(, $($rest:tt)*)?) => {
+        $crate::__msgpack!(@arr $v [
+            $($e,)*
+            $crate::__msgpack!($v, bin($($b)*)),
+        ] $($($rest)*)?)
+    };
+
+    (@arr $v:ident [$($e:expr,)*] ext($($b:tt)*) $(, $($rest:tt)*)?) => {
+        $crate::__msgpack!(@arr $v [
+            $($e,)*
+            $crate::__msgpack!($v, ext($($b)*)),
+        ] $($($rest)*)?)
+    };
+
+    (@arr $v:ident [$($e:expr,)*] $next:expr, $($rest:tt)*) => {
+        $crate::__msgpack!(@arr $v [
+            $($e,)*
+            $crate::__msgpack!($v, $next),
+        ] $($rest)*)
+    };
+
+    (@arr $v:ident [$($e:expr,)*] $last:expr) => {
+        $crate::__private::vec![
+            $($e,)*
+            $crate::__msgpack!($v, $last),
+        ]
+    };
+
+    (@arr $v:ident [$($e:expr,)*] $($rest:tt)+) => {
+        compile_error!("expected `,` between msgpack array items")
+    };
+
+    // -- map entries: (@map $v [entries,] (key tokens) rest..) -- //
+    // entry rules match the last key token and consume the following
+    // `,`, so an entry with a single token key costs one level
+
+    (@map $v:ident [$($e:expr,)*] ()) => {
+        $crate::__private::vec![$($e,)*]
+    };
+
+    (@map $v:ident [$($e:expr,)*] () : $($rest:tt)*) => {
+        compile_error!("missing msgpack map key")
+    };
+
+    (
+        @map $v:ident [$($e:expr,)*] ($($k:tt)*) $kl:tt : null
+        $(, $($rest:tt)*)?
+    ) => {
+        $crate::__msgpack!(@map $v [$($e,)* (
+            $crate::__msgpack!($v, $($k)* $kl),
+            $crate::__msgpack!($v, null),
+        ),] () $($($rest)*)?)
+    };
+
+    (
+        @map $v:ident [$($e:expr,)*] ($($k:tt)*) $kl:tt : true
+        $(, $($rest:tt)*)?
+    ) => {
+        $crate::__msgpack!(@map $v [$($e,)* (
+            $crate::__msgpack!($v, $($k)* $kl),
+            $crate::__msgpack!($v, true),
+        ),] () $($($rest)*)?)
+    };
+
+    (
+        @map $v:ident [$($e:expr,)*] ($($k:tt)*) $kl:tt : false
+        $(, $($rest:tt)*)?
+    ) => {
+        $crate::__msgpack!(@map $v [$($e,)* (
+            $crate::__msgpack!($v, $($k)* $kl),
+            $crate::__msgpack!($v, false),
+        ),] () $($($rest)*)?)
+    };
+
+    (
+        @map $v:ident [$($e:expr,)*] ($($k:tt)*) $kl:tt : [$($a:tt)*]
+        $(, $($rest:tt)*)?
+    ) => {
+        $crate::__msgpack!(@map $v [$($e,)* (
+            $crate::__msgpack!($v, $($k)* $kl),
+            $crate::__msgpack!($v, [$($a)*]),
+        ),] () $($($rest)*)?)
+    };
+
+    (
+        @map $v:ident [$($e:expr,)*] ($($k:tt)*) $kl:tt : {$($m:tt)*}
+        $(, $($rest:tt)*)?
+    ) => {
+        $crate::__msgpack!(@map $v [$($e,)* (
+            $crate::__msgpack!($v, $($k)* $kl),
+            $crate::__msgpack!($v, {$($m)*}),
+        ),] () $($($rest)*)?)
+    };
+
+    (
+        @map $v:ident [$($e:expr,)*] ($($k:tt)*) $kl:tt : bin($($b:tt)*)
+        $(, $($rest:tt)*)?
+    ) => {
+        $crate::__msgpack!(@map $v [$($e,)* (
+            $crate::__msgpack!($v, $($k)* $kl),
+            $crate::__msgpack!($v, bin($($b)*)),
+        ),] () $($($rest)*)?)
+    };
+
+    (
+        @map $v:ident [$($e:expr,)*] ($($k:tt)*) $kl:tt : ext($($b:tt)*)
+        $(, $($rest:tt)*)?
+    ) => {
+        $crate::__msgpack!(@map $v [$($e,)* (
+            $crate::__msgpack!($v, $($k)* $kl),
+            $crate::__msgpack!($v, ext($($b)*)),
+        ),] () $($($rest)*)?)
+    };
+
+    (
+        @map $v:ident [$($e:expr,)*] ($($k:tt)*) $kl:tt : $next:expr,
+        $($rest:tt)*
+    ) => {
+        $crate::__msgpack!(@map $v [$($e,)* (
+            $crate::__msgpack!($v, $($k)* $kl),
+            $crate::__msgpack!($v, $next),
+        ),] () $($rest)*)
+    };
+
+    (@map $v:ident [$($e:expr,)*] ($($k:tt)*) $kl:tt : $last:expr) => {
+        $crate::__private::vec![$($e,)* (
+            $crate::__msgpack!($v, $($k)* $kl),
+            $crate::__msgpack!($v, $last),
+        ),]
+    };
+
+    (@map $v:ident [$($e:expr,)*] ($($k:tt)*) $kl:tt : , $($rest:tt)*) => {
+        compile_error!("missing or invalid msgpack map value")
+    };
+
+    (
+        @map $v:ident [$($e:expr,)*] ($($k:tt)*) $kl:tt : $val:tt
+        $($rest:tt)+
+    ) => {
+        compile_error!("expected `,` between msgpack map entries")
+    };
+
+    (@map $v:ident [$($e:expr,)*] ($($k:tt)*) $kl:tt : $($rest:tt)*) => {
+        compile_error!("missing or invalid msgpack map value")
+    };
+
+    (@map $v:ident [$($e:expr,)*] ($($k:tt)*) , $($rest:tt)*) => {
+        compile_error!("expected `:` after msgpack map key")
+    };
+
+    // munch key tokens up to the last one before the `:`
+    (@map $v:ident [$($e:expr,)*] ($($k:tt)*) $t:tt $($rest:tt)*) => {
+        $crate::__msgpack!(@map $v [$($e,)*] ($($k)* $t) $($rest)*)
+    };
+
+    (@map $v:ident [$($e:expr,)*] ($($k:tt)+)) => {
+        compile_error!("expected `:` after msgpack map key")
+    };
+
+    // -- a single value -- //
+
+    ($v:ident, null) => {
+        $crate::value::$v::Nil
+    };
+
+    ($v:ident, true) => {
+        $crate::value::$v::Bool(true)
+    };
+
+    ($v:ident, false) => {
+        $crate::value::$v::Bool(false)
+    };
+
+    ($v:ident, [$($a:tt)*]) => {
+        $crate::value::$v::Arr($crate::__msgpack!(@arr $v [] $($a)*))
+    };
+
+    ($v:ident, {$($m:tt)*}) => {
+        $crate::value::$v::Map($crate::__msgpack!(@map $v [] () $($m)*))
+    };
+
+    ($v:ident, bin($data:expr $(,)?)) => {
+        $crate::value::$v::Bin(::core::convert::Into::into(&($data)[..]))
+    };
+
+    ($v:ident, ext($t:expr, $data:expr $(,)?)) => {
+        $crate::value::$v::Ext($t, ::core::convert::Into::into(&($data)[..]))
+    };
+
+    ($v:ident, $other:expr) => {
+        $crate::value::$v::from($other)
+    };
+}
@@ -102,10 +102,14 @@ impl<'lt> SerializerSync<'lt> {
         config: Config,
         consumer: C,
     ) -> Self {
+        let enc = msgpackin_core::encode::Encoder::with_float_policy(
+            config.float_policy,
+        )
+        .with_compat(config.compat);
         Self {
             config,
             con: consumer.into(),
-            enc: msgpackin_core::encode::Encoder::new(),
+            enc,
         }
     }
 
@@ -176,11 +180,10 @@ impl<'a, 'b, 'lt> ser::Serializer for &'b mut SerializerSyncRef<'a, 'lt> {
     }
 
     fn serialize_str(self, v: &str) -> Result<()> {
-        if v.as_bytes().len() > u32::MAX as usize {
+        if v.len() > u32::MAX as usize {
             return Err("str too long".into());
         }
-        self.con
-            .write(&self.enc.enc_str_len(v.as_bytes().len() as u32))?;
+        self.con.write(&self.enc.enc_str_len(v.len() as u32))?;
         self.con.write(v.as_bytes())
     }
 
@@ -229,6 +232,9 @@ impl<'a, 'b, 'lt> ser::Serializer for &'b mut SerializerSyncRef<'a, 'lt> {
         T: ?Sized + Serialize,
     {
         if name == EXT_STRUCT_NAME {
+            if self.enc.compat().is_legacy() {
+                return Err("ext is not supported in legacy compat mode".into());
+            }
             let mut buf = Vec::new();
             {
                 let SerializerSyncRef {
@@ -8,3 +8,6 @@ mod async_tests;
 
 #[cfg(feature = "serde")]
 mod serde_tests;
+
+#[cfg(feature = "serde_json")]
+mod json_tests;
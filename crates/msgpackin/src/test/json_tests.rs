@@ -0,0 +1,159 @@
+use crate::json::*;
+use crate::*;
+use serde_json::json;
+
+fn ext() -> Value {
+    Value::Ext(-42, b"hi".to_vec().into())
+}
+
+#[test]
+fn json_from_serde_json() {
+    let value = Value::from(json!({
+        "nil": null,
+        "arr": [true, -3, 18446744073709551615_u64, 0.5, "s"],
+    }));
+    assert_eq!(
+        Value::Map(vec![
+            (
+                "arr".into(),
+                Value::Arr(vec![
+                    true.into(),
+                    (-3).into(),
+                    u64::MAX.into(),
+                    0.5.into(),
+                    "s".into(),
+                ]),
+            ),
+            ("nil".into(), ().into()),
+        ]),
+        value,
+    );
+}
+
+#[test]
+fn json_round_trip() {
+    let j = json!({
+        "a": [1, -2, 3.5, null, false, "x", {}],
+        "b": { "c": [] },
+    });
+    let value = Value::from(&j);
+    assert_eq!(j, serde_json::Value::try_from(&value).unwrap());
+    assert_eq!(j, serde_json::Value::try_from(value.as_ref()).unwrap());
+    assert_eq!(j, serde_json::Value::try_from(value).unwrap());
+}
+
+#[test]
+fn json_bin_ext_policy() {
+    let bin = Value::Bin(b"hi".to_vec().into());
+    let with = |bin, ext| JsonPolicy {
+        bin,
+        ext,
+        ..Default::default()
+    };
+
+    assert_eq!(json!("aGk="), serde_json::Value::try_from(&bin).unwrap());
+    let policy = with(BinPolicy::Hex, ExtPolicy::Object);
+    assert_eq!(json!("6869"), bin.to_json_with(&policy).unwrap());
+    assert_eq!(
+        json!({ "type": -42, "data": "6869" }),
+        ext().to_json_with(&policy).unwrap(),
+    );
+    let policy = with(BinPolicy::Array, ExtPolicy::Error);
+    assert_eq!(json!([104, 105]), bin.to_json_with(&policy).unwrap());
+    assert!(ext().to_json_with(&policy).is_err());
+    let policy = with(BinPolicy::Error, ExtPolicy::Object);
+    assert!(bin.to_json_with(&policy).is_err());
+    assert_eq!(
+        json!({ "type": -42, "data": "aGk=" }),
+        ext().to_json_with(&policy).unwrap(),
+    );
+}
+
+#[test]
+fn json_key_policy() {
+    let value = Value::Map(vec![
+        (7.into(), 1.into()),
+        (Value::Arr(vec![true.into(), ().into()]), 2.into()),
+        ("s".into(), 3.into()),
+    ]);
+    assert_eq!(
+        json!({ "7": 1, "[true,null]": 2, "s": 3 }),
+        serde_json::Value::try_from(&value).unwrap(),
+    );
+    let policy = JsonPolicy {
+        key: KeyPolicy::Error,
+        ..Default::default()
+    };
+    assert!(value.to_json_with(&policy).is_err());
+
+    let bad = Value::Map(vec![(
+        Value::Str(value::Utf8Str(b"\xff".to_vec().into())),
+        ().into(),
+    )]);
+    assert!(matches!(
+        serde_json::Value::try_from(&bad),
+        Err(Error::EInvalidUtf8)
+    ));
+}
+
+#[test]
+fn json_big_int_policy() {
+    let safe = Value::from(MAX_SAFE_INTEGER);
+    let big = Value::from(u64::MAX);
+    let neg = Value::from(i64::MIN);
+    let with = |big_int| JsonPolicy {
+        big_int,
+        ..Default::default()
+    };
+
+    for policy in [
+        BigIntPolicy::Number,
+        BigIntPolicy::String,
+        BigIntPolicy::Float,
+        BigIntPolicy::Error,
+    ] {
+        assert_eq!(
+            json!(MAX_SAFE_INTEGER),
+            safe.to_json_with(&with(policy)).unwrap(),
+        );
+    }
+    let policy = with(BigIntPolicy::Number);
+    assert_eq!(json!(u64::MAX), big.to_json_with(&policy).unwrap());
+    assert_eq!(json!(i64::MIN), neg.to_json_with(&policy).unwrap());
+    let policy = with(BigIntPolicy::String);
+    assert_eq!(
+        json!("18446744073709551615"),
+        big.to_json_with(&policy).unwrap(),
+    );
+    assert_eq!(
+        json!("-9223372036854775808"),
+        neg.to_json_with(&policy).unwrap(),
+    );
+    let policy = with(BigIntPolicy::Float);
+    assert_eq!(json!(u64::MAX as f64), big.to_json_with(&policy).unwrap());
+    assert!(big.to_json_with(&with(BigIntPolicy::Error)).is_err());
+}
+
+#[test]
+fn json_non_finite_policy() {
+    let value = Value::Arr(vec![
+        f64::NAN.into(),
+        f32::INFINITY.into(),
+        f64::NEG_INFINITY.into(),
+        1.5_f32.into(),
+    ]);
+    let with = |non_finite| JsonPolicy {
+        non_finite,
+        ..Default::default()
+    };
+
+    assert_eq!(
+        json!([null, null, null, 1.5]),
+        value.to_json_with(&with(NonFinite::Null)).unwrap(),
+    );
+    assert_eq!(
+        json!(["NaN", "Infinity", "-Infinity", 1.5]),
+        value.to_json_with(&with(NonFinite::Str)).unwrap(),
+    );
+    assert!(value.to_json_with(&with(NonFinite::Error)).is_err());
+}
@@ -1,6 +1,7 @@
 use crate::*;
 
 #[test]
+#[allow(clippy::approx_constant)]
 fn no_std_encode_decode_demo() {
     let expect = Value::Map(vec![
         ("nil".into(), ().into()),
@@ -16,3 +17,106 @@ fn no_std_encode_decode_demo() {
     let decoded = ValueRef::from_ref(&encoded).unwrap();
     assert_eq!(expect, decoded);
 }
+
+#[test]
+fn no_std_msgpack_macro() {
+    let name = String::from("bob");
+    let data = vec![1_u8, 2];
+    let value = msgpack!({
+        "nil": null,
+        "bool": [true, false],
+        "num": [1, -42, u64::MAX, 3.5],
+        "bin": bin(b"bin-data"),
+        "ext": ext(-42, b"ext-data"),
+        "nested": { "arr": [[], {}, [null]], "data": bin(data) },
+        "name": &name,
+        "expr": (1 + 2) * 3,
+        7: "int key",
+        -1: null,
+        bin([0xff]): "bin key",
+        [1, 2]: {},
+    });
+    let expect = Value::Map(vec![
+        ("nil".into(), ().into()),
+        ("bool".into(), Value::Arr(vec![true.into(), false.into()])),
+        (
+            "num".into(),
+            Value::Arr(vec![
+                1.into(),
+                (-42).into(),
+                u64::MAX.into(),
+                3.5.into(),
+            ]),
+        ),
+        ("bin".into(), Value::Bin(b"bin-data".to_vec().into())),
+        ("ext".into(), Value::Ext(-42, b"ext-data".to_vec().into())),
+        (
+            "nested".into(),
+            Value::Map(vec![
+                (
+                    "arr".into(),
+                    Value::Arr(vec![
+                        Value::Arr(vec![]),
+                        Value::Map(vec![]),
+                        Value::Arr(vec![().into()]),
+                    ]),
+                ),
+                ("data".into(), Value::Bin(data.clone().into())),
+            ]),
+        ),
+        ("name".into(), "bob".into()),
+        ("expr".into(), 9.into()),
+        (7.into(), "int key".into()),
+        ((-1).into(), ().into()),
+        (Value::Bin(vec![0xff].into()), "bin key".into()),
+        (Value::Arr(vec![1.into(), 2.into()]), Value::Map(vec![])),
+    ]);
+    assert_eq!(expect, value);
+
+    let value_ref = msgpack_ref!({
+        "nil": null,
+        "bool": [true, false],
+        "num": [1, -42, u64::MAX, 3.5],
+        "bin": bin(b"bin-data"),
+        "ext": ext(-42, b"ext-data"),
+        "nested": { "arr": [[], {}, [null]], "data": bin(data) },
+        "name": &name,
+        "expr": (1 + 2) * 3,
+        7: "int key",
+        -1: null,
+        bin([0xff]): "bin key",
+        [1, 2]: {},
+    });
+    assert_eq!(expect, value_ref);
+    assert_eq!(Some("bob"), value_ref["name"].as_str());
+
+    assert_eq!(Value::Nil, msgpack!(null));
+    assert_eq!(Value::Arr(vec![1.into()]), msgpack!([1,]));
+    assert_eq!(
+        Value::Arr(vec![expect.clone()]),
+        msgpack_ref!([ValueRef::from(&expect)]),
+    );
+    assert_eq!(Value::Arr(vec![expect.clone()]), msgpack!([expect]));
+}
+
+#[test]
+fn no_std_msgpack_macro_large() {
+    // each item / entry costs one level of macro recursion, so literals
+    // close to the default recursion limit still expand
+    let value = msgpack!([
+        0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15, 16, 17, 18, 19,
+        20, 21, 22, 23, 24, 25, 26, 27, 28, 29, 30, 31, 32, 33, 34, 35, 36, 37,
+        38, 39, 40, 41, 42, 43, 44, 45, 46, 47, 48, 49, 50, 51, 52, 53, 54, 55,
+        56, 57, 58, 59, 60, 61, 62, 63, 64, 65, 66, 67, 68, 69, 70, 71, 72, 73,
+        74, 75, 76, 77, 78, 79, 80, 81, 82, 83, 84, 85, 86, 87, 88, 89, 90, 91,
+        92, 93, 94, 95, 96, 97, 98, 99, 100, 101, 102, 103, 104, 105, 106, 107,
+        108, 109, 110, 111, 112, 113, 114, 115, 116, 117, 118, 119
+    ]);
+    assert_eq!(Value::Arr((0..120).map(Value::from).collect()), value);
+
+    let value = msgpack_ref!({0: null, 1: null, 2: null, 3: null, 4: null, 5: null, 6: null, 7: null, 8: null, 9: null, 10: null, 11: null, 12: null, 13: null, 14: null, 15: null, 16: null, 17: null, 18: null, 19: null, 20: null, 21: null, 22: null, 23: null, 24: null, 25: null, 26: null, 27: null, 28: null, 29: null, 30: null, 31: null, 32: null, 33: null, 34: null, 35: null, 36: null, 37: null, 38: null, 39: null, 40: null, 41: null, 42: null, 43: null, 44: null, 45: null, 46: null, 47: null, 48: null, 49: null, 50: null, 51: null, 52: null, 53: null, 54: null, 55: null, 56: null, 57: null, 58: null, 59: null, 60: null, 61: null, 62: null, 63: null, 64: null, 65: null, 66: null, 67: null, 68: null, 69: null, 70: null, 71: null, 72: null, 73: null, 74: null, 75: null, 76: null, 77: null, 78: null, 79: null, 80: null, 81: null, 82: null, 83: null, 84: null, 85: null, 86: null, 87: null, 88: null, 89: null, 90: null, 91: null, 92: null, 93: null, 94: null, 95: null, 96: null, 97: null, 98: null, 99: null, 100: null, 101: null, 102: null, 103: null, 104: null, 105: null, 106: null, 107: null, 108: null, 109: null, 110: null, 111: null, 112: null, 113: null, 114: null, 115: null, 116: null, 117: null, 118: null, 119: null});
+    assert_eq!(
+        Value::Map((0..120).map(|i| (i.into(), Value::Nil)).collect()),
+        value,
+    );
+}
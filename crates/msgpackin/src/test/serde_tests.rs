@@ -1,6 +1,7 @@
 use crate::*;
 
 #[test]
+#[allow(clippy::approx_constant)]
 fn serde_encode_decode_demo() {
     #[derive(Debug, PartialEq, serde::Serialize, serde::Deserialize)]
     struct X {
@@ -291,3 +292,125 @@ fn can_de_enum() {
         r
     );
 }
+
+#[test]
+fn can_ser_float_width() {
+    let enc = to_bytes(&(42.0_f64, 1.5_f32)).unwrap();
+    assert_eq!(
+        &[0x92, 0xcb, 0x40, 0x45, 0, 0, 0, 0, 0, 0, 0xca, 0x3f, 0xc0, 0, 0],
+        enc.as_slice(),
+    );
+    let dec: (f64, f32) = from_ref(enc.as_slice()).unwrap();
+    assert_eq!((42.0, 1.5), dec);
+}
+
+/// yields its data one byte at a time
+struct Trickle<'a>(&'a [u8]);
+
+impl producer::AsProducerSync for Trickle<'_> {
+    fn read_next(&mut self, _len_hint: u32) -> Result<Option<&[u8]>> {
+        if self.0.is_empty() {
+            return Ok(None);
+        }
+        let (byte, rest) = self.0.split_at(1);
+        self.0 = rest;
+        Ok(Some(byte))
+    }
+}
+
+fn transcode_fixture() -> Value {
+    Value::Map(vec![
+        ("nil".into(), ().into()),
+        ("arr".into(), Value::Arr(vec![true.into(), (-42).into()])),
+        ("big".into(), u64::MAX.into()),
+        ("float".into(), 1.5_f32.into()),
+        ("str".into(), "x".repeat(300).into()),
+        ("bin".into(), Value::Bin(vec![7; 40].into())),
+        ("empty".into(), "".into()),
+        ("ext".into(), Value::Ext(-42, b"ext-data".to_vec().into())),
+        (42.into(), Value::Map(vec![])),
+    ])
+}
+
+#[test]
+fn can_transcode() {
+    let expect = transcode_fixture();
+    let enc = expect.to_bytes().unwrap();
+
+    let mut out = Vec::new();
+    let mut ser = ser::SerializerSync::new(Config::default(), &mut out);
+    transcode(enc.as_slice(), &mut ser.as_ref()).unwrap();
+    drop(ser);
+    assert_eq!(enc, out);
+
+    let mut out = Vec::new();
+    let mut ser = ser::SerializerSync::new(Config::default(), &mut out);
+    let p: producer::DynProducerSync = Box::new(Trickle(&enc));
+    transcode(p, &mut ser.as_ref()).unwrap();
+    drop(ser);
+    assert_eq!(enc, out);
+}
+
+#[test]
+fn can_transcode_sequence() {
+    let mut enc = transcode_fixture().to_bytes().unwrap();
+    enc.extend(Value::from("two").to_bytes().unwrap());
+    let p: producer::DynProducerSync = Box::new(Trickle(&enc));
+    let mut t = transcode::Transcoder::new(p, &Config::default());
+    let mut out = Vec::new();
+    let mut ser = ser::SerializerSync::new(Config::default(), &mut out);
+    let mut count = 0;
+    while t.next(&mut ser.as_ref()).unwrap().is_some() {
+        count += 1;
+    }
+    drop(ser);
+    assert_eq!(2, count);
+    assert_eq!(enc, out);
+}
+
+#[test]
+fn can_transcode_empty_payload_at_eof() {
+    for enc in [&[0xa0][..], &[0xc4, 0x00], &[0xc7, 0x00, 0x05]] {
+        let mut out = Vec::new();
+        let mut ser = ser::SerializerSync::new(Config::default(), &mut out);
+        transcode(enc, &mut ser.as_ref()).unwrap();
+        let p: producer::DynProducerSync = Box::new(Trickle(enc));
+        transcode(p, &mut ser.as_ref()).unwrap();
+        drop(ser);
+        assert_eq!([enc, enc].concat(), out);
+    }
+}
+
+#[test]
+fn transcode_errors() {
+    let enc = transcode_fixture().to_bytes().unwrap();
+    let mut out = Vec::new();
+    let mut ser = ser::SerializerSync::new(Config::default(), &mut out);
+    assert!(transcode(&enc[..enc.len() - 1], &mut ser.as_ref()).is_err());
+    assert!(transcode(&[][..], &mut ser.as_ref()).is_err());
+
+    let config = Config {
+        max_depth: 1,
+        ..Default::default()
+    };
+    let nested = Value::Arr(vec![Value::Arr(vec![])]).to_bytes().unwrap();
+    assert!(
+        transcode_config(nested.as_slice(), &mut ser.as_ref(), &config)
+            .is_err()
+    );
+
+    let config = Config {
+        validate_utf8: true,
+        ..Default::default()
+    };
+    match transcode_config(
+        &[0x92, 0xa2, b'o', 0xff][..],
+        &mut ser.as_ref(),
+        &config,
+    ) {
+        Err(Error::EOther(e)) => {
+            assert!(e.contains("EInvalidUtf8At(offset 3)"))
+        }
+        oth => panic!("{:?}", oth),
+    }
+}
@@ -14,3 +14,38 @@ fn std_encode_decode_demo() {
     let decoded = Value::from_sync(reader).unwrap();
     assert_eq!(expect, decoded);
 }
+
+/// reader yielding a single byte per read, splitting every payload
+struct OneByteReader<'a>(&'a [u8]);
+
+impl std::io::Read for OneByteReader<'_> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        if self.0.is_empty() || buf.is_empty() {
+            return Ok(0);
+        }
+        buf[0] = self.0[0];
+        self.0 = &self.0[1..];
+        Ok(1)
+    }
+}
+
+#[test]
+fn std_validate_utf8_chunked() {
+    let config = Config {
+        validate_utf8: true,
+        ..Default::default()
+    };
+
+    let expect = Value::Arr(vec!["caf\u{e9}".into(), "\u{1f600}".into()]);
+    let buf = expect.to_bytes().unwrap();
+    let reader: Box<dyn std::io::Read> = Box::new(OneByteReader(&buf));
+    assert_eq!(expect, Value::from_sync_config(reader, &config).unwrap());
+
+    // [ "ok", "ab\xf0\x9f(" ]
+    let buf = [0x92, 0xa2, b'o', b'k', 0xa5, b'a', b'b', 0xf0, 0x9f, b'('];
+    let reader: Box<dyn std::io::Read> = Box::new(OneByteReader(&buf));
+    match Value::from_sync_config(reader, &config) {
+        Err(Error::EInvalidUtf8At(7)) => (),
+        oth => panic!("unexpected: {:?}", oth),
+    }
+}
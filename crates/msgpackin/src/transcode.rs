@@ -0,0 +1,311 @@
+//! `feature=serde` streaming transcoding from msgpack into any serde
+//! Serializer
+//!
+//! Msgpack tokens are read from a `DynProducerSync` one at a time and
+//! forwarded directly to the serializer, no `Value` tree is built.
+//! Memory use is bounded by the producer's chunk size plus the largest
+//! single str / bin / ext payload, regardless of the size of the
+//! document, so arbitrarily large msgpack dumps can be converted to
+//! JSON, YAML, CBOR, etc.
+//!
+//! Msgpack specific types are forwarded the same way `ValueRef`
+//! serializes them: bin data via `serialize_bytes`, ext data as the
+//! newtype struct `_ExtStruct((i8, bytes))`, and str data that is not
+//! valid utf8 (when not rejected by `Config::validate_utf8`) as bytes.
+//!
+//! ```
+//! # #[cfg(feature = "serde_json")]
+//! # {
+//! use msgpackin::*;
+//! let data = Value::Map(vec![
+//!     ("a".into(), Value::Arr(vec![1.into(), true.into()])),
+//!     ("b".into(), ().into()),
+//! ])
+//! .to_bytes()
+//! .unwrap();
+//!
+//! let mut out = Vec::new();
+//! transcode(data.as_slice(), &mut serde_json::Serializer::new(&mut out))
+//!     .unwrap();
+//! assert_eq!(r#"{"a":[1,true],"b":null}"#, String::from_utf8(out).unwrap());
+//! # }
+//! ```
+
+use crate::lib::core::cell::RefCell;
+use crate::producer::*;
+use crate::value::ValueRef;
+use crate::*;
+use msgpackin_core::decode::{Decoder, LenType, Token};
+use serde::ser::{self, SerializeMap, SerializeSeq};
+
+/// Transcode a single msgpack value read from anything that can be
+/// converted into a `DynProducerSync`, e.g. `Read`, into `serializer`,
+/// using the default `Config`.
+/// Any data following the first value is not transcoded
+pub fn transcode<'prod, P, S>(
+    p: P,
+    serializer: S,
+) -> result::Result<S::Ok, S::Error>
+where
+    P: Into<DynProducerSync<'prod>>,
+    S: serde::Serializer,
+{
+    transcode_config(p, serializer, &Config::default())
+}
+
+/// As `transcode`, but reading according to `config`: containers nested
+/// deeper than `max_depth` are rejected, str payloads that are not valid
+/// utf8 are rejected if `validate_utf8` is set, and `compat` selects
+/// whether legacy raw data is decoded as str or bin.
+/// Any data following the first value is not transcoded
+pub fn transcode_config<'prod, P, S>(
+    p: P,
+    serializer: S,
+    config: &Config,
+) -> result::Result<S::Ok, S::Error>
+where
+    P: Into<DynProducerSync<'prod>>,
+    S: serde::Serializer,
+{
+    match Transcoder::new(p, config).next(serializer)? {
+        Some(ok) => Ok(ok),
+        None => Err(ser::Error::custom(eof())),
+    }
+}
+
+/// Streaming transcoder for input containing a sequence of msgpack
+/// values, each of which is forwarded to a separate serializer
+///
+/// ```
+/// # #[cfg(feature = "serde_json")]
+/// # {
+/// use msgpackin::*;
+/// let mut data = Value::from("one").to_bytes().unwrap();
+/// data.extend(Value::from(2).to_bytes().unwrap());
+///
+/// let config = types::Config::default();
+/// let mut t = transcode::Transcoder::new(data.as_slice(), &config);
+/// let mut lines = Vec::new();
+/// while let Some(()) =
+///     t.next(&mut serde_json::Serializer::new(&mut lines)).unwrap()
+/// {
+///     lines.push(b'\n');
+/// }
+/// assert_eq!("\"one\"\n2\n", String::from_utf8(lines).unwrap());
+/// # }
+/// ```
+pub struct Transcoder<'prod> {
+    stream: RefCell<Stream<'prod>>,
+}
+
+impl<'prod> Transcoder<'prod> {
+    /// Construct a new Transcoder reading from anything that can be
+    /// converted into a `DynProducerSync`, e.g. `Read`
+    pub fn new<P>(p: P, config: &Config) -> Self
+    where
+        P: Into<DynProducerSync<'prod>>,
+    {
+        Self {
+            stream: RefCell::new(Stream {
+                prod: p.into(),
+                dec: Decoder::with_compat(config.compat),
+                buf: Vec::new(),
+                pos: 0,
+                consumed: 0,
+                depth: 0,
+                max_depth: config.max_depth,
+                validate_utf8: config.validate_utf8,
+            }),
+        }
+    }
+
+    /// Transcode the next msgpack value into `serializer`.
+    /// Returns `Ok(None)` if the input ended cleanly before another value
+    pub fn next<S>(
+        &mut self,
+        serializer: S,
+    ) -> result::Result<Option<S::Ok>, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        let token = self
+            .stream
+            .get_mut()
+            .next_token()
+            .map_err(ser::Error::custom)?;
+        match token {
+            None => Ok(None),
+            Some(token) => Next(&self.stream)
+                .serialize_token(token, serializer)
+                .map(Some),
+        }
+    }
+}
+
+fn eof() -> Error {
+    Error::EDecode {
+        expected: "Marker".into(),
+        got: "UnexpectedEOF".into(),
+    }
+}
+
+struct Stream<'prod> {
+    prod: DynProducerSync<'prod>,
+    dec: Decoder,
+    /// the current chunk of input, and our position in it
+    buf: Vec<u8>,
+    pos: usize,
+    /// bytes of input read before the current chunk
+    consumed: usize,
+    depth: usize,
+    max_depth: usize,
+    validate_utf8: bool,
+}
+
+impl Stream<'_> {
+    /// read the next complete token, `None` if the input ended
+    /// before any bytes of it were read
+    fn next_token(&mut self) -> Result<Option<OwnedToken>> {
+        let mut started = false;
+        let mut len_type = LenType::Bin;
+        let mut payload_at = 0;
+        let mut data = Vec::new();
+        let mut empty_payload = false;
+        loop {
+            // a zero-length payload completes without reading more input
+            if self.pos >= self.buf.len() && !empty_payload {
+                self.consumed += self.buf.len();
+                self.buf.clear();
+                self.pos = 0;
+                match self.prod.read_next(self.dec.next_bytes_min())? {
+                    Some(chunk) => self.buf.extend_from_slice(chunk),
+                    None if started => return Err(eof()),
+                    None => return Ok(None),
+                }
+                continue;
+            }
+            started = true;
+            let mut iter = self.dec.parse(&self.buf[self.pos..]);
+            let token = iter.next();
+            self.pos += iter.cursor();
+            let token = match token {
+                // the rest of the chunk was a partial header
+                None => {
+                    self.pos = self.buf.len();
+                    continue;
+                }
+                Some(token) => token,
+            };
+            return Ok(Some(match token {
+                Token::Nil => OwnedToken::Nil,
+                Token::Bool(b) => OwnedToken::Bool(b),
+                Token::Num(n) => OwnedToken::Num(n),
+                Token::Len(LenType::Arr, l) => OwnedToken::Arr(l),
+                Token::Len(LenType::Map, l) => OwnedToken::Map(l),
+                Token::Len(t, l) => {
+                    len_type = t;
+                    payload_at = self.consumed + self.pos;
+                    empty_payload = l == 0;
+                    continue;
+                }
+                Token::BinCont(d, _) => {
+                    data.extend_from_slice(d);
+                    continue;
+                }
+                Token::Bin(d) => {
+                    data.extend_from_slice(d);
+                    let data = data.into_boxed_slice();
+                    match len_type {
+                        LenType::Str => {
+                            if self.validate_utf8 {
+                                if let Err(e) = core::str::from_utf8(&data) {
+                                    return Err(Error::EInvalidUtf8At(
+                                        payload_at + e.valid_up_to(),
+                                    ));
+                                }
+                            }
+                            OwnedToken::Str(data)
+                        }
+                        LenType::Ext(t) => OwnedToken::Ext(t, data),
+                        _ => OwnedToken::Bin(data),
+                    }
+                }
+            }));
+        }
+    }
+}
+
+/// serializes the next value read from the stream
+struct Next<'a, 'prod>(&'a RefCell<Stream<'prod>>);
+
+impl Next<'_, '_> {
+    fn serialize_token<S>(
+        &self,
+        token: OwnedToken,
+        serializer: S,
+    ) -> result::Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        match token {
+            OwnedToken::Nil => serializer.serialize_unit(),
+            OwnedToken::Bool(b) => serializer.serialize_bool(b),
+            OwnedToken::Num(Num::Unsigned(u)) => serializer.serialize_u64(u),
+            OwnedToken::Num(Num::Signed(i)) => serializer.serialize_i64(i),
+            OwnedToken::Num(Num::F32(f)) => serializer.serialize_f32(f),
+            OwnedToken::Num(Num::F64(f)) => serializer.serialize_f64(f),
+            OwnedToken::Bin(data) => serializer.serialize_bytes(&data),
+            OwnedToken::Str(data) => match core::str::from_utf8(&data) {
+                Ok(s) => serializer.serialize_str(s),
+                Err(_) => serializer.serialize_bytes(&data),
+            },
+            OwnedToken::Ext(t, data) => serializer.serialize_newtype_struct(
+                EXT_STRUCT_NAME,
+                &(t, ValueRef::Bin(&data)),
+            ),
+            OwnedToken::Arr(len) => {
+                self.enter()?;
+                let mut seq = serializer.serialize_seq(Some(len as usize))?;
+                for _ in 0..len {
+                    seq.serialize_element(self)?;
+                }
+                self.0.borrow_mut().depth -= 1;
+                seq.end()
+            }
+            OwnedToken::Map(len) => {
+                self.enter()?;
+                let mut map = serializer.serialize_map(Some(len as usize))?;
+                for _ in 0..len {
+                    map.serialize_key(self)?;
+                    map.serialize_value(self)?;
+                }
+                self.0.borrow_mut().depth -= 1;
+                map.end()
+            }
+        }
+    }
+
+    fn enter<E: ser::Error>(&self) -> result::Result<(), E> {
+        let mut stream = self.0.borrow_mut();
+        if stream.depth >= stream.max_depth {
+            return Err(E::custom("max_depth exceeded"));
+        }
+        stream.depth += 1;
+        Ok(())
+    }
+}
+
+impl serde::Serialize for Next<'_, '_> {
+    fn serialize<S>(&self, serializer: S) -> result::Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        let token = self
+            .0
+            .borrow_mut()
+            .next_token()
+            .map_err(ser::Error::custom)?
+            .ok_or_else(|| ser::Error::custom(eof()))?;
+        self.serialize_token(token, serializer)
+    }
+}
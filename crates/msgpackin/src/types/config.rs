@@ -1,13 +1,41 @@
+use crate::*;
+
 /// Msgpackin config for encoders / decoders
 #[non_exhaustive]
 #[derive(Debug, Clone)]
 pub struct Config {
     /// maximum container depth when encoding / decoding
     pub max_depth: usize,
+
+    /// if true, `ValueRef::from_ref` will reject data that is not
+    /// canonically encoded (see `msgpackin_core::canonical`)
+    pub canonical: bool,
+
+    /// how floats are written when encoding. The default
+    /// `FloatPolicy::Preserve` keeps the float width, negative zero,
+    /// and NaN payloads. `FloatPolicy::Compact` writes floats in their
+    /// most compact lossless form (e.g. `42.0` as the integer `42`)
+    pub float_policy: FloatPolicy,
+
+    /// the msgpack spec revision to target. The legacy modes write
+    /// pre-2013 "raw" strings for both str and bin, reject ext, and
+    /// decode raw data as either str or bin (see `Compat`)
+    pub compat: Compat,
+
+    /// if true, str payloads are utf8 validated as they are decoded,
+    /// rather than when they are accessed, and invalid data is
+    /// rejected with `Error::EInvalidUtf8At`
+    pub validate_utf8: bool,
 }
 
 impl Default for Config {
     fn default() -> Self {
-        Self { max_depth: 1024 }
+        Self {
+            max_depth: 1024,
+            canonical: false,
+            float_policy: FloatPolicy::default(),
+            compat: Compat::default(),
+            validate_utf8: false,
+        }
     }
 }
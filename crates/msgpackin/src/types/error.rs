@@ -5,6 +5,10 @@ pub enum Error {
     /// InvalidUtf8 data
     EInvalidUtf8,
 
+    /// InvalidUtf8 data found while decoding, at this byte offset
+    /// into the encoded input (see `Config::validate_utf8`)
+    EInvalidUtf8At(usize),
+
     /// Decode Error
     EDecode {
         /// What was expected during decode
@@ -14,6 +18,9 @@ pub enum Error {
         got: String,
     },
 
+    /// Data was not canonically encoded
+    ENonCanonical(msgpackin_core::canonical::Violation),
+
     /// (`feature = "std"`)
     /// std::io::Error
     #[cfg(feature = "std")]
@@ -27,9 +34,19 @@ impl fmt::Debug for Error {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
             Error::EInvalidUtf8 => f.write_str("EInvalidUtf8"),
+            Error::EInvalidUtf8At(offset) => {
+                write!(f, "EInvalidUtf8At(offset {})", offset)
+            }
             Error::EDecode { expected, got } => {
                 write!(f, "EDecode(expected: {}, got: {})", expected, got)
             }
+            Error::ENonCanonical(v) => {
+                write!(
+                    f,
+                    "ENonCanonical({:?} at offset {})",
+                    v.reason, v.offset
+                )
+            }
             #[cfg(feature = "std")]
             Error::EStdIo(e) => {
                 write!(f, "EStdIo({:?})", e)
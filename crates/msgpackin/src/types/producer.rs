@@ -269,38 +269,73 @@ macro_rules! mk_decode_owned {
             out: &'func mut Vec<OwnedToken>,
             dec: &'func mut msgpackin_core::decode::Decoder,
             prod: &'func mut $($prod)*,
-            _config: &'func Config,
+            config: &'func Config,
         ) -> $($ret)* {$wrap! {
+            use msgpackin_core::utf8::Utf8Validator;
             let mut len_type = msgpackin_core::decode::LenType::Bin;
             let mut buf = Vec::new();
-            while let Some(data) = prod.read_next(dec.next_bytes_min())$($await)*? {
-                for token in dec.parse(data) {
+            // bytes of input parsed before the current chunk
+            let mut consumed = 0;
+            // str payload start offset and validator, if validating
+            let mut utf8: Option<(usize, Utf8Validator)> = None;
+            while let Some(input) = prod.read_next(dec.next_bytes_min())$($await)*? {
+                let mut iter = dec.parse(input).value_tokens();
+                while let Some(token) = iter.next() {
                     use msgpackin_core::decode::LenType;
-                    use msgpackin_core::decode::Token::*;
+                    use msgpackin_core::decode::ValueToken::*;
                     match token {
-                        Len(LenType::Arr, len) => out.push(OwnedToken::Arr(len)),
-                        Len(LenType::Map, len) => out.push(OwnedToken::Map(len)),
-                        Len(t, _len) => len_type = t,
+                        Arr(len) => out.push(OwnedToken::Arr(len)),
+                        Map(len) => out.push(OwnedToken::Map(len)),
                         Nil => out.push(OwnedToken::Nil),
                         Bool(b) => out.push(OwnedToken::Bool(b)),
                         Num(n) => out.push(OwnedToken::Num(n)),
-                        BinCont(data, _) => buf.extend_from_slice(data),
-                        Bin(data) => {
-                            let owned_data = if buf.is_empty() {
-                                data.to_vec().into_boxed_slice()
-                            } else {
-                                buf.extend_from_slice(data);
-                                mem::take(&mut buf).into_boxed_slice()
-                            };
+                        Str(s) => out.push(OwnedToken::Str(s.as_bytes().into())),
+                        InvalidStr(data) => {
+                            if config.validate_utf8 {
+                                let start = consumed + iter.cursor() - data.len();
+                                let mut v = Utf8Validator::new();
+                                let e = v.push(data).unwrap_err();
+                                return Err(Error::EInvalidUtf8At(start + e.offset));
+                            }
+                            out.push(OwnedToken::Str(data.into()))
+                        }
+                        Bin(data) => out.push(OwnedToken::Bin(data.into())),
+                        Ext(t, data) => out.push(OwnedToken::Ext(t, data.into())),
+                        PayloadStart(t, _len) => {
+                            len_type = t;
+                            if t == LenType::Str && config.validate_utf8 {
+                                utf8 = Some((0, Utf8Validator::new()));
+                            }
+                        }
+                        Chunk(data, remaining) => {
+                            if let Some((start, v)) = &mut utf8 {
+                                if v.is_empty() {
+                                    *start = consumed + iter.cursor() - data.len();
+                                }
+                                let mut res = v.push(data);
+                                if remaining == 0 {
+                                    res = res.and_then(|_| v.finish());
+                                }
+                                if let Err(e) = res {
+                                    return Err(Error::EInvalidUtf8At(*start + e.offset));
+                                }
+                            }
+                            buf.extend_from_slice(data);
+                            if remaining > 0 {
+                                continue;
+                            }
+                            utf8 = None;
+                            let owned_data = mem::take(&mut buf).into_boxed_slice();
                             match len_type {
                                 LenType::Bin => out.push(OwnedToken::Bin(owned_data)),
                                 LenType::Str => out.push(OwnedToken::Str(owned_data)),
                                 LenType::Ext(t) => out.push(OwnedToken::Ext(t, owned_data)),
-                                _ => unreachable!(), // is it?
+                                _ => unreachable!(),
                             }
                         }
                     }
                 }
+                consumed += input.len();
             }
             Ok(())
         }}
@@ -1,6 +1,7 @@
 //! MessagePack Rust Value and ValueRef types
 
 use crate::consumer::*;
+use crate::lib::core::ops::{Index, IndexMut};
 use crate::producer::*;
 use crate::*;
 
@@ -113,7 +114,7 @@ impl<'a> From<Cow<'a, str>> for Utf8Str {
 
 impl Utf8Str {
     /// Get a Utf8StrRef from this instance
-    pub fn as_ref(&self) -> Utf8StrRef {
+    pub fn as_ref(&self) -> Utf8StrRef<'_> {
         self.into()
     }
 
@@ -422,9 +423,174 @@ fn priv_decode<'func, 'prod>(
     }
 }
 
+/// accessors with identical signatures on Value and ValueRef
+macro_rules! common_accessors {
+    ($t:ident) => {
+        /// The name of this value's type, e.g. `"str"` or `"map"`
+        pub fn type_name(&self) -> &'static str {
+            match self {
+                $t::Nil => "nil",
+                $t::Bool(_) => "bool",
+                $t::Num(_) => "num",
+                $t::Bin(_) => "bin",
+                $t::Str(_) => "str",
+                $t::Arr(_) => "arr",
+                $t::Map(_) => "map",
+                $t::Ext(_, _) => "ext",
+            }
+        }
+
+        /// Returns true if this is a `Nil` value
+        pub fn is_nil(&self) -> bool {
+            matches!(self, $t::Nil)
+        }
+
+        /// Returns true if this is a `Bool` value
+        pub fn is_bool(&self) -> bool {
+            matches!(self, $t::Bool(_))
+        }
+
+        /// Returns true if this is a `Num` value
+        pub fn is_num(&self) -> bool {
+            matches!(self, $t::Num(_))
+        }
+
+        /// Returns true if this is a `Bin` value
+        pub fn is_bin(&self) -> bool {
+            matches!(self, $t::Bin(_))
+        }
+
+        /// Returns true if this is a `Str` value
+        /// (which may not be valid utf8)
+        pub fn is_str(&self) -> bool {
+            matches!(self, $t::Str(_))
+        }
+
+        /// Returns true if this is an `Arr` value
+        pub fn is_array(&self) -> bool {
+            matches!(self, $t::Arr(_))
+        }
+
+        /// Returns true if this is a `Map` value
+        pub fn is_map(&self) -> bool {
+            matches!(self, $t::Map(_))
+        }
+
+        /// Returns true if this is an `Ext` value
+        pub fn is_ext(&self) -> bool {
+            matches!(self, $t::Ext(_, _))
+        }
+
+        /// Get the boolean if this is a `Bool` value
+        pub fn as_bool(&self) -> Option<bool> {
+            match self {
+                $t::Bool(b) => Some(*b),
+                _ => None,
+            }
+        }
+
+        /// Get the number if this is a `Num` value
+        pub fn as_num(&self) -> Option<Num> {
+            match self {
+                $t::Num(n) => Some(*n),
+                _ => None,
+            }
+        }
+
+        /// Get the number as a `u64`, if this is a `Num` value that
+        /// converts losslessly (see `Num::fits`)
+        pub fn as_u64(&self) -> Option<u64> {
+            self.as_num().filter(|n| n.fits::<u64>()).map(|n| n.to())
+        }
+
+        /// Get the number as an `i64`, if this is a `Num` value that
+        /// converts losslessly (see `Num::fits`)
+        pub fn as_i64(&self) -> Option<i64> {
+            self.as_num().filter(|n| n.fits::<i64>()).map(|n| n.to())
+        }
+
+        /// Get the number as an `f64`, if this is a float `Num` value
+        /// (including NaN), or an integer that converts losslessly
+        /// (see `Num::fits`), e.g. the integer `2^53 + 1`, which an
+        /// `f64` cannot represent, returns `None`
+        pub fn as_f64(&self) -> Option<f64> {
+            match self.as_num()? {
+                Num::F32(f) => Some(f as f64),
+                Num::F64(f) => Some(f),
+                n => Some(n).filter(|n| n.fits::<f64>()).map(|n| n.to()),
+            }
+        }
+    };
+}
+
+impl Value {
+    common_accessors!(Value);
+
+    /// Get the string if this is a `Str` value containing valid utf8
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            Value::Str(s) => s.as_str().ok(),
+            _ => None,
+        }
+    }
+
+    /// Get the data if this is a `Bin` value
+    pub fn as_bytes(&self) -> Option<&[u8]> {
+        match self {
+            Value::Bin(data) => Some(data),
+            _ => None,
+        }
+    }
+
+    /// Get the items if this is an `Arr` value
+    pub fn as_array(&self) -> Option<&[Value]> {
+        match self {
+            Value::Arr(arr) => Some(arr),
+            _ => None,
+        }
+    }
+
+    /// Get the mutable items if this is an `Arr` value
+    pub fn as_array_mut(&mut self) -> Option<&mut Vec<Value>> {
+        match self {
+            Value::Arr(arr) => Some(arr),
+            _ => None,
+        }
+    }
+
+    /// Get the key value pairs if this is a `Map` value
+    pub fn as_map(&self) -> Option<&[(Value, Value)]> {
+        match self {
+            Value::Map(map) => Some(map),
+            _ => None,
+        }
+    }
+
+    /// Get the mutable key value pairs if this is a `Map` value
+    pub fn as_map_mut(&mut self) -> Option<&mut Vec<(Value, Value)>> {
+        match self {
+            Value::Map(map) => Some(map),
+            _ => None,
+        }
+    }
+
+    /// Get the type and data if this is an `Ext` value
+    pub fn as_ext(&self) -> Option<(i8, &[u8])> {
+        match self {
+            Value::Ext(t, data) => Some((*t, data)),
+            _ => None,
+        }
+    }
+
+    /// Take this value, leaving `Nil` in its place
+    pub fn take(&mut self) -> Value {
+        mem::replace(self, Value::Nil)
+    }
+}
+
 impl Value {
     /// Get a ValueRef from this instance
-    pub fn as_ref(&self) -> ValueRef {
+    pub fn as_ref(&self) -> ValueRef<'_> {
         self.into()
     }
 
@@ -491,7 +657,8 @@ impl Value {
         P: Into<DynProducerSync<'prod>>,
     {
         let mut tokens = Vec::new();
-        let mut dec = msgpackin_core::decode::Decoder::new();
+        let mut dec =
+            msgpackin_core::decode::Decoder::with_compat(config.compat);
         let mut p = p.into();
         priv_decode_owned_sync(&mut tokens, &mut dec, &mut p, config)?;
         let mut iter = tokens.into_iter();
@@ -517,7 +684,8 @@ impl Value {
         P: Into<DynProducerAsync<'prod>>,
     {
         let mut tokens = Vec::new();
-        let mut dec = msgpackin_core::decode::Decoder::new();
+        let mut dec =
+            msgpackin_core::decode::Decoder::with_compat(config.compat);
         let mut p = p.into();
         priv_decode_owned_async(&mut tokens, &mut dec, &mut p, config).await?;
         let mut iter = tokens.into_iter();
@@ -823,6 +991,48 @@ impl<'a> From<&'a Value> for ValueRef<'a> {
     }
 }
 
+impl From<()> for ValueRef<'_> {
+    fn from(_: ()) -> Self {
+        ValueRef::Nil
+    }
+}
+
+impl From<bool> for ValueRef<'_> {
+    fn from(b: bool) -> Self {
+        ValueRef::Bool(b)
+    }
+}
+
+macro_rules! num_2_vref {
+    ($($t:ty)*) => {$(
+        impl From<$t> for ValueRef<'_> {
+            fn from(n: $t) -> Self {
+                ValueRef::Num(n.into())
+            }
+        }
+    )*};
+}
+
+num_2_vref!( i8 i16 i32 i64 isize u8 u16 u32 u64 usize f32 f64 );
+
+impl<'a> From<&'a str> for ValueRef<'a> {
+    fn from(s: &'a str) -> Self {
+        ValueRef::Str(s.into())
+    }
+}
+
+impl<'a> From<&'a String> for ValueRef<'a> {
+    fn from(s: &'a String) -> Self {
+        ValueRef::Str(s.as_str().into())
+    }
+}
+
+impl<'a> From<&'a [u8]> for ValueRef<'a> {
+    fn from(b: &'a [u8]) -> Self {
+        ValueRef::Bin(b)
+    }
+}
+
 macro_rules! stub_wrap {
     ($($t:tt)*) => { $($t)* };
 }
@@ -857,6 +1067,9 @@ macro_rules! mk_encode {
                     con.write(&enc.enc_str_len(data.0.len() as u32))$($await)*?;
                     con.write(&data.0)$($await)*
                 }
+                ValueRef::Ext(_, _) if config.compat.is_legacy() => {
+                    Err("ext is not supported in legacy compat mode".into())
+                }
                 ValueRef::Ext(t, data) => {
                     con.write(
                         &enc.enc_ext_len(data.len() as u32, *t),
@@ -900,58 +1113,35 @@ mk_encode!(
 );
 
 struct VRDecode<'dec, 'buf> {
-    iter: msgpackin_core::decode::TokenIter<'dec, 'buf>,
+    iter: msgpackin_core::decode::ValueTokenIter<'dec, 'buf>,
+    validate_utf8: bool,
 }
 
 impl<'dec, 'buf> VRDecode<'dec, 'buf> {
     fn next_val(&mut self) -> Result<ValueRef<'buf>> {
-        use msgpackin_core::decode::LenType;
-        use msgpackin_core::decode::Token::*;
+        use msgpackin_core::decode::ValueToken::*;
         match self.iter.next() {
             Some(Nil) => Ok(ValueRef::Nil),
             Some(Bool(b)) => Ok(ValueRef::Bool(b)),
             Some(Num(n)) => Ok(ValueRef::Num(n)),
-            tok @ Some(Len(LenType::Bin, l)) => {
-                if let Some(Bin(data)) = self.iter.next() {
-                    if data.len() == l as usize {
-                        return Ok(ValueRef::Bin(data));
-                    }
-                }
-                Err(Error::EDecode {
-                    expected: format!("Some(Bin({:?} bytes))", l),
-                    got: format!("{:?}", tok),
-                })
+            Some(Bin(data)) => Ok(ValueRef::Bin(data)),
+            Some(Str(s)) => Ok(ValueRef::Str(Utf8StrRef(s.as_bytes()))),
+            Some(InvalidStr(data)) if self.validate_utf8 => {
+                let start = self.iter.cursor() - data.len();
+                let mut v = msgpackin_core::utf8::Utf8Validator::new();
+                let e = v.push(data).unwrap_err();
+                Err(Error::EInvalidUtf8At(start + e.offset))
             }
-            tok @ Some(Len(LenType::Str, l)) => {
-                if let Some(Bin(data)) = self.iter.next() {
-                    if data.len() == l as usize {
-                        return Ok(ValueRef::Str(Utf8StrRef(data)));
-                    }
-                }
-                Err(Error::EDecode {
-                    expected: format!("Some(Bin({:?} bytes))", l),
-                    got: format!("{:?}", tok),
-                })
-            }
-            tok @ Some(Len(LenType::Ext(ext_type), l)) => {
-                if let Some(Bin(data)) = self.iter.next() {
-                    if data.len() == l as usize {
-                        return Ok(ValueRef::Ext(ext_type, data));
-                    }
-                }
-                Err(Error::EDecode {
-                    expected: format!("Some(Bin({:?} bytes))", l),
-                    got: format!("{:?}", tok),
-                })
-            }
-            Some(Len(LenType::Arr, l)) => {
+            Some(InvalidStr(data)) => Ok(ValueRef::Str(Utf8StrRef(data))),
+            Some(Ext(ext_type, data)) => Ok(ValueRef::Ext(ext_type, data)),
+            Some(Arr(l)) => {
                 let mut out = Vec::with_capacity(l as usize);
                 for _ in 0..l {
                     out.push(self.next_val()?);
                 }
                 Ok(ValueRef::Arr(out))
             }
-            Some(Len(LenType::Map, l)) => {
+            Some(Map(l)) => {
                 let mut out = Vec::with_capacity(l as usize);
                 for _ in 0..l {
                     let key = self.next_val()?;
@@ -960,6 +1150,10 @@ impl<'dec, 'buf> VRDecode<'dec, 'buf> {
                 }
                 Ok(ValueRef::Map(out))
             }
+            tok @ Some(PayloadStart(_, l)) => Err(Error::EDecode {
+                expected: format!("Some(Bin({:?} bytes))", l),
+                got: format!("{:?}", tok),
+            }),
             None => Err(Error::EDecode {
                 expected: "Marker".into(),
                 got: "UnexpectedEOF".into(),
@@ -972,6 +1166,73 @@ impl<'dec, 'buf> VRDecode<'dec, 'buf> {
     }
 }
 
+impl<'lt> ValueRef<'lt> {
+    common_accessors!(ValueRef);
+
+    /// Get the string if this is a `Str` value containing valid utf8
+    pub fn as_str(&self) -> Option<&'lt str> {
+        match self {
+            ValueRef::Str(s) => s.as_str().ok(),
+            _ => None,
+        }
+    }
+
+    /// Get the data if this is a `Bin` value
+    pub fn as_bytes(&self) -> Option<&'lt [u8]> {
+        match self {
+            ValueRef::Bin(data) => Some(data),
+            _ => None,
+        }
+    }
+
+    /// Get the items if this is an `Arr` value
+    pub fn as_array(&self) -> Option<&[ValueRef<'lt>]> {
+        match self {
+            ValueRef::Arr(arr) => Some(arr),
+            _ => None,
+        }
+    }
+
+    /// Get the mutable items if this is an `Arr` value
+    pub fn as_array_mut(&mut self) -> Option<&mut Vec<ValueRef<'lt>>> {
+        match self {
+            ValueRef::Arr(arr) => Some(arr),
+            _ => None,
+        }
+    }
+
+    /// Get the key value pairs if this is a `Map` value
+    pub fn as_map(&self) -> Option<&[(ValueRef<'lt>, ValueRef<'lt>)]> {
+        match self {
+            ValueRef::Map(map) => Some(map),
+            _ => None,
+        }
+    }
+
+    /// Get the mutable key value pairs if this is a `Map` value
+    pub fn as_map_mut(
+        &mut self,
+    ) -> Option<&mut Vec<(ValueRef<'lt>, ValueRef<'lt>)>> {
+        match self {
+            ValueRef::Map(map) => Some(map),
+            _ => None,
+        }
+    }
+
+    /// Get the type and data if this is an `Ext` value
+    pub fn as_ext(&self) -> Option<(i8, &'lt [u8])> {
+        match self {
+            ValueRef::Ext(t, data) => Some((*t, data)),
+            _ => None,
+        }
+    }
+
+    /// Take this value ref, leaving `Nil` in its place
+    pub fn take(&mut self) -> ValueRef<'lt> {
+        mem::replace(self, ValueRef::Nil)
+    }
+}
+
 impl<'lt> ValueRef<'lt> {
     /// Convert this ValueRef into an owned Value
     pub fn to_owned(&self) -> Value {
@@ -1000,7 +1261,10 @@ impl<'lt> ValueRef<'lt> {
     where
         C: Into<DynConsumerSync<'con>>,
     {
-        let mut enc = msgpackin_core::encode::Encoder::new();
+        let mut enc = msgpackin_core::encode::Encoder::with_float_policy(
+            config.float_policy,
+        )
+        .with_compat(config.compat);
         let mut c = c.into();
         priv_encode_sync(self, &mut enc, &mut c, config)
     }
@@ -1024,7 +1288,10 @@ impl<'lt> ValueRef<'lt> {
     where
         C: Into<DynConsumerAsync<'con>>,
     {
-        let mut enc = msgpackin_core::encode::Encoder::new();
+        let mut enc = msgpackin_core::encode::Encoder::with_float_policy(
+            config.float_policy,
+        )
+        .with_compat(config.compat);
         let mut c = c.into();
         priv_encode_async(self, &mut enc, &mut c, config).await
     }
@@ -1044,16 +1311,344 @@ impl<'lt> ValueRef<'lt> {
     where
         P: Into<DynProducerComplete<'lt>>,
     {
-        let _config = config;
-        let mut dec = msgpackin_core::decode::Decoder::new();
+        let data = p.into().read_all()?;
+        if config.canonical {
+            if let Some(v) = msgpackin_core::canonical::check(
+                data,
+                config.max_depth,
+                config.compat,
+            ) {
+                return Err(Error::ENonCanonical(v));
+            }
+        }
+        let mut dec =
+            msgpackin_core::decode::Decoder::with_compat(config.compat);
         let mut dec = VRDecode {
-            iter: dec.parse(p.into().read_all()?),
+            iter: dec.parse(data).value_tokens(),
+            validate_utf8: config.validate_utf8,
         };
 
         dec.next_val()
     }
 }
 
+/// A map key or array index for looking up sub-values with `get`,
+/// `get_mut`, or indexing (e.g. `value[Key::Bin(b"id")]`).
+/// Integer keys select array items by index, or map entries with an
+/// equal `Num` key
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Key<'a> {
+    /// Match a `Str` map key
+    Str(&'a str),
+
+    /// Match an array index, or a `Num` map key
+    Num(Num),
+
+    /// Match a `Bin` map key
+    Bin(&'a [u8]),
+}
+
+impl<'a> From<&'a str> for Key<'a> {
+    fn from(s: &'a str) -> Self {
+        Key::Str(s)
+    }
+}
+
+impl<'a> From<&'a String> for Key<'a> {
+    fn from(s: &'a String) -> Self {
+        Key::Str(s)
+    }
+}
+
+impl<'a> From<&'a [u8]> for Key<'a> {
+    fn from(b: &'a [u8]) -> Self {
+        Key::Bin(b)
+    }
+}
+
+impl From<Num> for Key<'_> {
+    fn from(n: Num) -> Self {
+        Key::Num(n)
+    }
+}
+
+macro_rules! num_2_key {
+    ($($t:ty)*) => {$(
+        impl From<$t> for Key<'_> {
+            fn from(n: $t) -> Self {
+                Key::Num(n.into())
+            }
+        }
+    )*};
+}
+
+num_2_key!( i8 i16 i32 i64 isize u8 u16 u32 u64 usize );
+
+impl Key<'_> {
+    fn as_index(&self) -> Option<usize> {
+        match self {
+            Key::Num(n) if n.fits::<usize>() => Some(n.to()),
+            _ => None,
+        }
+    }
+
+    /// the integer key for a JSON Pointer segment, if it is one
+    fn from_pointer(seg: &str) -> Option<Key<'static>> {
+        // RFC 6901 indexes have no `+` sign and no leading zeros
+        let digits = seg.strip_prefix('-').unwrap_or(seg);
+        if digits.starts_with('+') || (digits.starts_with('0') && seg != "0") {
+            return None;
+        }
+        if let Ok(u) = seg.parse::<u64>() {
+            Some(u.into())
+        } else {
+            seg.parse::<i64>().ok().map(Into::into)
+        }
+    }
+}
+
+impl From<Key<'_>> for Value {
+    fn from(k: Key<'_>) -> Self {
+        match k {
+            Key::Str(s) => s.into(),
+            Key::Num(n) => Value::Num(n),
+            Key::Bin(b) => b.into(),
+        }
+    }
+}
+
+impl<'a> From<Key<'a>> for ValueRef<'a> {
+    fn from(k: Key<'a>) -> Self {
+        match k {
+            Key::Str(s) => ValueRef::Str(s.into()),
+            Key::Num(n) => ValueRef::Num(n),
+            Key::Bin(b) => ValueRef::Bin(b),
+        }
+    }
+}
+
+/// unescape `~1` and `~0` in a JSON Pointer segment
+/// integer keys compare by value with a sign check, so `-1` does not
+/// match `u64::MAX` as it would through the wrapping `Num` comparison
+fn num_key_eq(a: &Num, b: &Num) -> bool {
+    match (a, b) {
+        (Num::Signed(i), Num::Unsigned(u))
+        | (Num::Unsigned(u), Num::Signed(i)) => *i >= 0 && *i as u64 == *u,
+        _ => a == b,
+    }
+}
+
+fn unescape_pointer(seg: &str) -> Cow<'_, str> {
+    if seg.contains('~') {
+        Cow::Owned(seg.replace("~1", "/").replace("~0", "~"))
+    } else {
+        Cow::Borrowed(seg)
+    }
+}
+
+/// lookup methods with identical signatures on Value and ValueRef
+macro_rules! nav_methods {
+    ($t:ident) => {
+        /// Get the array item or map value for `key`, if present.
+        /// If a map contains duplicate keys, the first is used
+        pub fn get<'k, K: Into<Key<'k>>>(&self, key: K) -> Option<&Self> {
+            let pos = self.position(&key.into())?;
+            Some(self.at(pos))
+        }
+
+        /// Get the mutable array item or map value for `key`,
+        /// if present
+        pub fn get_mut<'k, K: Into<Key<'k>>>(
+            &mut self,
+            key: K,
+        ) -> Option<&mut Self> {
+            let pos = self.position(&key.into())?;
+            Some(self.at_mut(pos))
+        }
+
+        /// Look up a sub-value by a JSON Pointer (RFC 6901) such as
+        /// `/users/3/name`. The empty string refers to this value.
+        /// Segments select array items by index, and map values by
+        /// `Str` key, falling back to an equal `Num` key for integer
+        /// segments. Integer segments have no `+` sign or leading zeros
+        pub fn pointer(&self, pointer: &str) -> Option<&Self> {
+            let mut target = self;
+            for seg in Self::pointer_segs(pointer)? {
+                let pos = target.pointer_position(&unescape_pointer(seg))?;
+                target = target.at(pos);
+            }
+            Some(target)
+        }
+
+        /// Look up a mutable sub-value by a JSON Pointer,
+        /// see `pointer`
+        pub fn pointer_mut(&mut self, pointer: &str) -> Option<&mut Self> {
+            let mut target = self;
+            for seg in Self::pointer_segs(pointer)? {
+                let pos = target.pointer_position(&unescape_pointer(seg))?;
+                target = target.at_mut(pos);
+            }
+            Some(target)
+        }
+
+        // -- private -- //
+
+        fn pointer_segs(
+            pointer: &str,
+        ) -> Option<impl Iterator<Item = &str> + '_> {
+            let segs = match pointer {
+                "" => None,
+                _ => Some(pointer.strip_prefix('/')?.split('/')),
+            };
+            Some(segs.into_iter().flatten())
+        }
+
+        fn pointer_position(&self, seg: &str) -> Option<usize> {
+            self.position(&Key::Str(seg))
+                .or_else(|| self.position(&Key::from_pointer(seg)?))
+        }
+
+        /// the index of the array item or map entry for `key`
+        fn position(&self, key: &Key<'_>) -> Option<usize> {
+            match self {
+                $t::Arr(arr) => key.as_index().filter(|i| *i < arr.len()),
+                $t::Map(map) => map.iter().position(|(k, _)| match (k, key) {
+                    ($t::Str(s), Key::Str(key)) => {
+                        s.as_bytes() == key.as_bytes()
+                    }
+                    ($t::Num(n), Key::Num(key)) => num_key_eq(n, key),
+                    ($t::Bin(b), Key::Bin(key)) => **b == **key,
+                    _ => false,
+                }),
+                _ => None,
+            }
+        }
+
+        fn at(&self, pos: usize) -> &Self {
+            match self {
+                $t::Arr(arr) => &arr[pos],
+                $t::Map(map) => &map[pos].1,
+                _ => unreachable!(),
+            }
+        }
+
+        fn at_mut(&mut self, pos: usize) -> &mut Self {
+            match self {
+                $t::Arr(arr) => &mut arr[pos],
+                $t::Map(map) => &mut map[pos].1,
+                _ => unreachable!(),
+            }
+        }
+    };
+}
+
+impl Value {
+    nav_methods!(Value);
+
+    /// the value for `key`, inserting a `Nil` map value if needed
+    fn index_or_insert(&mut self, key: Key<'_>) -> &mut Value {
+        if self.is_nil() {
+            *self = Value::Map(Vec::new());
+        }
+        if let Some(pos) = self.position(&key) {
+            return self.at_mut(pos);
+        }
+        match self {
+            Value::Map(map) => {
+                map.push((key.into(), Value::Nil));
+                &mut map.last_mut().unwrap().1
+            }
+            _ => {
+                panic!("cannot index {} value with {:?}", self.type_name(), key)
+            }
+        }
+    }
+}
+
+impl<'lt> ValueRef<'lt> {
+    nav_methods!(ValueRef);
+
+    /// the value for `key`, inserting a `Nil` map value if needed
+    fn index_or_insert(&mut self, key: Key<'lt>) -> &mut ValueRef<'lt> {
+        if self.is_nil() {
+            *self = ValueRef::Map(Vec::new());
+        }
+        if let Some(pos) = self.position(&key) {
+            return self.at_mut(pos);
+        }
+        match self {
+            ValueRef::Map(map) => {
+                map.push((key.into(), ValueRef::Nil));
+                &mut map.last_mut().unwrap().1
+            }
+            _ => {
+                panic!("cannot index {} value with {:?}", self.type_name(), key)
+            }
+        }
+    }
+}
+
+static NIL: Value = Value::Nil;
+static NIL_REF: ValueRef<'static> = ValueRef::Nil;
+
+macro_rules! index_impls {
+    ($($k:ty)*) => {$(
+        /// Indexing returns a `Nil` value if `key` is not present
+        impl<'k> Index<$k> for Value {
+            type Output = Value;
+
+            fn index(&self, key: $k) -> &Value {
+                self.get(key).unwrap_or(&NIL)
+            }
+        }
+
+        /// Indexing returns a `Nil` value if `key` is not present
+        impl<'k, 'lt> Index<$k> for ValueRef<'lt> {
+            type Output = ValueRef<'lt>;
+
+            fn index(&self, key: $k) -> &ValueRef<'lt> {
+                self.get(key).unwrap_or(&NIL_REF)
+            }
+        }
+
+        /// Mutable indexing inserts a `Nil` map value if `key` is not
+        /// present, first replacing a `Nil` value with an empty map.
+        /// Panics if this is an array and the index is out of bounds,
+        /// or if this is not an array or map
+        impl<'k> IndexMut<$k> for Value {
+            fn index_mut(&mut self, key: $k) -> &mut Value {
+                self.index_or_insert(key.into())
+            }
+        }
+    )*};
+}
+
+index_impls!( &'k str Key<'k> usize );
+
+/// Mutable indexing inserts a `Nil` map value if `key` is not
+/// present, see `IndexMut` for `Value`
+impl<'lt> IndexMut<&'lt str> for ValueRef<'lt> {
+    fn index_mut(&mut self, key: &'lt str) -> &mut ValueRef<'lt> {
+        self.index_or_insert(key.into())
+    }
+}
+
+/// Mutable indexing inserts a `Nil` map value if `key` is not
+/// present, see `IndexMut` for `Value`
+impl<'lt> IndexMut<Key<'lt>> for ValueRef<'lt> {
+    fn index_mut(&mut self, key: Key<'lt>) -> &mut ValueRef<'lt> {
+        self.index_or_insert(key)
+    }
+}
+
+/// Mutable indexing inserts a `Nil` map value if `key` is not
+/// present, see `IndexMut` for `Value`
+impl<'lt> IndexMut<usize> for ValueRef<'lt> {
+    fn index_mut(&mut self, key: usize) -> &mut ValueRef<'lt> {
+        self.index_or_insert(key.into())
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -1070,6 +1665,7 @@ mod tests {
     }
 
     #[test]
+    #[allow(clippy::approx_constant)]
     fn test_value_encode_decode() {
         let arr = Value::Arr(vec![
             Value::from(()),
@@ -1104,4 +1700,298 @@ mod tests {
         .unwrap();
         assert_eq!(dec2, dec3);
     }
+
+    #[test]
+    fn test_value_accessors() {
+        let mut value = Value::Arr(vec![
+            ().into(),
+            true.into(),
+            42_u8.into(),
+            (-1_i8).into(),
+            1.5_f32.into(),
+            ((1_u64 << 53) + 1).into(),
+            "s".into(),
+            Value::Str(Utf8Str(b"\xff".to_vec().into())),
+            Value::Bin(b"b".to_vec().into()),
+            Value::Ext(-42, b"e".to_vec().into()),
+            Value::Map(vec![("k".into(), ().into())]),
+        ]);
+        let names: Vec<&str> = value
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|v| v.type_name())
+            .collect();
+        assert_eq!(
+            vec![
+                "nil", "bool", "num", "num", "num", "num", "str", "str", "bin",
+                "ext", "map"
+            ],
+            names,
+        );
+
+        let check = |v: ValueRef<'_>| {
+            let arr = v.as_array().unwrap();
+            assert!(arr[0].is_nil() && !arr[1].is_nil());
+            assert_eq!(Some(true), arr[1].as_bool());
+            assert_eq!(None, arr[0].as_bool());
+            assert_eq!(Some(42), arr[2].as_u64());
+            assert_eq!(Some(42), arr[2].as_i64());
+            assert_eq!(Some(42.0), arr[2].as_f64());
+            assert_eq!(None, arr[3].as_u64());
+            assert_eq!(Some(-1), arr[3].as_i64());
+            assert_eq!(None, arr[4].as_i64());
+            assert_eq!(Some(1.5), arr[4].as_f64());
+            assert_eq!(Some((1 << 53) + 1), arr[5].as_i64());
+            assert_eq!(None, arr[5].as_f64());
+            assert_eq!(Some("s"), arr[6].as_str());
+            assert!(arr[7].is_str() && arr[7].as_str().is_none());
+            assert_eq!(Some(&b"b"[..]), arr[8].as_bytes());
+            assert_eq!(None, arr[6].as_bytes());
+            assert_eq!(Some((-42, &b"e"[..])), arr[9].as_ext());
+            assert_eq!(1, arr[10].as_map().unwrap().len());
+            assert!(arr[10].as_array().is_none());
+        };
+        check(value.as_ref());
+        check(
+            ValueRef::from_ref(value.to_bytes().unwrap().as_slice()).unwrap(),
+        );
+        assert!(Value::from(f64::NAN).as_f64().unwrap().is_nan());
+        assert!(Value::from(f32::NAN).as_f64().unwrap().is_nan());
+        assert!(ValueRef::from(f32::NAN).as_f64().unwrap().is_nan());
+
+        let arr = value.as_array_mut().unwrap();
+        assert_eq!(Value::from(true), arr[1].take());
+        assert!(arr[1].is_nil());
+        arr[10].as_map_mut().unwrap().clear();
+        arr.truncate(2);
+        assert_eq!(Value::Arr(vec![().into(), ().into()]), value.take());
+        assert!(value.is_nil() && value.as_array_mut().is_none());
+
+        let mut r = ValueRef::Arr(vec![ValueRef::Str("x".into())]);
+        let item = r.as_array_mut().unwrap()[0].take();
+        assert_eq!(Some("x"), item.as_str());
+        assert_eq!(ValueRef::Arr(vec![ValueRef::Nil]), r);
+    }
+
+    #[test]
+    fn test_value_index() {
+        let mut value = Value::Map(vec![
+            (
+                "users".into(),
+                Value::Arr(vec![Value::Map(vec![
+                    ("name".into(), "bob".into()),
+                    ("a/b~".into(), 1.into()),
+                ])]),
+            ),
+            (7.into(), "int".into()),
+            (Value::Bin(b"id".to_vec().into()), "bin".into()),
+            (u64::MAX.into(), "max".into()),
+            (Value::from(-1), "neg".into()),
+        ]);
+
+        assert_eq!(Some("bob"), value["users"][0]["name"].as_str());
+        assert_eq!(Some("int"), value[Key::Num(7.into())].as_str());
+        assert_eq!(Some("bin"), value[Key::Bin(b"id")].as_str());
+        assert_eq!(Some("neg"), value.get(-1).and_then(Value::as_str));
+        assert_eq!(Some("max"), value.get(u64::MAX).and_then(Value::as_str));
+        assert!(value["nope"][3]["x"].is_nil());
+        assert!(value.get("nope").is_none());
+        assert!(value.get(7_u8).is_some());
+        assert!(value["users"].get(1).is_none());
+
+        assert_eq!(Some(&value), value.pointer(""));
+        assert_eq!(
+            Some("bob"),
+            value.pointer("/users/0/name").and_then(Value::as_str),
+        );
+        assert_eq!(Some(1), value.pointer("/users/0/a~1b~0").unwrap().as_u64());
+        assert_eq!(Some("int"), value.pointer("/7").and_then(Value::as_str));
+        assert_eq!(Some("neg"), value.pointer("/-1").and_then(Value::as_str));
+        assert_eq!(
+            Some("max"),
+            value
+                .pointer("/18446744073709551615")
+                .and_then(Value::as_str),
+        );
+        assert!(value.pointer("users").is_none());
+        assert!(value.pointer("/users/1").is_none());
+        assert!(value.pointer("/users/x").is_none());
+        assert!(value.pointer("/users/00").is_none());
+        assert!(value.pointer("/users/+0").is_none());
+        assert!(value.pointer("/07").is_none());
+        assert!(value.pointer("/+7").is_none());
+        assert!(value.pointer("/-01").is_none());
+
+        *value.pointer_mut("/users/0/name").unwrap() = "alice".into();
+        *value.get_mut(7).unwrap() = ().into();
+        value["users"][0]["age"] = 42.into();
+        value["new"]["nested"] = true.into();
+        assert_eq!(Some("alice"), value["users"][0]["name"].as_str());
+        assert!(value[7].is_nil());
+        assert_eq!(Some(42), value.pointer("/users/0/age").unwrap().as_u64());
+        assert_eq!(Some(true), value["new"]["nested"].as_bool());
+
+        let data = value.to_bytes().unwrap();
+        let mut r = ValueRef::from_ref(&data).unwrap();
+        assert_eq!(Some("alice"), r["users"][0]["name"].as_str());
+        assert_eq!(Some("bin"), r[Key::Bin(b"id")].as_str());
+        assert!(r["users"][0]["nope"].is_nil());
+        assert_eq!(Some(42), r.pointer("/users/0/age").unwrap().as_u64());
+        r["users"][0]["name"] = ValueRef::Str("carol".into());
+        assert_eq!(Some("carol"), r.pointer("/users/0/name").unwrap().as_str());
+
+        let mut r = ValueRef::Nil;
+        r[3] = ValueRef::Bool(true);
+        assert_eq!(ValueRef::Map(vec![(3.into(), ValueRef::Bool(true))]), r);
+    }
+
+    #[test]
+    #[should_panic(expected = "cannot index num value")]
+    fn test_value_index_mut_panics() {
+        let mut value = Value::from(1);
+        value["x"] = ().into();
+    }
+
+    #[test]
+    fn test_value_ref_canonical() {
+        let config = Config {
+            canonical: true,
+            ..Default::default()
+        };
+
+        let data = [0x92, 0x01, 0xce, 0x00, 0x00, 0x00, 0x05];
+        assert_eq!(
+            Value::Arr(vec![1.into(), 5.into()]),
+            ValueRef::from_ref(&data[..]).unwrap(),
+        );
+        match ValueRef::from_ref_config(&data[..], &config) {
+            Err(Error::ENonCanonical(v)) => assert_eq!(2, v.offset),
+            oth => panic!("expected ENonCanonical, got: {:?}", oth),
+        }
+
+        let data = [0x92, 0x01, 0x05];
+        assert_eq!(
+            Value::Arr(vec![1.into(), 5.into()]),
+            ValueRef::from_ref_config(&data[..], &config).unwrap(),
+        );
+    }
+
+    #[test]
+    fn test_value_float_policy() {
+        let value = Value::Arr(vec![42.0_f64.into(), (-0.0_f64).into()]);
+        let data = value.to_bytes().unwrap();
+        assert_eq!(
+            &[
+                0x92, 0xcb, 0x40, 0x45, 0, 0, 0, 0, 0, 0, 0xcb, 0x80, 0, 0, 0,
+                0, 0, 0, 0
+            ],
+            data.as_slice(),
+        );
+        match ValueRef::from_ref(data.as_slice()).unwrap() {
+            ValueRef::Arr(arr) => match arr.as_slice() {
+                [ValueRef::Num(Num::F64(a)), ValueRef::Num(Num::F64(b))] => {
+                    assert_eq!(42.0, *a);
+                    assert!(*b == 0.0 && b.is_sign_negative());
+                }
+                oth => panic!("unexpected: {:?}", oth),
+            },
+            oth => panic!("unexpected: {:?}", oth),
+        }
+
+        let config = Config {
+            float_policy: FloatPolicy::Compact,
+            ..Default::default()
+        };
+        let mut data = Vec::new();
+        value.to_sync_config(&mut data, &config).unwrap();
+        assert_eq!(&[0x92, 0x2a, 0xca, 0x80, 0, 0, 0], data.as_slice());
+    }
+
+    #[test]
+    fn test_value_legacy_compat() {
+        let long = "a".repeat(40);
+        let value = Value::Arr(vec![
+            long.as_str().into(),
+            Value::Bin(b"hi".to_vec().into()),
+        ]);
+        let config = Config {
+            compat: Compat::LegacyRawBin,
+            ..Default::default()
+        };
+        let mut data = Vec::new();
+        value.to_sync_config(&mut data, &config).unwrap();
+        assert_eq!(&[0x92, 0xda, 0, 40], &data[..4]);
+        assert_eq!(&[0xa2, b'h', b'i'], &data[44..]);
+
+        match ValueRef::from_ref_config(&data[..], &config).unwrap() {
+            ValueRef::Arr(arr) => match arr.as_slice() {
+                [ValueRef::Bin(a), ValueRef::Bin(b)] => {
+                    assert_eq!(long.as_bytes(), *a);
+                    assert_eq!(b"hi", *b);
+                }
+                oth => panic!("unexpected: {:?}", oth),
+            },
+            oth => panic!("unexpected: {:?}", oth),
+        }
+
+        let config = Config {
+            compat: Compat::LegacyRawStr,
+            ..Default::default()
+        };
+        assert_eq!(
+            Value::Arr(vec![long.as_str().into(), "hi".into()]),
+            Value::from_sync_config(&data[..], &config).unwrap(),
+        );
+
+        // canonical checks follow the legacy length encoding
+        let config = Config {
+            canonical: true,
+            ..config
+        };
+        let mut data = Vec::new();
+        Value::from(long.as_str())
+            .to_sync_config(&mut data, &config)
+            .unwrap();
+        assert_eq!(&[0xda, 0, 40], &data[..3]);
+        assert_eq!(
+            ValueRef::from(long.as_str()),
+            ValueRef::from_ref_config(&data[..], &config).unwrap(),
+        );
+
+        let ext = Value::Ext(1, b"x".to_vec().into());
+        let mut out = Vec::new();
+        assert!(ext.to_sync_config(&mut out, &config).is_err());
+        assert!(out.is_empty());
+    }
+
+    #[test]
+    fn test_value_validate_utf8() {
+        let config = Config {
+            validate_utf8: true,
+            ..Default::default()
+        };
+        // [ "ok", "a\xc3(" ]
+        let data = [0x92, 0xa2, b'o', b'k', 0xa3, b'a', 0xc3, b'('];
+
+        // without validation, decoding succeeds
+        let value = Value::from_sync(&data[..]).unwrap();
+        assert!(ValueRef::from_ref(&data[..]).is_ok());
+        match value {
+            Value::Arr(arr) => match &arr[1] {
+                Value::Str(s) => assert!(s.as_str().is_err()),
+                oth => panic!("unexpected: {:?}", oth),
+            },
+            oth => panic!("unexpected: {:?}", oth),
+        }
+
+        match ValueRef::from_ref_config(&data[..], &config) {
+            Err(Error::EInvalidUtf8At(6)) => (),
+            oth => panic!("unexpected: {:?}", oth),
+        }
+        match Value::from_sync_config(&data[..], &config) {
+            Err(Error::EInvalidUtf8At(6)) => (),
+            oth => panic!("unexpected: {:?}", oth),
+        }
+    }
 }
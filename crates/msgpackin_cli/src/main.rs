@@ -0,0 +1,476 @@
+//! Msgpackin command line tool for converting, inspecting, validating,
+//! and querying MessagePack data.
+//!
+//! Input is read from FILE, or from stdin if FILE is omitted or `-`.
+//! Msgpack input may contain a stream of concatenated values, each of
+//! which is processed in turn. See `msgpackin --help` for usage.
+
+#![deny(unsafe_code)]
+#![deny(missing_docs)]
+#![deny(warnings)]
+
+use msgpackin::json::*;
+use msgpackin::types::Config;
+use msgpackin::{Value, ValueRef};
+use msgpackin_core::decode::*;
+use std::io::{Read, Write};
+
+type Result<T> = std::result::Result<T, Box<dyn std::error::Error>>;
+
+const USAGE: &str = "\
+msgpackin - convert, inspect, validate, and query MessagePack data
+
+USAGE:
+    msgpackin <COMMAND> [OPTIONS] [FILE]
+
+Input is read from FILE, or from stdin if FILE is omitted or `-`.
+Msgpack input may be a stream of concatenated values.
+
+COMMANDS:
+    to-json      write each msgpack value as a line of JSON (JSON Lines)
+        --pretty                 indent the JSON output
+        --bin <base64|hex|array|error>
+                                 how bin data is written [base64]
+        --big-int <number|string|float|error>
+                                 how integers beyond 2^53 are written
+                                 [number]
+        --non-finite <null|string|error>
+                                 how NaN / infinite floats are written
+                                 [null]
+
+    from-json    convert JSON values (e.g. JSON Lines) to msgpack
+
+    inspect      write an annotated hex dump of the msgpack data
+        --max-depth <N>          maximum container nesting [1024]
+
+    validate     check that the input is well formed msgpack
+        --strict                 reject invalid utf8 strings, the
+                                 reserved marker (0xc1), and data
+                                 following the first value
+        --canonical              reject non-canonical encodings
+        --max-depth <N>          maximum container nesting [1024]
+        --max-len <N>            maximum str / bin / ext / array / map
+                                 length
+
+    get <PATH>   extract the sub-value at PATH from each msgpack value,
+                 e.g. `/users/3/name`. Array items are selected by index,
+                 map values by key (`~1` escapes `/`, `~0` escapes `~`)
+        --msgpack                write the result as msgpack, not JSON
+        --pretty, --bin, --big-int, --non-finite
+                                 as for to-json
+
+    help         print this message
+";
+
+/// a parsed command line
+#[derive(Debug, PartialEq)]
+enum Cmd {
+    ToJson(JsonOut),
+    FromJson,
+    Inspect { max_depth: usize },
+    Validate(Validate),
+    Get { path: String, out: Option<JsonOut> },
+    Help,
+}
+
+/// options for writing JSON output
+#[derive(Debug, PartialEq)]
+struct JsonOut {
+    pretty: bool,
+    policy: JsonPolicy,
+}
+
+/// options for the validate command
+#[derive(Debug, PartialEq)]
+struct Validate {
+    strict: bool,
+    canonical: bool,
+    max_depth: usize,
+    max_len: Option<u32>,
+}
+
+fn main() -> std::process::ExitCode {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    match main_inner(&args) {
+        Ok(()) => std::process::ExitCode::SUCCESS,
+        Err(err) => {
+            eprintln!("msgpackin: {}", err);
+            std::process::ExitCode::FAILURE
+        }
+    }
+}
+
+fn main_inner(args: &[String]) -> Result<()> {
+    let (cmd, file) = parse_args(args)?;
+    if cmd == Cmd::Help {
+        print!("{}", USAGE);
+        return Ok(());
+    }
+    let mut input = Vec::new();
+    match file.as_deref() {
+        None | Some("-") => std::io::stdin().lock().read_to_end(&mut input)?,
+        Some(file) => std::fs::File::open(file)
+            .map_err(|e| format!("{}: {}", file, e))?
+            .read_to_end(&mut input)?,
+    };
+    let stdout = std::io::stdout();
+    let mut out = stdout.lock();
+    run(&cmd, &input, &mut out)?;
+    out.flush()?;
+    Ok(())
+}
+
+/// parse the arguments following the program name into a command
+/// and an optional input file
+fn parse_args(args: &[String]) -> Result<(Cmd, Option<String>)> {
+    let mut args = args.iter();
+    let name = match args.next() {
+        Some(name) => name.as_str(),
+        None => return Err("missing COMMAND, see `msgpackin --help`".into()),
+    };
+    let mut flags = Vec::new();
+    let mut pos = Vec::new();
+    let mut json = JsonOut {
+        pretty: false,
+        policy: JsonPolicy::default(),
+    };
+    let mut validate = Validate {
+        strict: false,
+        canonical: false,
+        max_depth: 1024,
+        max_len: None,
+    };
+    let mut msgpack = false;
+    while let Some(arg) = args.next() {
+        let flag = arg.as_str();
+        let mut value = || {
+            args.next()
+                .map(|v| v.as_str())
+                .ok_or_else(|| format!("{} requires a value", flag))
+        };
+        match flag {
+            "-h" | "--help" => return Ok((Cmd::Help, None)),
+            "--pretty" => json.pretty = true,
+            "--bin" => {
+                json.policy.bin = match value()? {
+                    "base64" => BinPolicy::Base64,
+                    "hex" => BinPolicy::Hex,
+                    "array" => BinPolicy::Array,
+                    "error" => BinPolicy::Error,
+                    v => return Err(bad_value(flag, v)),
+                }
+            }
+            "--big-int" => {
+                json.policy.big_int = match value()? {
+                    "number" => BigIntPolicy::Number,
+                    "string" => BigIntPolicy::String,
+                    "float" => BigIntPolicy::Float,
+                    "error" => BigIntPolicy::Error,
+                    v => return Err(bad_value(flag, v)),
+                }
+            }
+            "--non-finite" => {
+                json.policy.non_finite = match value()? {
+                    "null" => NonFinite::Null,
+                    "string" => NonFinite::Str,
+                    "error" => NonFinite::Error,
+                    v => return Err(bad_value(flag, v)),
+                }
+            }
+            "--strict" => validate.strict = true,
+            "--canonical" => validate.canonical = true,
+            "--max-depth" => {
+                let v = value()?;
+                validate.max_depth =
+                    v.parse().map_err(|_| bad_value(flag, v))?;
+            }
+            "--max-len" => {
+                let v = value()?;
+                validate.max_len =
+                    Some(v.parse().map_err(|_| bad_value(flag, v))?);
+            }
+            "--msgpack" => msgpack = true,
+            "-" => pos.push(arg.clone()),
+            _ if flag.starts_with('-') => {
+                return Err(format!("unknown option {}", flag).into())
+            }
+            _ => pos.push(arg.clone()),
+        }
+        if flag.starts_with("--") {
+            flags.push(flag);
+        }
+    }
+
+    const JSON_FLAGS: &[&str] =
+        &["--pretty", "--bin", "--big-int", "--non-finite"];
+    let (cmd, allowed, paths): (Cmd, &[&str], usize) = match name {
+        "to-json" => (Cmd::ToJson(json), JSON_FLAGS, 0),
+        "from-json" => (Cmd::FromJson, &[], 0),
+        "inspect" => (
+            Cmd::Inspect {
+                max_depth: validate.max_depth,
+            },
+            &["--max-depth"],
+            0,
+        ),
+        "validate" => (
+            Cmd::Validate(validate),
+            &["--strict", "--canonical", "--max-depth", "--max-len"],
+            0,
+        ),
+        "get" => {
+            let path = match pos.first() {
+                Some(path) => pointer(path),
+                None => return Err("get requires a PATH".into()),
+            };
+            let out = if msgpack { None } else { Some(json) };
+            let cmd = Cmd::Get { path, out };
+            if msgpack {
+                (cmd, &["--msgpack"], 1)
+            } else {
+                (cmd, JSON_FLAGS, 1)
+            }
+        }
+        "help" | "-h" | "--help" => return Ok((Cmd::Help, None)),
+        _ => {
+            return Err(format!(
+                "unknown COMMAND {}, see `msgpackin --help`",
+                name
+            )
+            .into())
+        }
+    };
+    if let Some(flag) = flags.iter().find(|f| !allowed.contains(f)) {
+        return Err(format!("{} does not accept {}", name, flag).into());
+    }
+    let mut pos = pos.into_iter().skip(paths);
+    let file = pos.next();
+    if let Some(extra) = pos.next() {
+        return Err(format!("unexpected argument {}", extra).into());
+    }
+    Ok((cmd, file))
+}
+
+fn bad_value(flag: &str, value: &str) -> Box<dyn std::error::Error> {
+    format!("invalid value {:?} for {}", value, flag).into()
+}
+
+/// the JSON Pointer for a path, which may omit the leading `/`
+fn pointer(path: &str) -> String {
+    match path {
+        "" | "/" => String::new(),
+        _ if path.starts_with('/') => path.to_string(),
+        _ => format!("/{}", path),
+    }
+}
+
+/// run a command against the complete input
+fn run(cmd: &Cmd, input: &[u8], out: &mut dyn Write) -> Result<()> {
+    match cmd {
+        Cmd::ToJson(json) => {
+            for data in split(input)? {
+                json.write(&ValueRef::from_ref(data)?, out)?;
+            }
+        }
+        Cmd::FromJson => {
+            // deserialize straight into `Value`, as `serde_json::Value`
+            // would sort object keys
+            let iter = serde_json::Deserializer::from_slice(input)
+                .into_iter::<Value>();
+            for value in iter {
+                value?.to_sync(&mut *out)?;
+            }
+        }
+        Cmd::Inspect { max_depth } => {
+            let mut text = String::new();
+            msgpackin_core::inspect::inspect(input, *max_depth, &mut text)?;
+            out.write_all(text.as_bytes())?;
+        }
+        Cmd::Validate(validate) => validate.run(input, out)?,
+        Cmd::Get { path, out: json } => {
+            for data in split(input)? {
+                let value = ValueRef::from_ref(data)?;
+                let found = value
+                    .pointer(path)
+                    .ok_or_else(|| format!("path not found: {}", path))?;
+                match json {
+                    Some(json) => json.write(found, out)?,
+                    None => found.to_sync(&mut *out)?,
+                }
+            }
+        }
+        Cmd::Help => out.write_all(USAGE.as_bytes())?,
+    }
+    Ok(())
+}
+
+/// split a buffer of concatenated msgpack values into one slice
+/// per value
+fn split(input: &[u8]) -> Result<Vec<&[u8]>> {
+    let mut values = Vec::new();
+    let mut dec = Decoder::new();
+    let mut iter = dec.parse(input);
+    let mut start = 0;
+    while start < input.len() {
+        if !iter.skip_value() {
+            return Err(format!("truncated value at offset {}", start).into());
+        }
+        values.push(&input[start..iter.cursor()]);
+        start = iter.cursor();
+    }
+    Ok(values)
+}
+
+/// serializes a value as JSON with map entries in wire order, where
+/// `serde_json::Map` would sort the keys and merge duplicates
+struct Ordered<'a, 'lt>(&'a ValueRef<'lt>, &'a JsonPolicy);
+
+impl serde::Serialize for Ordered<'_, '_> {
+    fn serialize<S>(&self, s: S) -> std::result::Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        use serde::ser::{Error, SerializeMap, SerializeSeq};
+        let Ordered(value, policy) = *self;
+        match value {
+            ValueRef::Arr(arr) => {
+                let mut seq = s.serialize_seq(Some(arr.len()))?;
+                for item in arr {
+                    seq.serialize_element(&Ordered(item, policy))?;
+                }
+                seq.end()
+            }
+            ValueRef::Map(map) => {
+                let mut obj = s.serialize_map(Some(map.len()))?;
+                for (k, v) in map {
+                    let k = json_key(k, policy).map_err(S::Error::custom)?;
+                    obj.serialize_entry(&k, &Ordered(v, policy))?;
+                }
+                obj.end()
+            }
+            _ => value
+                .to_json_with(policy)
+                .map_err(S::Error::custom)?
+                .serialize(s),
+        }
+    }
+}
+
+/// the JSON object key for a map key, as `to_json_with` converts it
+fn json_key(key: &ValueRef<'_>, policy: &JsonPolicy) -> Result<String> {
+    match key {
+        ValueRef::Str(s) => Ok(s.as_str()?.into()),
+        _ if policy.key == KeyPolicy::Error => {
+            Err("non-string map key is not allowed by JsonPolicy".into())
+        }
+        _ => Ok(key.to_json_with(policy)?.to_string()),
+    }
+}
+
+impl JsonOut {
+    fn write(&self, value: &ValueRef<'_>, out: &mut dyn Write) -> Result<()> {
+        let json = Ordered(value, &self.policy);
+        if self.pretty {
+            serde_json::to_writer_pretty(&mut *out, &json)?;
+        } else {
+            serde_json::to_writer(&mut *out, &json)?;
+        }
+        out.write_all(b"\n")?;
+        Ok(())
+    }
+}
+
+impl Validate {
+    fn run(&self, input: &[u8], out: &mut dyn Write) -> Result<()> {
+        let values = split(input)?;
+        if values.is_empty() {
+            return Err("no msgpack values in input".into());
+        }
+        if self.strict && values.len() > 1 {
+            return Err(format!(
+                "trailing data after the first value at offset {}",
+                values[0].len()
+            )
+            .into());
+        }
+        let mut config = Config::default();
+        config.max_depth = self.max_depth;
+        config.canonical = self.canonical;
+        config.validate_utf8 = self.strict;
+        let mut offset = 0;
+        for data in values.iter() {
+            let res = self.check_limits(data, offset).and_then(|_| {
+                ValueRef::from_ref_config(*data, &config)?;
+                Ok(())
+            });
+            if let Err(e) = res {
+                return Err(format!("value at offset {}: {}", offset, e).into());
+            }
+            offset += data.len();
+        }
+        writeln!(out, "ok: {} value(s), {} bytes", values.len(), input.len())?;
+        Ok(())
+    }
+
+    /// check depth, lengths, and (if strict) reserved markers without
+    /// recursion, so deeply nested input cannot overflow the stack.
+    /// `base` is the offset of `data` in the input, so reported offsets
+    /// are absolute
+    fn check_limits(&self, data: &[u8], base: usize) -> Result<()> {
+        let mut dec = Decoder::new();
+        let mut iter = dec.parse(data);
+        // the count of items remaining in each open container
+        let mut stack = vec![1_u64];
+        while let Some(remaining) = stack.last_mut() {
+            if *remaining == 0 {
+                stack.pop();
+                continue;
+            }
+            *remaining -= 1;
+            let offset = base + iter.cursor();
+            let (token, format) =
+                iter.next_with_format().ok_or("truncated value")?;
+            if self.strict && format == Some(Format::Reserved) {
+                return Err(format!(
+                    "reserved marker 0xc1 at offset {}",
+                    offset
+                )
+                .into());
+            }
+            if let Token::Len(t, len) = token {
+                if matches!(self.max_len, Some(max) if len > max) {
+                    return Err(format!(
+                        "length {} exceeds --max-len at offset {}",
+                        len, offset
+                    )
+                    .into());
+                }
+                match t {
+                    LenType::Arr | LenType::Map => {
+                        if stack.len() > self.max_depth {
+                            return Err(format!(
+                                "nesting exceeds --max-depth at offset {}",
+                                offset
+                            )
+                            .into());
+                        }
+                        let len = len as u64;
+                        stack.push(if t == LenType::Map {
+                            len * 2
+                        } else {
+                            len
+                        });
+                    }
+                    // the payload, known to be complete from `split`
+                    _ => {
+                        iter.next();
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test;
@@ -0,0 +1,160 @@
+use super::*;
+
+fn args(args: &[&str]) -> Result<(Cmd, Option<String>)> {
+    let args: Vec<String> = args.iter().map(|a| a.to_string()).collect();
+    parse_args(&args)
+}
+
+fn run_args(cmd: &[&str], input: &[u8]) -> Result<Vec<u8>> {
+    let (cmd, _) = args(cmd)?;
+    let mut out = Vec::new();
+    run(&cmd, input, &mut out)?;
+    Ok(out)
+}
+
+// {
+//     "users": [{ "name": "bob", "a/b": bin("hi"), 7: u64::MAX }],
+//     "nan": f64::NAN,
+// }
+// "two"
+const FIXTURE_CLI: &[u8] = &[
+    0x82, 0xa5, 0x75, 0x73, 0x65, 0x72, 0x73, 0x91, 0x83, 0xa4, 0x6e, 0x61,
+    0x6d, 0x65, 0xa3, 0x62, 0x6f, 0x62, 0xa3, 0x61, 0x2f, 0x62, 0xc4, 0x02,
+    0x68, 0x69, 0x07, 0xcf, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff,
+    0xa3, 0x6e, 0x61, 0x6e, 0xcb, 0x7f, 0xf8, 0x00, 0x00, 0x00, 0x00, 0x00,
+    0x00, 0xa3, 0x74, 0x77, 0x6f,
+];
+
+#[test]
+fn cli_parse_args() {
+    assert_eq!(Cmd::Help, args(&["--help"]).unwrap().0);
+    assert_eq!(Cmd::Help, args(&["to-json", "-h"]).unwrap().0);
+    assert_eq!(
+        (Cmd::FromJson, Some("x.json".to_string())),
+        args(&["from-json", "x.json"]).unwrap(),
+    );
+    assert_eq!(
+        Cmd::Get {
+            path: "/a~1b/3/~0".into(),
+            out: None,
+        },
+        args(&["get", "/a~1b/3/~0", "--msgpack"]).unwrap().0,
+    );
+    assert!(args(&[]).is_err());
+    assert!(args(&["nope"]).is_err());
+    assert!(args(&["get"]).is_err());
+    assert!(args(&["to-json", "--bin"]).is_err());
+    assert!(args(&["to-json", "--bin", "nope"]).is_err());
+    assert!(args(&["to-json", "--strict"]).is_err());
+    assert!(args(&["get", "/a", "--msgpack", "--pretty"]).is_err());
+    assert!(args(&["inspect", "a", "b"]).is_err());
+}
+
+#[test]
+fn cli_json() {
+    let data = FIXTURE_CLI;
+    let out = run_args(&["to-json"], data).unwrap();
+    assert_eq!(
+        "{\"users\":[{\"name\":\"bob\",\"a/b\":\"aGk=\",\
+         \"7\":18446744073709551615}],\"nan\":null}\n\"two\"\n",
+        String::from_utf8(out.clone()).unwrap(),
+    );
+    let out = run_args(&["to-json", "--bin", "hex"], data).unwrap();
+    assert!(String::from_utf8(out).unwrap().contains("\"6869\""));
+    assert!(run_args(&["to-json", "--big-int", "error"], data).is_err());
+    assert!(run_args(&["to-json"], &data[..data.len() - 1]).is_err());
+
+    // map entries are written in wire order, duplicates are kept
+    let dup = [0x82, 0xa1, b'b', 0x01, 0xa1, b'b', 0x02];
+    let out = run_args(&["to-json"], &dup).unwrap();
+    assert_eq!("{\"b\":1,\"b\":2}\n", String::from_utf8(out).unwrap());
+    let out = run_args(&["to-json", "--pretty"], &dup).unwrap();
+    assert_eq!(
+        "{\n  \"b\": 1,\n  \"b\": 2\n}\n",
+        String::from_utf8(out).unwrap(),
+    );
+
+    let json = b"{\"a\": [1, true]}\n\"two\"\n";
+    let out = run_args(&["from-json"], json).unwrap();
+    let mut expect =
+        Value::Map(vec![("a".into(), Value::Arr(vec![1.into(), true.into()]))])
+            .to_bytes()
+            .unwrap();
+    expect.extend(Value::from("two").to_bytes().unwrap());
+    assert_eq!(expect, out);
+    let out = run_args(&["from-json"], b"{\"b\": 1, \"a\": 2}").unwrap();
+    let expect =
+        Value::Map(vec![("b".into(), 1.into()), ("a".into(), 2.into())]);
+    assert_eq!(expect.to_bytes().unwrap(), out);
+    assert!(run_args(&["from-json"], b"{").is_err());
+}
+
+#[test]
+fn cli_get() {
+    let data = FIXTURE_CLI;
+    assert!(run_args(&["get", "/users/0/name"], data).is_err());
+
+    let data = &data[..data.len() - 4];
+    let get = |path| {
+        String::from_utf8(run_args(&["get", path], data).unwrap()).unwrap()
+    };
+    assert_eq!("\"bob\"\n", get("/users/0/name"));
+    assert_eq!("\"aGk=\"\n", get("/users/0/a~1b"));
+    assert_eq!("18446744073709551615\n", get("/users/0/7"));
+    assert!(run_args(&["get", "/users/1"], data).is_err());
+    assert_eq!(
+        Value::from("bob").to_bytes().unwrap(),
+        run_args(&["get", "/users/0/name", "--msgpack"], data).unwrap(),
+    );
+    assert_eq!("\"bob\"\n", get("users/0/name"));
+    assert_eq!(data, run_args(&["get", "", "--msgpack"], data).unwrap());
+    assert_eq!(data, run_args(&["get", "/", "--msgpack"], data).unwrap());
+}
+
+#[test]
+fn cli_inspect() {
+    let out = run_args(&["inspect"], &[0x91, 0xc3]).unwrap();
+    assert_eq!(
+        "\
+00000000  91                          fixarray(1)
+00000001  c3                            true
+",
+        String::from_utf8(out).unwrap(),
+    );
+}
+
+#[test]
+fn cli_validate() {
+    let data = FIXTURE_CLI;
+    assert_eq!(
+        format!("ok: 2 value(s), {} bytes\n", data.len()),
+        String::from_utf8(run_args(&["validate"], data).unwrap()).unwrap(),
+    );
+    assert!(run_args(&["validate", "--strict"], data).is_err());
+    assert!(run_args(&["validate", "--max-depth", "2"], data).is_err());
+    assert!(run_args(&["validate", "--max-depth", "3"], data).is_ok());
+    assert!(run_args(&["validate", "--max-len", "4"], data).is_err());
+    assert!(run_args(&["validate", "--max-len", "5"], data).is_ok());
+    assert!(run_args(&["validate"], &[]).is_err());
+
+    // offsets are absolute in the input, not relative to the value
+    let two = [0xc0, 0x91, 0xa5, b'h', b'e', b'l', b'l', b'o'];
+    assert_eq!(
+        "value at offset 1: length 5 exceeds --max-len at offset 2",
+        run_args(&["validate", "--max-len", "4"], &two)
+            .unwrap_err()
+            .to_string(),
+    );
+    assert!(run_args(&["validate"], &data[..data.len() - 1]).is_err());
+
+    // non-canonical map key order
+    let map = [0x82, 0xa1, b'b', 0xc0, 0xa1, b'a', 0xc0];
+    assert!(run_args(&["validate", "--strict"], &map).is_ok());
+    assert!(run_args(&["validate", "--canonical"], &map).is_err());
+
+    // reserved marker and invalid utf8
+    assert!(run_args(&["validate"], &[0xc1]).is_ok());
+    assert!(run_args(&["validate", "--strict"], &[0xc1]).is_err());
+    assert!(run_args(&["validate"], &[0xa1, 0xff]).is_ok());
+    assert!(run_args(&["validate", "--strict"], &[0xa1, 0xff]).is_err());
+}
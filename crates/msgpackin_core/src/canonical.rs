@@ -0,0 +1,191 @@
+//! canonical / minimal-encoding validation
+//!
+//! MessagePack data is considered canonical if it is byte-for-byte
+//! identical to what the `Encoder` would produce for the same values,
+//! with floats in their most compact representation, and with map keys
+//! ordered by their encoded bytes. In legacy `Compat` modes, lengths
+//! are checked against the legacy encoding, which never uses `str 8`.
+//!
+//! # Example
+//!
+//! ```
+//! use msgpackin_core::canonical::*;
+//! use msgpackin_core::encode::Compat;
+//!
+//! // the number `5` encoded as a `u32`
+//! let buf = [0x92, 0x01, 0xce, 0x00, 0x00, 0x00, 0x05];
+//!
+//! assert_eq!(
+//!     Some(Violation {
+//!         offset: 2,
+//!         reason: Reason::Int,
+//!     }),
+//!     check(&buf, 1024, Compat::Current),
+//! );
+//!
+//! assert_eq!(None, check(&[0x92, 0x01, 0x05], 1024, Compat::Current));
+//! ```
+
+use crate::const_::*;
+use crate::decode::*;
+use crate::encode::*;
+
+/// The reason a message was found to be non-canonical
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Reason {
+    /// An integer was encoded with a wider marker than required
+    Int,
+
+    /// A float was encoded that could be represented
+    /// as an integer, or as a narrower float
+    Float,
+
+    /// A bin / str / arr / map / ext length header
+    /// was encoded with a wider marker than required
+    Len,
+
+    /// The reserved marker (`0xc1`) was used
+    Reserved,
+
+    /// Map keys are not in strictly ascending order of their encoded bytes.
+    /// This includes duplicate keys
+    KeyOrder,
+
+    /// The buffer ended before the value was complete
+    Truncated,
+
+    /// Additional bytes follow the top-level value
+    Trailing,
+
+    /// Container nesting exceeded the maximum depth
+    Depth,
+}
+
+/// Details of the first non-canonical encoding found in a message
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Violation {
+    /// The byte offset of the offending marker
+    pub offset: usize,
+
+    /// Why the encoding at `offset` is not canonical
+    pub reason: Reason,
+}
+
+/// Check that `buf` contains exactly one canonically encoded value,
+/// with containers nested no more than `max_depth` deep, as encoded
+/// for the `compat` spec revision.
+/// Returns `None` if the message is canonical, otherwise
+/// the first violation found
+pub fn check(
+    buf: &[u8],
+    max_depth: usize,
+    compat: Compat,
+) -> Option<Violation> {
+    let mut dec = Decoder::with_compat(compat);
+    let mut check = Check {
+        buf,
+        iter: dec.parse(buf),
+        enc: Encoder::new().with_compat(compat),
+        max_depth,
+    };
+    if let Err(violation) = check.value(0) {
+        return Some(violation);
+    }
+    if check.iter.cursor() < buf.len() {
+        return Some(Violation {
+            offset: check.iter.cursor(),
+            reason: Reason::Trailing,
+        });
+    }
+    None
+}
+
+struct Check<'dec, 'buf> {
+    buf: &'buf [u8],
+    iter: TokenIter<'dec, 'buf>,
+    enc: Encoder,
+    max_depth: usize,
+}
+
+impl<'dec, 'buf> Check<'dec, 'buf> {
+    fn value(&mut self, depth: usize) -> Result<(), Violation> {
+        let offset = self.iter.cursor();
+        let err = |reason| Err(Violation { offset, reason });
+        let token = match self.iter.next() {
+            Some(token) => token,
+            None => return err(Reason::Truncated),
+        };
+        let raw = &self.buf[offset..self.iter.cursor()];
+        match token {
+            Token::Nil if raw[0] == C_RES => err(Reason::Reserved),
+            Token::Nil | Token::Bool(_) => Ok(()),
+            Token::Num(n) => {
+                let is_float = raw[0] == C_F32 || raw[0] == C_F64;
+                if is_float && *self.enc.enc_num(n.compact()) != *raw {
+                    err(Reason::Float)
+                } else if !is_float && *self.enc.enc_num(n) != *raw {
+                    err(Reason::Int)
+                } else {
+                    Ok(())
+                }
+            }
+            Token::Len(t, len) => {
+                let header = match t {
+                    LenType::Bin => self.enc.enc_bin_len(len),
+                    LenType::Str => self.enc.enc_str_len(len),
+                    LenType::Arr => self.enc.enc_arr_len(len),
+                    LenType::Map => self.enc.enc_map_len(len),
+                    LenType::Ext(ext_type) => {
+                        self.enc.enc_ext_len(len, ext_type)
+                    }
+                };
+                if *header != *raw {
+                    return err(Reason::Len);
+                }
+                match t {
+                    LenType::Arr | LenType::Map if depth >= self.max_depth => {
+                        err(Reason::Depth)
+                    }
+                    LenType::Arr => {
+                        for _ in 0..len {
+                            self.value(depth + 1)?;
+                        }
+                        Ok(())
+                    }
+                    LenType::Map => self.map(depth, len),
+                    _ => {
+                        let offset = self.iter.cursor();
+                        match self.iter.next() {
+                            Some(Token::Bin(_)) => Ok(()),
+                            _ => Err(Violation {
+                                offset,
+                                reason: Reason::Truncated,
+                            }),
+                        }
+                    }
+                }
+            }
+            Token::Bin(_) | Token::BinCont(_, _) => err(Reason::Truncated),
+        }
+    }
+
+    fn map(&mut self, depth: usize, len: u32) -> Result<(), Violation> {
+        let mut prev_key: Option<&'buf [u8]> = None;
+        for _ in 0..len {
+            let offset = self.iter.cursor();
+            self.value(depth + 1)?;
+            let key = &self.buf[offset..self.iter.cursor()];
+            if let Some(prev_key) = prev_key {
+                if key <= prev_key {
+                    return Err(Violation {
+                        offset,
+                        reason: Reason::KeyOrder,
+                    });
+                }
+            }
+            prev_key = Some(key);
+            self.value(depth + 1)?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,487 @@
+//! `const fn` encoding, for building msgpack bytes at compile time
+//!
+//! These functions produce the same bytes as their `Encoder` counterparts,
+//! but can be evaluated in `const` contexts. `ConstBuf` composes them into
+//! complete values, and the `msgpack_const!` macro builds a
+//! `&'static [u8]` from a literal structure:
+//!
+//! ```
+//! use msgpackin_core::msgpack_const;
+//!
+//! const RPC_PREFIX: &[u8] = msgpack_const!([0, 42, "ping"]);
+//!
+//! assert_eq!(&[0x93, 0x00, 0x2a, 0xa4, b'p', b'i', b'n', b'g'], RPC_PREFIX);
+//! ```
+
+use crate::const_::*;
+use crate::encode::{VarBytes, VbPriv};
+
+/// Encode msgpack bytes for `nil`
+pub const fn enc_nil() -> VarBytes {
+    VarBytes(VbPriv::B1([C_NIL]))
+}
+
+/// Encode msgpack bytes for `bool`
+pub const fn enc_bool(b: bool) -> VarBytes {
+    if b {
+        VarBytes(VbPriv::B1([C_TRUE]))
+    } else {
+        VarBytes(VbPriv::B1([C_FALSE]))
+    }
+}
+
+/// Encode msgpack bytes for an unsigned integer
+pub const fn enc_u64(u: u64) -> VarBytes {
+    enc_int(u as i128)
+}
+
+/// Encode msgpack bytes for a signed integer
+pub const fn enc_i64(i: i64) -> VarBytes {
+    enc_int(i as i128)
+}
+
+/// Encode msgpack bytes for arbitrary binary byte length
+pub const fn enc_bin_len(len: u32) -> VarBytes {
+    if len < 256 {
+        VarBytes(VbPriv::B2([C_BIN8, len as u8]))
+    } else if len < 65536 {
+        be16(C_BIN16, len as u16)
+    } else {
+        be32(C_BIN32, len)
+    }
+}
+
+/// Encode msgpack bytes for utf8 string data byte length
+pub const fn enc_str_len(len: u32) -> VarBytes {
+    if len < 32 {
+        VarBytes(VbPriv::B1([C_FIXSTR0 | (len as u8 & 0x1f)]))
+    } else if len < 256 {
+        VarBytes(VbPriv::B2([C_STR8, len as u8]))
+    } else if len < 65536 {
+        be16(C_STR16, len as u16)
+    } else {
+        be32(C_STR32, len)
+    }
+}
+
+/// Encode msgpack bytes for a pre-2013 "raw" byte length
+/// (fixstr / str16 / str32 only, see `Compat::LegacyRawStr`)
+pub const fn enc_raw_len(len: u32) -> VarBytes {
+    if len < 32 {
+        VarBytes(VbPriv::B1([C_FIXSTR0 | (len as u8 & 0x1f)]))
+    } else if len < 65536 {
+        be16(C_STR16, len as u16)
+    } else {
+        be32(C_STR32, len)
+    }
+}
+
+/// Encode msgpack bytes for array marker / length
+pub const fn enc_arr_len(len: u32) -> VarBytes {
+    if len < 16 {
+        VarBytes(VbPriv::B1([C_FIXARR0 | (len as u8 & 0x0f)]))
+    } else if len < 65536 {
+        be16(C_ARR16, len as u16)
+    } else {
+        be32(C_ARR32, len)
+    }
+}
+
+/// Encode msgpack bytes for map marker / length.
+/// This should be the number of key/value pairs in the map
+pub const fn enc_map_len(len: u32) -> VarBytes {
+    if len < 16 {
+        VarBytes(VbPriv::B1([C_FIXMAP0 | (len as u8 & 0x0f)]))
+    } else if len < 65536 {
+        be16(C_MAP16, len as u16)
+    } else {
+        be32(C_MAP32, len)
+    }
+}
+
+/// Encode msgpack bytes for arbitrary msgpack ext byte length
+pub const fn enc_ext_len(len: u32, t: i8) -> VarBytes {
+    let t = t as u8;
+    match len {
+        1 => VarBytes(VbPriv::B2([C_FIXEXT1, t])),
+        2 => VarBytes(VbPriv::B2([C_FIXEXT2, t])),
+        4 => VarBytes(VbPriv::B2([C_FIXEXT4, t])),
+        8 => VarBytes(VbPriv::B2([C_FIXEXT8, t])),
+        16 => VarBytes(VbPriv::B2([C_FIXEXT16, t])),
+        _ if len < 256 => VarBytes(VbPriv::B3([C_EXT8, len as u8, t])),
+        _ if len < 65536 => {
+            let b = (len as u16).to_be_bytes();
+            VarBytes(VbPriv::B4([C_EXT16, b[0], b[1], t]))
+        }
+        _ => {
+            let b = len.to_be_bytes();
+            VarBytes(VbPriv::B6([C_EXT32, b[0], b[1], b[2], b[3], t]))
+        }
+    }
+}
+
+const fn be16(marker: u8, v: u16) -> VarBytes {
+    let b = v.to_be_bytes();
+    VarBytes(VbPriv::B3([marker, b[0], b[1]]))
+}
+
+const fn be32(marker: u8, v: u32) -> VarBytes {
+    let b = v.to_be_bytes();
+    VarBytes(VbPriv::B5([marker, b[0], b[1], b[2], b[3]]))
+}
+
+const fn be64(marker: u8, v: u64) -> VarBytes {
+    let b = v.to_be_bytes();
+    VarBytes(VbPriv::B9([
+        marker, b[0], b[1], b[2], b[3], b[4], b[5], b[6], b[7],
+    ]))
+}
+
+/// integers use the smallest format that can hold them,
+/// preferring unsigned formats for positive values
+#[allow(clippy::manual_range_contains)]
+const fn enc_int(i: i128) -> VarBytes {
+    if i >= 0 && i < 128 {
+        VarBytes(VbPriv::B1([i as u8]))
+    } else if i >= -32 && i < 0 {
+        VarBytes(VbPriv::B1([i as i8 as u8]))
+    } else if i >= u8::MIN as i128 && i <= u8::MAX as i128 {
+        VarBytes(VbPriv::B2([C_U8, i as u8]))
+    } else if i >= i8::MIN as i128 && i <= i8::MAX as i128 {
+        VarBytes(VbPriv::B2([C_I8, i as u8]))
+    } else if i >= u16::MIN as i128 && i <= u16::MAX as i128 {
+        be16(C_U16, i as u16)
+    } else if i >= i16::MIN as i128 && i <= i16::MAX as i128 {
+        be16(C_I16, i as i16 as u16)
+    } else if i >= u32::MIN as i128 && i <= u32::MAX as i128 {
+        be32(C_U32, i as u32)
+    } else if i >= i32::MIN as i128 && i <= i32::MAX as i128 {
+        be32(C_I32, i as i32 as u32)
+    } else if i >= u64::MIN as i128 && i <= u64::MAX as i128 {
+        be64(C_U64, i as u64)
+    } else if i >= i64::MIN as i128 && i <= i64::MAX as i128 {
+        be64(C_I64, i as i64 as u64)
+    } else {
+        panic!("integer out of msgpack range")
+    }
+}
+
+/// Maximum container nesting depth supported by `ConstBuf`
+pub const CONST_BUF_MAX_DEPTH: usize = 32;
+
+/// Compile-time msgpack buffer builder.
+///
+/// Each method consumes and returns the builder, so calls chain in
+/// `const` contexts. Array and map headers are inserted when the
+/// container is closed, so item counts never need to be supplied.
+///
+/// Bytes beyond capacity `N` are dropped, but `len()` still reports the
+/// full encoded length. This allows a `ConstBuf<0>` to measure a value,
+/// and a second pass with exactly that capacity to produce it.
+#[derive(Debug, Clone, Copy)]
+pub struct ConstBuf<const N: usize> {
+    buf: [u8; N],
+    len: usize,
+    depth: usize,
+    // (is_map, header offset, item count) per open container
+    open: [(bool, usize, u32); CONST_BUF_MAX_DEPTH],
+    top: u32,
+}
+
+impl<const N: usize> Default for ConstBuf<N> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<const N: usize> ConstBuf<N> {
+    /// Construct a new, empty builder
+    pub const fn new() -> Self {
+        Self {
+            buf: [0; N],
+            len: 0,
+            depth: 0,
+            open: [(false, 0, 0); CONST_BUF_MAX_DEPTH],
+            top: 0,
+        }
+    }
+
+    /// Full encoded length of everything written so far
+    pub const fn len(&self) -> usize {
+        self.len
+    }
+
+    /// True if nothing has been written
+    pub const fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Number of complete top-level values written
+    pub const fn value_count(&self) -> u32 {
+        self.top
+    }
+
+    /// Write `nil`
+    pub const fn nil(self) -> Self {
+        self.value(enc_nil().as_bytes(), b"")
+    }
+
+    /// Write a `bool`
+    pub const fn bool(self, b: bool) -> Self {
+        self.value(enc_bool(b).as_bytes(), b"")
+    }
+
+    /// Write an unsigned integer
+    pub const fn uint(self, u: u64) -> Self {
+        self.value(enc_u64(u).as_bytes(), b"")
+    }
+
+    /// Write a signed integer
+    pub const fn int(self, i: i64) -> Self {
+        self.value(enc_i64(i).as_bytes(), b"")
+    }
+
+    /// Write a utf8 string
+    pub const fn str(self, s: &str) -> Self {
+        let s = s.as_bytes();
+        self.value(enc_str_len(payload_len(s)).as_bytes(), s)
+    }
+
+    /// Write binary data
+    pub const fn bin(self, data: &[u8]) -> Self {
+        self.value(enc_bin_len(payload_len(data)).as_bytes(), data)
+    }
+
+    /// Write ext data of type `t`
+    pub const fn ext(self, t: i8, data: &[u8]) -> Self {
+        self.value(enc_ext_len(payload_len(data), t).as_bytes(), data)
+    }
+
+    /// Open an array. Subsequent values are its items until `end_arr()`
+    pub const fn begin_arr(self) -> Self {
+        self.begin(false)
+    }
+
+    /// Close the innermost open array, inserting its header
+    pub const fn end_arr(self) -> Self {
+        self.end(false)
+    }
+
+    /// Open a map. Subsequent values alternate key, value until
+    /// `end_map()`
+    pub const fn begin_map(self) -> Self {
+        self.begin(true)
+    }
+
+    /// Close the innermost open map, inserting its header
+    pub const fn end_map(self) -> Self {
+        self.end(true)
+    }
+
+    /// Extract the encoded bytes. Panics (at compile time, in a `const`
+    /// context) if containers are still open, or the encoded length
+    /// is not exactly `N`
+    pub const fn into_array(self) -> [u8; N] {
+        if self.depth != 0 {
+            panic!("unclosed msgpack container");
+        }
+        if self.len != N {
+            panic!("ConstBuf capacity does not match encoded length");
+        }
+        self.buf
+    }
+
+    const fn value(mut self, head: &[u8], data: &[u8]) -> Self {
+        self = self.count();
+        self = self.put(head);
+        self.put(data)
+    }
+
+    const fn count(mut self) -> Self {
+        if self.depth == 0 {
+            self.top += 1;
+        } else {
+            self.open[self.depth - 1].2 += 1;
+        }
+        self
+    }
+
+    const fn put(mut self, data: &[u8]) -> Self {
+        let mut i = 0;
+        while i < data.len() {
+            if self.len < N {
+                self.buf[self.len] = data[i];
+            }
+            self.len += 1;
+            i += 1;
+        }
+        self
+    }
+
+    const fn begin(mut self, is_map: bool) -> Self {
+        if self.depth == CONST_BUF_MAX_DEPTH {
+            panic!("msgpack containers nested too deeply");
+        }
+        self = self.count();
+        self.open[self.depth] = (is_map, self.len, 0);
+        self.depth += 1;
+        self
+    }
+
+    const fn end(mut self, is_map: bool) -> Self {
+        if self.depth == 0 {
+            panic!("no open msgpack container");
+        }
+        self.depth -= 1;
+        let (open_map, at, count) = self.open[self.depth];
+        if open_map != is_map {
+            panic!("mismatched msgpack container");
+        }
+        let header = if is_map {
+            if count % 2 != 0 {
+                panic!("msgpack map missing a value");
+            }
+            enc_map_len(count / 2)
+        } else {
+            enc_arr_len(count)
+        };
+        self.insert(at, header.as_bytes())
+    }
+
+    /// shift everything after `at` right to make room for `header`
+    const fn insert(mut self, at: usize, header: &[u8]) -> Self {
+        let h = header.len();
+        let mut i = self.len;
+        while i > at {
+            i -= 1;
+            if i + h < N {
+                self.buf[i + h] = self.buf[i];
+            }
+        }
+        let mut i = 0;
+        while i < h {
+            if at + i < N {
+                self.buf[at + i] = header[i];
+            }
+            i += 1;
+        }
+        self.len += h;
+        self
+    }
+}
+
+const fn payload_len(data: &[u8]) -> u32 {
+    if data.len() > u32::MAX as usize {
+        panic!("msgpack payload too large");
+    }
+    data.len() as u32
+}
+
+/// Literal wrapper used by `msgpack_const!` to pick an encoding
+/// based on the literal's type
+#[doc(hidden)]
+pub struct Lit<T>(pub T);
+
+impl Lit<i128> {
+    #[doc(hidden)]
+    pub const fn write<const N: usize>(self, b: ConstBuf<N>) -> ConstBuf<N> {
+        if self.0 < 0 {
+            if self.0 < i64::MIN as i128 {
+                panic!("integer out of msgpack range");
+            }
+            b.int(self.0 as i64)
+        } else {
+            if self.0 > u64::MAX as i128 {
+                panic!("integer out of msgpack range");
+            }
+            b.uint(self.0 as u64)
+        }
+    }
+}
+
+impl Lit<&str> {
+    #[doc(hidden)]
+    pub const fn write<const N: usize>(self, b: ConstBuf<N>) -> ConstBuf<N> {
+        b.str(self.0)
+    }
+}
+
+/// Build a `&'static [u8]` of msgpack bytes from a literal structure,
+/// entirely at compile time.
+///
+/// Supports `nil`, `true`, `false`, integer and string literals,
+/// `bin(<&[u8] const expr>)`, `ext(<i8 const expr>, <&[u8] const expr>)`,
+/// arrays `[a, b, ...]`, and maps `{ k: v, ... }` with any value as keys.
+///
+/// ```
+/// use msgpackin_core::msgpack_const;
+///
+/// const HDR: &[u8] = msgpack_const!({ "id": -1, "tag": bin(b"\x01") });
+///
+/// assert_eq!(
+///     &[0x82, 0xa2, b'i', b'd', 0xff, 0xa3, b't', b'a', b'g', 0xc4, 1, 1],
+///     HDR,
+/// );
+/// ```
+#[macro_export]
+macro_rules! msgpack_const {
+    (@seq $m:ident $b:expr;) => { $b };
+    (@seq $m:ident $b:expr; nil $($rest:tt)*) => {
+        $crate::msgpack_const!(@sep $m $b.nil(); $($rest)*)
+    };
+    (@seq $m:ident $b:expr; true $($rest:tt)*) => {
+        $crate::msgpack_const!(@sep $m $b.bool(true); $($rest)*)
+    };
+    (@seq $m:ident $b:expr; false $($rest:tt)*) => {
+        $crate::msgpack_const!(@sep $m $b.bool(false); $($rest)*)
+    };
+    (@seq $m:ident $b:expr; bin($d:expr) $($rest:tt)*) => {
+        $crate::msgpack_const!(@sep $m $b.bin($d); $($rest)*)
+    };
+    (@seq $m:ident $b:expr; ext($t:expr, $d:expr) $($rest:tt)*) => {
+        $crate::msgpack_const!(@sep $m $b.ext($t, $d); $($rest)*)
+    };
+    (@seq $m:ident $b:expr; - $l:literal $($rest:tt)*) => {
+        $crate::msgpack_const!(
+            @sep $m $crate::const_enc::Lit(-$l).write($b); $($rest)*
+        )
+    };
+    (@seq $m:ident $b:expr; $l:literal $($rest:tt)*) => {
+        $crate::msgpack_const!(
+            @sep $m $crate::const_enc::Lit($l).write($b); $($rest)*
+        )
+    };
+    (@seq $m:ident $b:expr; [$($inner:tt)*] $($rest:tt)*) => {
+        $crate::msgpack_const!(
+            @sep $m $crate::msgpack_const!(
+                @seq arr $b.begin_arr(); $($inner)*
+            ).end_arr(); $($rest)*
+        )
+    };
+    (@seq $m:ident $b:expr; {$($inner:tt)*} $($rest:tt)*) => {
+        $crate::msgpack_const!(
+            @sep $m $crate::msgpack_const!(
+                @seq key $b.begin_map(); $($inner)*
+            ).end_map(); $($rest)*
+        )
+    };
+    (@sep $m:ident $b:expr;) => { $b };
+    (@sep arr $b:expr; , $($rest:tt)*) => {
+        $crate::msgpack_const!(@seq arr $b; $($rest)*)
+    };
+    (@sep key $b:expr; : $($rest:tt)*) => {
+        $crate::msgpack_const!(@seq val $b; $($rest)*)
+    };
+    (@sep val $b:expr; , $($rest:tt)*) => {
+        $crate::msgpack_const!(@seq key $b; $($rest)*)
+    };
+    ($($v:tt)+) => {{
+        const LEN: usize = $crate::msgpack_const!(
+            @seq top $crate::const_enc::ConstBuf::<0>::new(); $($v)+
+        ).len();
+        const OUT: [u8; LEN] = $crate::msgpack_const!(
+            @seq top $crate::const_enc::ConstBuf::<LEN>::new(); $($v)+
+        ).into_array();
+        &OUT as &'static [u8]
+    }};
+}
@@ -0,0 +1,609 @@
+//! `feature=serde` no-alloc serde Deserializer reading from a byte slice
+//!
+//! Strings and binary data are borrowed from the input.
+//!
+//! ```
+//! # #[cfg(feature = "serde")]
+//! # {
+//! use msgpackin_core::de::*;
+//!
+//! let buf = b"\x92\xa5hello\x93\x01\x02\x03";
+//! let (s, arr): (&str, [u8; 3]) = from_slice(buf).unwrap();
+//! assert_eq!("hello", s);
+//! assert_eq!([1, 2, 3], arr);
+//!
+//! let short = from_slice::<(&str, [u8; 3])>(&buf[..4]);
+//! assert_eq!(Err(DeError::Truncated), short);
+//! # }
+//! ```
+
+use crate::decode::*;
+use crate::num::Num;
+use crate::EXT_STRUCT_NAME;
+use serde::de;
+use serde::de::IntoDeserializer;
+use serde::Deserialize;
+
+/// Error produced by the slice `Deserializer`
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum DeError {
+    /// The input ended in the middle of a value
+    Truncated,
+
+    /// The next value was not of the expected type
+    Expected(&'static str),
+
+    /// Containers were nested deeper than the deserializer `max_depth`
+    TooDeep,
+
+    /// An array or map had more items than the `Deserialize`
+    /// implementation consumed
+    InvalidLength,
+
+    /// A custom error was reported by a `Deserialize` implementation
+    Custom,
+}
+
+impl core::fmt::Display for DeError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        core::fmt::Debug::fmt(self, f)
+    }
+}
+
+impl de::StdError for DeError {}
+
+impl de::Error for DeError {
+    fn custom<T: core::fmt::Display>(_msg: T) -> Self {
+        DeError::Custom
+    }
+}
+
+/// Default container nesting limit of the slice `Deserializer`, each
+/// level recurses through serde, so keep it small for small stacks
+pub const DEFAULT_MAX_DEPTH: usize = 32;
+
+/// Deserialize the first value in `buf`, with containers nested no more
+/// than `DEFAULT_MAX_DEPTH` deep.
+/// Any bytes following that value are ignored, see `Deserializer::cursor()`
+pub fn from_slice<'de, T>(buf: &'de [u8]) -> Result<T, DeError>
+where
+    T: Deserialize<'de>,
+{
+    T::deserialize(&mut Deserializer::new(buf))
+}
+
+/// Msgpackin no-alloc serde Deserializer
+pub struct Deserializer<'de> {
+    dec: Decoder,
+    buf: &'de [u8],
+    cursor: usize,
+    depth: usize,
+    max_depth: usize,
+}
+
+impl<'de> Deserializer<'de> {
+    /// Construct a new Deserializer over the given buffer
+    pub fn new(buf: &'de [u8]) -> Self {
+        Self::with_decoder(buf, Decoder::new())
+    }
+
+    /// Construct a new Deserializer using a specifically configured decoder
+    pub fn with_decoder(buf: &'de [u8], dec: Decoder) -> Self {
+        Self {
+            dec,
+            buf,
+            cursor: 0,
+            depth: 0,
+            max_depth: DEFAULT_MAX_DEPTH,
+        }
+    }
+
+    /// Reject containers nested deeper than `max_depth`
+    /// (default `DEFAULT_MAX_DEPTH`) with `DeError::TooDeep`
+    pub fn with_max_depth(mut self, max_depth: usize) -> Self {
+        self.max_depth = max_depth;
+        self
+    }
+
+    /// The number of bytes consumed so far
+    pub fn cursor(&self) -> usize {
+        self.cursor
+    }
+
+    fn next_token(&mut self) -> Result<ValueToken<'de>, DeError> {
+        let buf = self.buf;
+        let mut iter = self.dec.parse(&buf[self.cursor..]).value_tokens();
+        match iter.next() {
+            None
+            | Some(ValueToken::PayloadStart(_, _))
+            | Some(ValueToken::Chunk(_, _)) => Err(DeError::Truncated),
+            Some(token) => {
+                self.cursor += iter.cursor();
+                Ok(token)
+            }
+        }
+    }
+
+    fn peek_kind(&mut self) -> Option<Kind> {
+        let buf = self.buf;
+        self.dec.parse(&buf[self.cursor..]).peek_kind()
+    }
+
+    fn visit_seq<V>(
+        &mut self,
+        len: u32,
+        visitor: V,
+    ) -> Result<V::Value, DeError>
+    where
+        V: de::Visitor<'de>,
+    {
+        self.enter()?;
+        let mut access = Access::new(self, len);
+        let res = visitor
+            .visit_seq(&mut access)
+            .and_then(|value| access.end().map(|_| value));
+        self.depth -= 1;
+        res
+    }
+
+    fn visit_map<V>(
+        &mut self,
+        len: u32,
+        visitor: V,
+    ) -> Result<V::Value, DeError>
+    where
+        V: de::Visitor<'de>,
+    {
+        self.enter()?;
+        let mut access = Access::new(self, len);
+        let res = visitor
+            .visit_map(&mut access)
+            .and_then(|value| access.end().map(|_| value));
+        self.depth -= 1;
+        res
+    }
+
+    fn enter(&mut self) -> Result<(), DeError> {
+        if self.depth >= self.max_depth {
+            return Err(DeError::TooDeep);
+        }
+        self.depth += 1;
+        Ok(())
+    }
+
+    fn num(&mut self, expected: &'static str) -> Result<Num, DeError> {
+        match self.next_token()? {
+            ValueToken::Num(n) => Ok(n),
+            _ => Err(DeError::Expected(expected)),
+        }
+    }
+}
+
+macro_rules! de_num {
+    ($($f:ident: $t:ty => $v:ident,)*) => {$(
+        fn $f<V>(self, visitor: V) -> Result<V::Value, DeError>
+        where
+            V: de::Visitor<'de>,
+        {
+            let n = self.num(stringify!($t))?;
+            if !n.fits::<$t>() {
+                return Err(DeError::Expected(stringify!($t)));
+            }
+            visitor.$v(n.to())
+        }
+    )*};
+}
+
+impl<'de> de::Deserializer<'de> for &mut Deserializer<'de> {
+    type Error = DeError;
+
+    fn is_human_readable(&self) -> bool {
+        false
+    }
+
+    fn deserialize_any<V>(self, visitor: V) -> Result<V::Value, DeError>
+    where
+        V: de::Visitor<'de>,
+    {
+        match self.next_token()? {
+            ValueToken::Nil => visitor.visit_unit(),
+            ValueToken::Bool(b) => visitor.visit_bool(b),
+            ValueToken::Num(Num::Unsigned(n)) => visitor.visit_u64(n),
+            ValueToken::Num(Num::Signed(n)) => visitor.visit_i64(n),
+            ValueToken::Num(Num::F32(n)) => visitor.visit_f32(n),
+            ValueToken::Num(Num::F64(n)) => visitor.visit_f64(n),
+            ValueToken::Str(s) => visitor.visit_borrowed_str(s),
+            ValueToken::InvalidStr(data) | ValueToken::Bin(data) => {
+                visitor.visit_borrowed_bytes(data)
+            }
+            ValueToken::Ext(t, data) => {
+                visitor.visit_newtype_struct(ExtDeserializer(t, data))
+            }
+            ValueToken::Arr(len) => self.visit_seq(len, visitor),
+            ValueToken::Map(len) => self.visit_map(len, visitor),
+            // next_token() reports partial payloads as truncated
+            _ => Err(DeError::Truncated),
+        }
+    }
+
+    fn deserialize_bool<V>(self, visitor: V) -> Result<V::Value, DeError>
+    where
+        V: de::Visitor<'de>,
+    {
+        match self.next_token()? {
+            ValueToken::Bool(b) => visitor.visit_bool(b),
+            _ => Err(DeError::Expected("bool")),
+        }
+    }
+
+    de_num! {
+        deserialize_i8: i8 => visit_i8,
+        deserialize_i16: i16 => visit_i16,
+        deserialize_i32: i32 => visit_i32,
+        deserialize_i64: i64 => visit_i64,
+        deserialize_u8: u8 => visit_u8,
+        deserialize_u16: u16 => visit_u16,
+        deserialize_u32: u32 => visit_u32,
+        deserialize_u64: u64 => visit_u64,
+        deserialize_f32: f32 => visit_f32,
+        deserialize_f64: f64 => visit_f64,
+    }
+
+    fn deserialize_char<V>(self, visitor: V) -> Result<V::Value, DeError>
+    where
+        V: de::Visitor<'de>,
+    {
+        if let ValueToken::Str(s) = self.next_token()? {
+            let mut iter = s.chars();
+            if let (Some(c), None) = (iter.next(), iter.next()) {
+                return visitor.visit_char(c);
+            }
+        }
+        Err(DeError::Expected("char"))
+    }
+
+    fn deserialize_str<V>(self, visitor: V) -> Result<V::Value, DeError>
+    where
+        V: de::Visitor<'de>,
+    {
+        match self.next_token()? {
+            ValueToken::Str(s) => visitor.visit_borrowed_str(s),
+            ValueToken::InvalidStr(data) => visitor.visit_borrowed_bytes(data),
+            _ => Err(DeError::Expected("str")),
+        }
+    }
+
+    fn deserialize_string<V>(self, visitor: V) -> Result<V::Value, DeError>
+    where
+        V: de::Visitor<'de>,
+    {
+        self.deserialize_str(visitor)
+    }
+
+    fn deserialize_bytes<V>(self, visitor: V) -> Result<V::Value, DeError>
+    where
+        V: de::Visitor<'de>,
+    {
+        match self.next_token()? {
+            ValueToken::Bin(data) => visitor.visit_borrowed_bytes(data),
+            _ => Err(DeError::Expected("bin")),
+        }
+    }
+
+    fn deserialize_byte_buf<V>(self, visitor: V) -> Result<V::Value, DeError>
+    where
+        V: de::Visitor<'de>,
+    {
+        self.deserialize_bytes(visitor)
+    }
+
+    fn deserialize_option<V>(self, visitor: V) -> Result<V::Value, DeError>
+    where
+        V: de::Visitor<'de>,
+    {
+        match self.peek_kind() {
+            Some(Kind::Nil) => {
+                self.next_token()?;
+                visitor.visit_none()
+            }
+            _ => visitor.visit_some(self),
+        }
+    }
+
+    fn deserialize_unit<V>(self, visitor: V) -> Result<V::Value, DeError>
+    where
+        V: de::Visitor<'de>,
+    {
+        match self.next_token()? {
+            ValueToken::Nil => visitor.visit_unit(),
+            _ => Err(DeError::Expected("unit")),
+        }
+    }
+
+    fn deserialize_unit_struct<V>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, DeError>
+    where
+        V: de::Visitor<'de>,
+    {
+        self.deserialize_unit(visitor)
+    }
+
+    fn deserialize_newtype_struct<V>(
+        self,
+        name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, DeError>
+    where
+        V: de::Visitor<'de>,
+    {
+        if name == EXT_STRUCT_NAME && self.peek_kind() == Some(Kind::Ext) {
+            if let ValueToken::Ext(t, data) = self.next_token()? {
+                return visitor.visit_newtype_struct(ExtDeserializer(t, data));
+            }
+        }
+        visitor.visit_newtype_struct(self)
+    }
+
+    fn deserialize_seq<V>(self, visitor: V) -> Result<V::Value, DeError>
+    where
+        V: de::Visitor<'de>,
+    {
+        match self.next_token()? {
+            ValueToken::Arr(len) => self.visit_seq(len, visitor),
+            _ => Err(DeError::Expected("seq")),
+        }
+    }
+
+    fn deserialize_tuple<V>(
+        self,
+        _len: usize,
+        visitor: V,
+    ) -> Result<V::Value, DeError>
+    where
+        V: de::Visitor<'de>,
+    {
+        self.deserialize_seq(visitor)
+    }
+
+    fn deserialize_tuple_struct<V>(
+        self,
+        _name: &'static str,
+        _len: usize,
+        visitor: V,
+    ) -> Result<V::Value, DeError>
+    where
+        V: de::Visitor<'de>,
+    {
+        self.deserialize_seq(visitor)
+    }
+
+    fn deserialize_map<V>(self, visitor: V) -> Result<V::Value, DeError>
+    where
+        V: de::Visitor<'de>,
+    {
+        match self.next_token()? {
+            ValueToken::Map(len) => self.visit_map(len, visitor),
+            _ => Err(DeError::Expected("map")),
+        }
+    }
+
+    fn deserialize_struct<V>(
+        self,
+        _name: &'static str,
+        _fields: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, DeError>
+    where
+        V: de::Visitor<'de>,
+    {
+        self.deserialize_map(visitor)
+    }
+
+    fn deserialize_enum<V>(
+        self,
+        _name: &'static str,
+        _variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, DeError>
+    where
+        V: de::Visitor<'de>,
+    {
+        match self.next_token()? {
+            ValueToken::Str(s) => visitor.visit_enum(s.into_deserializer()),
+            ValueToken::Map(1) => {
+                self.enter()?;
+                let res = visitor.visit_enum(Enum(&mut *self));
+                self.depth -= 1;
+                res
+            }
+            _ => Err(DeError::Expected("str or map(len == 1)")),
+        }
+    }
+
+    fn deserialize_identifier<V>(self, visitor: V) -> Result<V::Value, DeError>
+    where
+        V: de::Visitor<'de>,
+    {
+        self.deserialize_str(visitor)
+    }
+
+    fn deserialize_ignored_any<V>(self, visitor: V) -> Result<V::Value, DeError>
+    where
+        V: de::Visitor<'de>,
+    {
+        let buf = self.buf;
+        let mut iter = self.dec.parse(&buf[self.cursor..]);
+        if !iter.skip_value() {
+            return Err(DeError::Truncated);
+        }
+        self.cursor += iter.cursor();
+        visitor.visit_unit()
+    }
+}
+
+/// seq / map access over the next `len` items (or pairs)
+struct Access<'a, 'de> {
+    de: &'a mut Deserializer<'de>,
+    remaining: u32,
+}
+
+impl<'a, 'de> Access<'a, 'de> {
+    fn new(de: &'a mut Deserializer<'de>, len: u32) -> Self {
+        Self { de, remaining: len }
+    }
+
+    /// the visitor returned, items it did not consume are an error
+    /// rather than being read as the values that follow
+    fn end(&self) -> Result<(), DeError> {
+        if self.remaining == 0 {
+            Ok(())
+        } else {
+            Err(DeError::InvalidLength)
+        }
+    }
+}
+
+impl<'de> de::SeqAccess<'de> for Access<'_, 'de> {
+    type Error = DeError;
+
+    fn next_element_seed<T>(
+        &mut self,
+        seed: T,
+    ) -> Result<Option<T::Value>, DeError>
+    where
+        T: de::DeserializeSeed<'de>,
+    {
+        if self.remaining == 0 {
+            return Ok(None);
+        }
+        self.remaining -= 1;
+        seed.deserialize(&mut *self.de).map(Some)
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        Some(self.remaining as usize)
+    }
+}
+
+impl<'de> de::MapAccess<'de> for Access<'_, 'de> {
+    type Error = DeError;
+
+    fn next_key_seed<K>(&mut self, seed: K) -> Result<Option<K::Value>, DeError>
+    where
+        K: de::DeserializeSeed<'de>,
+    {
+        de::SeqAccess::next_element_seed(self, seed)
+    }
+
+    fn next_value_seed<V>(&mut self, seed: V) -> Result<V::Value, DeError>
+    where
+        V: de::DeserializeSeed<'de>,
+    {
+        seed.deserialize(&mut *self.de)
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        Some(self.remaining as usize)
+    }
+}
+
+/// the single key / value entry of an enum variant map
+struct Enum<'a, 'de>(&'a mut Deserializer<'de>);
+
+impl<'a, 'de> de::EnumAccess<'de> for Enum<'a, 'de> {
+    type Error = DeError;
+    type Variant = Self;
+
+    fn variant_seed<V>(self, seed: V) -> Result<(V::Value, Self), DeError>
+    where
+        V: de::DeserializeSeed<'de>,
+    {
+        let key = seed.deserialize(&mut *self.0)?;
+        Ok((key, self))
+    }
+}
+
+impl<'de> de::VariantAccess<'de> for Enum<'_, 'de> {
+    type Error = DeError;
+
+    fn unit_variant(self) -> Result<(), DeError> {
+        // just ignoring any value that might have been placed here
+        de::Deserializer::deserialize_ignored_any(self.0, de::IgnoredAny)
+            .map(|_| ())
+    }
+
+    fn newtype_variant_seed<T>(self, seed: T) -> Result<T::Value, DeError>
+    where
+        T: de::DeserializeSeed<'de>,
+    {
+        seed.deserialize(self.0)
+    }
+
+    fn tuple_variant<V>(
+        self,
+        _len: usize,
+        visitor: V,
+    ) -> Result<V::Value, DeError>
+    where
+        V: de::Visitor<'de>,
+    {
+        de::Deserializer::deserialize_seq(self.0, visitor)
+    }
+
+    fn struct_variant<V>(
+        self,
+        _fields: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, DeError>
+    where
+        V: de::Visitor<'de>,
+    {
+        de::Deserializer::deserialize_map(self.0, visitor)
+    }
+}
+
+/// presents ext data as an `(i8, bin)` seq
+struct ExtDeserializer<'de>(i8, &'de [u8]);
+
+impl<'de> de::Deserializer<'de> for ExtDeserializer<'de> {
+    type Error = DeError;
+
+    fn deserialize_any<V>(self, visitor: V) -> Result<V::Value, DeError>
+    where
+        V: de::Visitor<'de>,
+    {
+        visitor.visit_seq(ExtAccess(Some(self.0), Some(self.1)))
+    }
+
+    serde::forward_to_deserialize_any! {
+        bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string
+        bytes byte_buf option unit unit_struct newtype_struct seq tuple
+        tuple_struct map struct enum identifier ignored_any
+    }
+}
+
+struct ExtAccess<'de>(Option<i8>, Option<&'de [u8]>);
+
+impl<'de> de::SeqAccess<'de> for ExtAccess<'de> {
+    type Error = DeError;
+
+    fn next_element_seed<T>(
+        &mut self,
+        seed: T,
+    ) -> Result<Option<T::Value>, DeError>
+    where
+        T: de::DeserializeSeed<'de>,
+    {
+        if let Some(t) = self.0.take() {
+            return seed.deserialize(t.into_deserializer()).map(Some);
+        }
+        if let Some(data) = self.1.take() {
+            let data = de::value::BorrowedBytesDeserializer::new(data);
+            return seed.deserialize(data).map(Some);
+        }
+        Ok(None)
+    }
+}
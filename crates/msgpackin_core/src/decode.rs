@@ -1,6 +1,7 @@
 //! decode library code
 
 use crate::const_::*;
+pub use crate::encode::Compat;
 use crate::num::*;
 
 /// MessagePack Rust length markers come in these varieties
@@ -22,6 +23,259 @@ pub enum LenType {
     Ext(i8),
 }
 
+/// The general type of a MessagePack value, as identified by its marker
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Kind {
+    /// MessagePack 'Nil' type (also reported for the reserved marker)
+    Nil,
+
+    /// A boolean value
+    Bool,
+
+    /// An integer or float value
+    Num,
+
+    /// Binary data
+    Bin,
+
+    /// Utf8 string data
+    Str,
+
+    /// An array of message pack objects
+    Arr,
+
+    /// A map of message pack key value pairs
+    Map,
+
+    /// Msgpack ext data
+    Ext,
+}
+
+impl Kind {
+    /// Identify the kind of value introduced by a marker byte
+    pub fn from_marker(m: u8) -> Self {
+        match m {
+            0x00..=0x7f | 0xe0..=0xff => Kind::Num,
+            0x80..=0x8f | C_MAP16 | C_MAP32 => Kind::Map,
+            0x90..=0x9f | C_ARR16 | C_ARR32 => Kind::Arr,
+            0xa0..=0xbf | C_STR8 | C_STR16 | C_STR32 => Kind::Str,
+            C_NIL | C_RES => Kind::Nil,
+            C_FALSE | C_TRUE => Kind::Bool,
+            C_BIN8 | C_BIN16 | C_BIN32 => Kind::Bin,
+            C_EXT8 | C_EXT16 | C_EXT32 => Kind::Ext,
+            C_FIXEXT1 | C_FIXEXT2 | C_FIXEXT4 | C_FIXEXT8 | C_FIXEXT16 => {
+                Kind::Ext
+            }
+            C_F32 | C_F64 | C_U8 | C_U16 | C_U32 | C_U64 => Kind::Num,
+            C_I8 | C_I16 | C_I32 | C_I64 => Kind::Num,
+        }
+    }
+}
+
+/// The specific wire format of a MessagePack marker
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Format {
+    /// positive fixint (`0x00 - 0x7f`)
+    PosFixInt,
+
+    /// fixmap (`0x80 - 0x8f`)
+    FixMap,
+
+    /// fixarray (`0x90 - 0x9f`)
+    FixArr,
+
+    /// fixstr (`0xa0 - 0xbf`)
+    FixStr,
+
+    /// nil (`0xc0`)
+    Nil,
+
+    /// reserved (`0xc1`), decoded as nil
+    Reserved,
+
+    /// false (`0xc2`)
+    False,
+
+    /// true (`0xc3`)
+    True,
+
+    /// bin 8 (`0xc4`)
+    Bin8,
+
+    /// bin 16 (`0xc5`)
+    Bin16,
+
+    /// bin 32 (`0xc6`)
+    Bin32,
+
+    /// ext 8 (`0xc7`)
+    Ext8,
+
+    /// ext 16 (`0xc8`)
+    Ext16,
+
+    /// ext 32 (`0xc9`)
+    Ext32,
+
+    /// float 32 (`0xca`)
+    F32,
+
+    /// float 64 (`0xcb`)
+    F64,
+
+    /// uint 8 (`0xcc`)
+    U8,
+
+    /// uint 16 (`0xcd`)
+    U16,
+
+    /// uint 32 (`0xce`)
+    U32,
+
+    /// uint 64 (`0xcf`)
+    U64,
+
+    /// int 8 (`0xd0`)
+    I8,
+
+    /// int 16 (`0xd1`)
+    I16,
+
+    /// int 32 (`0xd2`)
+    I32,
+
+    /// int 64 (`0xd3`)
+    I64,
+
+    /// fixext 1 (`0xd4`)
+    FixExt1,
+
+    /// fixext 2 (`0xd5`)
+    FixExt2,
+
+    /// fixext 4 (`0xd6`)
+    FixExt4,
+
+    /// fixext 8 (`0xd7`)
+    FixExt8,
+
+    /// fixext 16 (`0xd8`)
+    FixExt16,
+
+    /// str 8 (`0xd9`)
+    Str8,
+
+    /// str 16 (`0xda`)
+    Str16,
+
+    /// str 32 (`0xdb`)
+    Str32,
+
+    /// array 16 (`0xdc`)
+    Arr16,
+
+    /// array 32 (`0xdd`)
+    Arr32,
+
+    /// map 16 (`0xde`)
+    Map16,
+
+    /// map 32 (`0xdf`)
+    Map32,
+
+    /// negative fixint (`0xe0 - 0xff`)
+    NegFixInt,
+}
+
+impl Format {
+    /// Identify the wire format of a marker byte
+    pub fn from_marker(m: u8) -> Self {
+        match m {
+            0x00..=0x7f => Format::PosFixInt,
+            0x80..=0x8f => Format::FixMap,
+            0x90..=0x9f => Format::FixArr,
+            0xa0..=0xbf => Format::FixStr,
+            C_NIL => Format::Nil,
+            C_RES => Format::Reserved,
+            C_FALSE => Format::False,
+            C_TRUE => Format::True,
+            C_BIN8 => Format::Bin8,
+            C_BIN16 => Format::Bin16,
+            C_BIN32 => Format::Bin32,
+            C_EXT8 => Format::Ext8,
+            C_EXT16 => Format::Ext16,
+            C_EXT32 => Format::Ext32,
+            C_F32 => Format::F32,
+            C_F64 => Format::F64,
+            C_U8 => Format::U8,
+            C_U16 => Format::U16,
+            C_U32 => Format::U32,
+            C_U64 => Format::U64,
+            C_I8 => Format::I8,
+            C_I16 => Format::I16,
+            C_I32 => Format::I32,
+            C_I64 => Format::I64,
+            C_FIXEXT1 => Format::FixExt1,
+            C_FIXEXT2 => Format::FixExt2,
+            C_FIXEXT4 => Format::FixExt4,
+            C_FIXEXT8 => Format::FixExt8,
+            C_FIXEXT16 => Format::FixExt16,
+            C_STR8 => Format::Str8,
+            C_STR16 => Format::Str16,
+            C_STR32 => Format::Str32,
+            C_ARR16 => Format::Arr16,
+            C_ARR32 => Format::Arr32,
+            C_MAP16 => Format::Map16,
+            C_MAP32 => Format::Map32,
+            0xe0..=0xff => Format::NegFixInt,
+        }
+    }
+
+    /// The name of this format as listed in the msgpack spec,
+    /// e.g. `fixmap` or `uint32`
+    pub fn name(&self) -> &'static str {
+        match self {
+            Format::PosFixInt => "positive fixint",
+            Format::FixMap => "fixmap",
+            Format::FixArr => "fixarray",
+            Format::FixStr => "fixstr",
+            Format::Nil => "nil",
+            Format::Reserved => "(never used)",
+            Format::False => "false",
+            Format::True => "true",
+            Format::Bin8 => "bin8",
+            Format::Bin16 => "bin16",
+            Format::Bin32 => "bin32",
+            Format::Ext8 => "ext8",
+            Format::Ext16 => "ext16",
+            Format::Ext32 => "ext32",
+            Format::F32 => "float32",
+            Format::F64 => "float64",
+            Format::U8 => "uint8",
+            Format::U16 => "uint16",
+            Format::U32 => "uint32",
+            Format::U64 => "uint64",
+            Format::I8 => "int8",
+            Format::I16 => "int16",
+            Format::I32 => "int32",
+            Format::I64 => "int64",
+            Format::FixExt1 => "fixext1",
+            Format::FixExt2 => "fixext2",
+            Format::FixExt4 => "fixext4",
+            Format::FixExt8 => "fixext8",
+            Format::FixExt16 => "fixext16",
+            Format::Str8 => "str8",
+            Format::Str16 => "str16",
+            Format::Str32 => "str32",
+            Format::Arr16 => "array16",
+            Format::Arr32 => "array32",
+            Format::Map16 => "map16",
+            Format::Map32 => "map32",
+            Format::NegFixInt => "negative fixint",
+        }
+    }
+}
+
 /// MessagePack Rust decoded message pack tokens
 #[derive(Clone, Copy, PartialEq)]
 pub enum Token<'lt> {
@@ -161,6 +415,20 @@ enum DecState {
     Pend64(PendType, PartialStore<8>),
 }
 
+impl PendType {
+    fn kind(&self) -> Kind {
+        use PendType::*;
+        match self {
+            Len(LenType::Bin) => Kind::Bin,
+            Len(LenType::Str) => Kind::Str,
+            Len(LenType::Arr) => Kind::Arr,
+            Len(LenType::Map) => Kind::Map,
+            Len(LenType::Ext(_)) | ExtLen | Ext(_) => Kind::Ext,
+            _ => Kind::Num,
+        }
+    }
+}
+
 impl DecState {
     fn next_bytes_min(&self) -> u32 {
         use DecState::*;
@@ -179,12 +447,24 @@ impl DecState {
 /// MessagePack Rust Decoder
 pub struct Decoder {
     state: DecState,
+
+    /// count of complete values still to be skipped
+    skip: u64,
+
+    /// the most recently decoded marker byte
+    marker: u8,
+
+    /// the length type str / raw markers are decoded as
+    raw: LenType,
 }
 
 impl Default for Decoder {
     fn default() -> Self {
         Self {
             state: DecState::WantMarker,
+            skip: 0,
+            marker: C_NIL,
+            raw: LenType::Str,
         }
     }
 }
@@ -195,6 +475,20 @@ impl Decoder {
         Self::default()
     }
 
+    /// Construct a Decoder for data written to the given spec revision.
+    /// With `Compat::LegacyRawBin`, str (pre-2013 "raw") markers are
+    /// decoded as `LenType::Bin` rather than `LenType::Str`
+    pub fn with_compat(compat: Compat) -> Self {
+        let raw = match compat {
+            Compat::LegacyRawBin => LenType::Bin,
+            _ => LenType::Str,
+        };
+        Self {
+            raw,
+            ..Self::default()
+        }
+    }
+
     /// Returns the minimum bytes required to do the next atomic decode.
     /// Note the decoder will work fine if you pass less or more,
     /// but it may result in a partial decode requiring you to do some
@@ -203,6 +497,13 @@ impl Decoder {
         self.state.next_bytes_min()
     }
 
+    /// Returns true if a `skip_value()` call did not complete within
+    /// the buffer it was given. The remainder of the value will be
+    /// skipped at the start of subsequent `parse` calls
+    pub fn is_skipping(&self) -> bool {
+        self.skip > 0
+    }
+
     /// Parse a length of encoded messagepack binary data into
     /// an iterator of Token tokens
     pub fn parse<'dec, 'buf>(
@@ -235,6 +536,137 @@ pub struct TokenIter<'dec, 'buf> {
 }
 
 impl<'dec, 'buf> TokenIter<'dec, 'buf> {
+    /// The current byte offset of this iterator into the buffer
+    /// passed to `parse`
+    pub fn cursor(&self) -> usize {
+        self.cursor
+    }
+
+    /// Adapt this iterator to yield `ValueToken`s, which combine
+    /// str / bin / ext headers with their payloads
+    pub fn value_tokens(self) -> ValueTokenIter<'dec, 'buf> {
+        ValueTokenIter {
+            iter: self,
+            pending: None,
+        }
+    }
+
+    /// Get the next token along with the wire format of the marker it
+    /// was decoded from. The format is `None` for `Bin` and `BinCont`
+    /// payload tokens. Pass the format to the matching
+    /// `Encoder::enc_*_with_format` function to reproduce the exact
+    /// original bytes
+    pub fn next_with_format(
+        &mut self,
+    ) -> Option<(Token<'buf>, Option<Format>)> {
+        let token = self.next()?;
+        match token {
+            Token::Bin(_) | Token::BinCont(_, _) => Some((token, None)),
+            _ => Some((token, Some(Format::from_marker(self.dec.marker)))),
+        }
+    }
+
+    /// Consume one complete value (including any nested container items
+    /// and bin / str / ext payloads) without yielding tokens.
+    /// If called after the `Len` token of a bin / str / ext value,
+    /// the remainder of that payload is skipped.
+    /// Returns `true` if the value was fully skipped within this buffer.
+    /// If `false`, the decoder will finish skipping the value at the start
+    /// of subsequent `parse` calls before yielding any more tokens
+    /// (see `Decoder::is_skipping()`)
+    pub fn skip_value(&mut self) -> bool {
+        self.dec.skip += 1;
+        self.run_skip()
+    }
+
+    /// the count of bin / str / ext payload bytes still expected,
+    /// if payload data is expected next
+    pub(crate) fn payload_remaining(&self) -> Option<u32> {
+        match self.dec.state {
+            DecState::WantBinZero => Some(0),
+            DecState::WantBin(len) => Some(len),
+            _ => None,
+        }
+    }
+
+    /// Report the kind of the next value without advancing the iterator.
+    /// Returns `None` if no marker is available, either because the buffer
+    /// is exhausted (or an in-progress skip has not yet completed),
+    /// or because bin / str / ext payload data is expected next
+    pub fn peek_kind(&mut self) -> Option<Kind> {
+        if !self.run_skip() {
+            return None;
+        }
+        match self.dec.state {
+            DecState::WantMarker => {
+                self.data.get(self.cursor).map(|m| {
+                    match (Kind::from_marker(*m), self.dec.raw) {
+                        (Kind::Str, LenType::Bin) => Kind::Bin,
+                        (kind, _) => kind,
+                    }
+                })
+            }
+            DecState::WantBinZero | DecState::WantBin(_) => None,
+            DecState::Pend8(t)
+            | DecState::Pend16(t, _)
+            | DecState::Pend32(t, _)
+            | DecState::Pend64(t, _) => Some(t.kind()),
+        }
+    }
+
+    /// continue any pending skip operation,
+    /// returns `true` if there is nothing left to skip
+    fn run_skip(&mut self) -> bool {
+        while self.dec.skip > 0 {
+            match self.dec.state {
+                DecState::WantBinZero => {
+                    self.dec.state = DecState::WantMarker;
+                    self.dec.skip -= 1;
+                }
+                DecState::WantBin(len) => {
+                    let rem_len = self.data.len() - self.cursor;
+                    if rem_len < len as usize {
+                        self.cursor += rem_len;
+                        self.dec.state =
+                            DecState::WantBin(len - rem_len as u32);
+                        return false;
+                    }
+                    self.cursor += len as usize;
+                    self.dec.state = DecState::WantMarker;
+                    self.dec.skip -= 1;
+                }
+                _ => match self.next_token() {
+                    None => return false,
+                    Some(Token::Len(LenType::Arr, len)) => {
+                        self.dec.skip += len as u64;
+                        self.dec.skip -= 1;
+                    }
+                    Some(Token::Len(LenType::Map, len)) => {
+                        self.dec.skip += len as u64 * 2;
+                        self.dec.skip -= 1;
+                    }
+                    // the value is complete once the payload is consumed
+                    Some(Token::Len(_, _)) => (),
+                    Some(_) => self.dec.skip -= 1,
+                },
+            }
+        }
+        true
+    }
+
+    /// parse the next token given the current decoder state
+    fn next_token(&mut self) -> Option<Token<'buf>> {
+        match core::mem::replace(&mut self.dec.state, DecState::WantMarker) {
+            DecState::WantMarker => self.parse_want_marker(),
+            DecState::WantBinZero => Some(Token::Bin(&[])),
+            DecState::WantBin(len) => self.parse_want_bin_data(len),
+            DecState::Pend8(t) => self.parse_pend_8(t),
+            DecState::Pend16(t, p) => self.parse_pend_16(t, p),
+            DecState::Pend32(t, p) => self.parse_pend_32(t, p),
+            DecState::Pend64(t, p) => self.parse_pend_64(t, p),
+        }
+    }
+
     /// get a byte or none if end of buffer
     fn get_byte(&mut self) -> Option<u8> {
         if self.cursor >= self.data.len() {
@@ -264,13 +696,15 @@ impl<'dec, 'buf> TokenIter<'dec, 'buf> {
     }
 
     /// parse a want marker, will either return a token
-    /// or tail recurse call back into self.next()
+    /// or tail recurse call back into self.next_token()
     fn parse_want_marker(&mut self) -> Option<Token<'buf>> {
         const FIXSTR_SIZE: u8 = 0x1f;
         const FIXARR_SIZE: u8 = 0x0f;
         const FIXMAP_SIZE: u8 = 0x0f;
 
-        match self.get_byte()? {
+        let m = self.get_byte()?;
+        self.dec.marker = m;
+        match m {
             // positive fixint
             m @ 0x00..=0x7f => Some(Token::Num(m.into())),
             // fixmap
@@ -285,7 +719,7 @@ impl<'dec, 'buf> TokenIter<'dec, 'buf> {
             m @ 0xa0..=0xbf => {
                 let len = (m & FIXSTR_SIZE) as u32;
                 self.dec.set_want_bin_data(len);
-                Some(Token::Len(LenType::Str, len))
+                Some(Token::Len(self.dec.raw, len))
             }
             C_NIL => Some(Token::Nil),
             // reserved (this should never be used... treat it like nil)
@@ -294,149 +728,149 @@ impl<'dec, 'buf> TokenIter<'dec, 'buf> {
             C_TRUE => Some(Token::Bool(true)),
             C_BIN8 => {
                 self.dec.state = DecState::Pend8(PendType::Len(LenType::Bin));
-                self.next()
+                self.next_token()
             }
             C_BIN16 => {
                 self.dec.state = DecState::Pend16(
                     PendType::Len(LenType::Bin),
                     PartialStore::new(),
                 );
-                self.next()
+                self.next_token()
             }
             C_BIN32 => {
                 self.dec.state = DecState::Pend32(
                     PendType::Len(LenType::Bin),
                     PartialStore::new(),
                 );
-                self.next()
+                self.next_token()
             }
             C_EXT8 => {
                 self.dec.state = DecState::Pend8(PendType::ExtLen);
-                self.next()
+                self.next_token()
             }
             C_EXT16 => {
                 self.dec.state =
                     DecState::Pend16(PendType::ExtLen, PartialStore::new());
-                self.next()
+                self.next_token()
             }
             C_EXT32 => {
                 self.dec.state =
                     DecState::Pend32(PendType::ExtLen, PartialStore::new());
-                self.next()
+                self.next_token()
             }
             C_F32 => {
                 self.dec.state =
                     DecState::Pend32(PendType::F32, PartialStore::new());
-                self.next()
+                self.next_token()
             }
             C_F64 => {
                 self.dec.state =
                     DecState::Pend64(PendType::F64, PartialStore::new());
-                self.next()
+                self.next_token()
             }
             C_U8 => {
                 self.dec.state = DecState::Pend8(PendType::U8);
-                self.next()
+                self.next_token()
             }
             C_U16 => {
                 self.dec.state =
                     DecState::Pend16(PendType::U16, PartialStore::new());
-                self.next()
+                self.next_token()
             }
             C_U32 => {
                 self.dec.state =
                     DecState::Pend32(PendType::U32, PartialStore::new());
-                self.next()
+                self.next_token()
             }
             C_U64 => {
                 self.dec.state =
                     DecState::Pend64(PendType::U64, PartialStore::new());
-                self.next()
+                self.next_token()
             }
             C_I8 => {
                 self.dec.state = DecState::Pend8(PendType::I8);
-                self.next()
+                self.next_token()
             }
             C_I16 => {
                 self.dec.state =
                     DecState::Pend16(PendType::I16, PartialStore::new());
-                self.next()
+                self.next_token()
             }
             C_I32 => {
                 self.dec.state =
                     DecState::Pend32(PendType::I32, PartialStore::new());
-                self.next()
+                self.next_token()
             }
             C_I64 => {
                 self.dec.state =
                     DecState::Pend64(PendType::I64, PartialStore::new());
-                self.next()
+                self.next_token()
             }
             C_FIXEXT1 => {
                 self.dec.state = DecState::Pend8(PendType::Ext(1));
-                self.next()
+                self.next_token()
             }
             C_FIXEXT2 => {
                 self.dec.state = DecState::Pend8(PendType::Ext(2));
-                self.next()
+                self.next_token()
             }
             C_FIXEXT4 => {
                 self.dec.state = DecState::Pend8(PendType::Ext(4));
-                self.next()
+                self.next_token()
             }
             C_FIXEXT8 => {
                 self.dec.state = DecState::Pend8(PendType::Ext(8));
-                self.next()
+                self.next_token()
             }
             C_FIXEXT16 => {
                 self.dec.state = DecState::Pend8(PendType::Ext(16));
-                self.next()
+                self.next_token()
             }
             C_STR8 => {
-                self.dec.state = DecState::Pend8(PendType::Len(LenType::Str));
-                self.next()
+                self.dec.state = DecState::Pend8(PendType::Len(self.dec.raw));
+                self.next_token()
             }
             C_STR16 => {
                 self.dec.state = DecState::Pend16(
-                    PendType::Len(LenType::Str),
+                    PendType::Len(self.dec.raw),
                     PartialStore::new(),
                 );
-                self.next()
+                self.next_token()
             }
             C_STR32 => {
                 self.dec.state = DecState::Pend32(
-                    PendType::Len(LenType::Str),
+                    PendType::Len(self.dec.raw),
                     PartialStore::new(),
                 );
-                self.next()
+                self.next_token()
             }
             C_ARR16 => {
                 self.dec.state = DecState::Pend16(
                     PendType::Len(LenType::Arr),
                     PartialStore::new(),
                 );
-                self.next()
+                self.next_token()
             }
             C_ARR32 => {
                 self.dec.state = DecState::Pend32(
                     PendType::Len(LenType::Arr),
                     PartialStore::new(),
                 );
-                self.next()
+                self.next_token()
             }
             C_MAP16 => {
                 self.dec.state = DecState::Pend16(
                     PendType::Len(LenType::Map),
                     PartialStore::new(),
                 );
-                self.next()
+                self.next_token()
             }
             C_MAP32 => {
                 self.dec.state = DecState::Pend32(
                     PendType::Len(LenType::Map),
                     PartialStore::new(),
                 );
-                self.next()
+                self.next_token()
             }
             // negative fixint
             m @ 0xe0..=0xff => Some(Token::Num((m as i8).into())),
@@ -476,7 +910,7 @@ impl<'dec, 'buf> TokenIter<'dec, 'buf> {
     /// in this ext case we still need to read the type byte
     fn parse_ext_len(&mut self, len: u32) -> Option<Token<'buf>> {
         self.dec.state = DecState::Pend8(PendType::Ext(len));
-        self.next()
+        self.next_token()
     }
 
     /// in this ext case we have already read the type byte
@@ -597,14 +1031,110 @@ impl<'dec, 'buf> core::iter::Iterator for TokenIter<'dec, 'buf> {
     type Item = Token<'buf>;
 
     fn next(&mut self) -> Option<Self::Item> {
-        match core::mem::replace(&mut self.dec.state, DecState::WantMarker) {
-            DecState::WantMarker => self.parse_want_marker(),
-            DecState::WantBinZero => Some(Token::Bin(&[])),
-            DecState::WantBin(len) => self.parse_want_bin_data(len),
-            DecState::Pend8(t) => self.parse_pend_8(t),
-            DecState::Pend16(t, p) => self.parse_pend_16(t, p),
-            DecState::Pend32(t, p) => self.parse_pend_32(t, p),
-            DecState::Pend64(t, p) => self.parse_pend_64(t, p),
+        if !self.run_skip() {
+            return None;
+        }
+        self.next_token()
+    }
+}
+
+/// Higher-level decoded tokens, where str / bin / ext payloads that are
+/// entirely contained in the current buffer are combined with
+/// their headers. See `TokenIter::value_tokens()`
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ValueToken<'lt> {
+    /// MessagePack 'Nil' type
+    Nil,
+
+    /// A boolean value
+    Bool(bool),
+
+    /// A number value
+    Num(Num),
+
+    /// A complete utf8 string
+    Str(&'lt str),
+
+    /// A complete str payload that is not valid utf8
+    InvalidStr(&'lt [u8]),
+
+    /// Complete binary data
+    Bin(&'lt [u8]),
+
+    /// Complete ext data
+    Ext(i8, &'lt [u8]),
+
+    /// An array header, followed by this many values
+    Arr(u32),
+
+    /// A map header, followed by this many key value pairs
+    Map(u32),
+
+    /// The header of a str / bin / ext payload that did not fit in the
+    /// current buffer. `Chunk` tokens, possibly from subsequent `parse`
+    /// calls, will follow with the payload data
+    PayloadStart(LenType, u32),
+
+    /// A piece of a payload announced by `PayloadStart`.
+    /// The u32 value is the remaining length, zero on the final chunk
+    Chunk(&'lt [u8], u32),
+}
+
+/// ValueToken Iterator returned from `TokenIter::value_tokens()`
+pub struct ValueTokenIter<'dec, 'buf> {
+    iter: TokenIter<'dec, 'buf>,
+    pending: Option<ValueToken<'buf>>,
+}
+
+impl<'dec, 'buf> ValueTokenIter<'dec, 'buf> {
+    /// The current byte offset of this iterator into the buffer
+    /// passed to `parse`
+    pub fn cursor(&self) -> usize {
+        self.iter.cursor()
+    }
+
+    /// Consume one complete value without yielding tokens,
+    /// see `TokenIter::skip_value()`
+    /// If called after `PayloadStart`, the remainder of the payload
+    /// is skipped
+    pub fn skip_value(&mut self) -> bool {
+        self.pending = None;
+        self.iter.skip_value()
+    }
+}
+
+impl<'dec, 'buf> core::iter::Iterator for ValueTokenIter<'dec, 'buf> {
+    type Item = ValueToken<'buf>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if let Some(tok) = self.pending.take() {
+            return Some(tok);
         }
+        Some(match self.iter.next()? {
+            Token::Nil => ValueToken::Nil,
+            Token::Bool(b) => ValueToken::Bool(b),
+            Token::Num(n) => ValueToken::Num(n),
+            Token::Len(LenType::Arr, l) => ValueToken::Arr(l),
+            Token::Len(LenType::Map, l) => ValueToken::Map(l),
+            Token::Len(t, l) => match self.iter.next() {
+                Some(Token::Bin(data)) => match t {
+                    LenType::Str => match core::str::from_utf8(data) {
+                        Ok(s) => ValueToken::Str(s),
+                        Err(_) => ValueToken::InvalidStr(data),
+                    },
+                    LenType::Ext(t) => ValueToken::Ext(t, data),
+                    _ => ValueToken::Bin(data),
+                },
+                Some(Token::BinCont(data, rem)) => {
+                    self.pending = Some(ValueToken::Chunk(data, rem));
+                    ValueToken::PayloadStart(t, l)
+                }
+                // the buffer ended right after the header
+                _ => ValueToken::PayloadStart(t, l),
+            },
+            // payload data from a header seen in a previous buffer
+            Token::Bin(data) => ValueToken::Chunk(data, 0),
+            Token::BinCont(data, rem) => ValueToken::Chunk(data, rem),
+        })
     }
 }
@@ -0,0 +1,376 @@
+//! no-alloc document model over a caller-supplied node arena
+//!
+//! `parse()` decodes a buffer into a flat slice of `Node`s. Scalars and
+//! str / bin / ext payloads are stored inline (payloads borrow from the
+//! input), while the direct children of each array or map are stored
+//! contiguously, with the container node recording where they start.
+//! Map children alternate key, value.
+//!
+//! # Example
+//!
+//! ```
+//! use msgpackin_core::dom::*;
+//! use msgpackin_core::query::PathSeg::*;
+//!
+//! // {"users": [{"name": "bob"}]}
+//! let buf = [
+//!     0x81, 0xa5, b'u', b's', b'e', b'r', b's', 0x91, 0x81, 0xa4, b'n',
+//!     b'a', b'm', b'e', 0xa3, b'b', b'o', b'b',
+//! ];
+//!
+//! let mut arena = [Node::Nil; 8];
+//! let dom = parse(&buf, &mut arena, 16).unwrap();
+//! assert_eq!(6, dom.len());
+//!
+//! let users = dom.root().get("users").unwrap();
+//! assert_eq!(1, users.len());
+//! for user in users.items() {
+//!     assert_eq!(Some("bob"), user.get("name").and_then(|n| n.as_str()));
+//! }
+//!
+//! let name = dom.root().path(&[Key("users"), Index(0), Key("name")]);
+//! assert_eq!(Some(Node::Str("bob")), name.map(|n| n.node()));
+//!
+//! let mut small = [Node::Nil; 4];
+//! assert_eq!(Some(DomError::OutOfNodes), parse(&buf, &mut small, 16).err());
+//! ```
+
+use crate::decode::*;
+use crate::num::*;
+use crate::query::PathSeg;
+
+/// Error produced by `parse()`
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum DomError {
+    /// The node arena is too small for the decoded value
+    OutOfNodes,
+
+    /// The input ended in the middle of a value
+    Truncated,
+
+    /// Containers were nested deeper than `max_depth`
+    TooDeep,
+
+    /// A str payload was not valid utf8
+    InvalidUtf8,
+}
+
+impl core::fmt::Display for DomError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        core::fmt::Debug::fmt(self, f)
+    }
+}
+
+/// A single decoded value in a node arena
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Node<'buf> {
+    /// MessagePack 'Nil' type
+    Nil,
+
+    /// A boolean value
+    Bool(bool),
+
+    /// A number value
+    Num(Num),
+
+    /// A utf8 string
+    Str(&'buf str),
+
+    /// Binary data
+    Bin(&'buf [u8]),
+
+    /// Ext data
+    Ext(i8, &'buf [u8]),
+
+    /// An array of `len` items, stored at arena index `start` onward
+    Arr {
+        /// arena index of the first item
+        start: u32,
+
+        /// item count
+        len: u32,
+    },
+
+    /// A map of `len` entries, stored as alternating
+    /// key, value nodes at arena index `start` onward
+    Map {
+        /// arena index of the first key
+        start: u32,
+
+        /// entry count
+        len: u32,
+    },
+}
+
+/// Decode the first value in `buf` into `arena`.
+/// Containers nested deeper than `max_depth` are rejected
+pub fn parse<'n, 'buf>(
+    buf: &'buf [u8],
+    arena: &'n mut [Node<'buf>],
+    max_depth: usize,
+) -> Result<Dom<'n, 'buf>, DomError> {
+    if arena.is_empty() {
+        return Err(DomError::OutOfNodes);
+    }
+    let mut dec = Decoder::new();
+    let mut build = Build {
+        iter: dec.parse(buf).value_tokens(),
+        nodes: arena,
+        next: 1,
+        max_depth,
+    };
+    build.value(0, 0)?;
+    let Build { nodes, next, .. } = build;
+    Ok(Dom {
+        nodes: &nodes[..next],
+    })
+}
+
+/// A decoded document, see `parse()`
+#[derive(Debug, Clone, Copy)]
+pub struct Dom<'n, 'buf> {
+    nodes: &'n [Node<'buf>],
+}
+
+impl<'n, 'buf> Dom<'n, 'buf> {
+    /// The top-level value
+    pub fn root(&self) -> NodeRef<'n, 'buf> {
+        NodeRef {
+            nodes: self.nodes,
+            idx: 0,
+        }
+    }
+
+    /// The number of arena nodes used
+    pub fn len(&self) -> usize {
+        self.nodes.len()
+    }
+
+    /// True if no nodes are used (never the case for a parsed document)
+    pub fn is_empty(&self) -> bool {
+        self.nodes.is_empty()
+    }
+
+    /// The used portion of the arena
+    pub fn nodes(&self) -> &'n [Node<'buf>] {
+        self.nodes
+    }
+}
+
+/// A reference to a node within a `Dom`,
+/// allowing navigation to its children
+#[derive(Debug, Clone, Copy)]
+pub struct NodeRef<'n, 'buf> {
+    nodes: &'n [Node<'buf>],
+    idx: usize,
+}
+
+impl<'n, 'buf> NodeRef<'n, 'buf> {
+    /// The node itself
+    pub fn node(&self) -> Node<'buf> {
+        self.nodes[self.idx]
+    }
+
+    /// The string, if this is a str node
+    pub fn as_str(&self) -> Option<&'buf str> {
+        match self.node() {
+            Node::Str(s) => Some(s),
+            _ => None,
+        }
+    }
+
+    /// Array item count, or map entry count. Zero for other nodes
+    pub fn len(&self) -> usize {
+        match self.node() {
+            Node::Arr { len, .. } | Node::Map { len, .. } => len as usize,
+            _ => 0,
+        }
+    }
+
+    /// True if this is not a container, or an empty one
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// The array item at `index`
+    pub fn at(&self, index: usize) -> Option<NodeRef<'n, 'buf>> {
+        match self.node() {
+            Node::Arr { start, len } if index < len as usize => {
+                Some(self.child(start as usize + index))
+            }
+            _ => None,
+        }
+    }
+
+    /// The value for a utf8 string `key` of a map
+    pub fn get(&self, key: &str) -> Option<NodeRef<'n, 'buf>> {
+        self.entries()
+            .find(|(k, _)| k.node() == Node::Str(key))
+            .map(|(_, v)| v)
+    }
+
+    /// The node at `path` below this one
+    pub fn path(&self, path: &[PathSeg<'_>]) -> Option<NodeRef<'n, 'buf>> {
+        let mut cur = *self;
+        for seg in path {
+            cur = match seg {
+                PathSeg::Key(key) => cur.get(key)?,
+                PathSeg::Index(index) => cur.at(*index as usize)?,
+            };
+        }
+        Some(cur)
+    }
+
+    /// Iterate the items of an array. Empty for other nodes
+    pub fn items(&self) -> Items<'n, 'buf> {
+        let (next, end) = match self.node() {
+            Node::Arr { start, len } => self.span(start, len as u64),
+            _ => (0, 0),
+        };
+        Items {
+            nodes: self.nodes,
+            next,
+            end,
+        }
+    }
+
+    /// Iterate the key, value entries of a map. Empty for other nodes
+    pub fn entries(&self) -> Entries<'n, 'buf> {
+        let (next, end) = match self.node() {
+            Node::Map { start, len } => self.span(start, len as u64 * 2),
+            _ => (0, 0),
+        };
+        Entries {
+            nodes: self.nodes,
+            next,
+            end,
+        }
+    }
+
+    // child node range, computed in u64 and clamped to the arena so
+    // the end cannot overflow `usize` on 32-bit targets
+    fn span(&self, start: u32, count: u64) -> (usize, usize) {
+        let max = self.nodes.len() as u64;
+        let next = (start as u64).min(max);
+        let end = next.saturating_add(count).min(max);
+        (next as usize, end as usize)
+    }
+
+    fn child(&self, idx: usize) -> NodeRef<'n, 'buf> {
+        NodeRef {
+            nodes: self.nodes,
+            idx,
+        }
+    }
+}
+
+/// Iterator over array items, see `NodeRef::items()`
+pub struct Items<'n, 'buf> {
+    nodes: &'n [Node<'buf>],
+    next: usize,
+    end: usize,
+}
+
+impl<'n, 'buf> Iterator for Items<'n, 'buf> {
+    type Item = NodeRef<'n, 'buf>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.next >= self.end {
+            return None;
+        }
+        self.next += 1;
+        Some(NodeRef {
+            nodes: self.nodes,
+            idx: self.next - 1,
+        })
+    }
+}
+
+/// Iterator over map entries, see `NodeRef::entries()`
+pub struct Entries<'n, 'buf> {
+    nodes: &'n [Node<'buf>],
+    next: usize,
+    end: usize,
+}
+
+impl<'n, 'buf> Iterator for Entries<'n, 'buf> {
+    type Item = (NodeRef<'n, 'buf>, NodeRef<'n, 'buf>);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.next >= self.end {
+            return None;
+        }
+        self.next += 2;
+        let key = NodeRef {
+            nodes: self.nodes,
+            idx: self.next - 2,
+        };
+        let val = NodeRef {
+            nodes: self.nodes,
+            idx: self.next - 1,
+        };
+        Some((key, val))
+    }
+}
+
+struct Build<'dec, 'n, 'buf> {
+    iter: ValueTokenIter<'dec, 'buf>,
+    nodes: &'n mut [Node<'buf>],
+
+    /// next unreserved arena index
+    next: usize,
+    max_depth: usize,
+}
+
+impl<'dec, 'n, 'buf> Build<'dec, 'n, 'buf> {
+    /// decode one value into the already reserved `slot`
+    fn value(&mut self, slot: usize, depth: usize) -> Result<(), DomError> {
+        let (is_map, len) = match self.iter.next() {
+            Some(ValueToken::Arr(len)) => (false, len),
+            Some(ValueToken::Map(len)) => (true, len),
+            Some(token) => {
+                self.nodes[slot] = match token {
+                    ValueToken::Nil => Node::Nil,
+                    ValueToken::Bool(b) => Node::Bool(b),
+                    ValueToken::Num(n) => Node::Num(n),
+                    ValueToken::Str(s) => Node::Str(s),
+                    ValueToken::Bin(data) => Node::Bin(data),
+                    ValueToken::Ext(t, data) => Node::Ext(t, data),
+                    ValueToken::InvalidStr(_) => {
+                        return Err(DomError::InvalidUtf8)
+                    }
+                    _ => return Err(DomError::Truncated),
+                };
+                return Ok(());
+            }
+            None => return Err(DomError::Truncated),
+        };
+        if depth >= self.max_depth {
+            return Err(DomError::TooDeep);
+        }
+        // count in u64: `len * 2` can overflow `usize` on 32-bit targets
+        let count = if is_map { len as u64 * 2 } else { len as u64 };
+        let start = self.next;
+        if count > (self.nodes.len() - start) as u64 {
+            return Err(DomError::OutOfNodes);
+        }
+        // fits: bounded by the arena length above
+        let count = count as usize;
+        self.next += count;
+        self.nodes[slot] = if is_map {
+            Node::Map {
+                start: start as u32,
+                len,
+            }
+        } else {
+            Node::Arr {
+                start: start as u32,
+                len,
+            }
+        };
+        for i in 0..count {
+            self.value(start + i, depth + 1)?;
+        }
+        Ok(())
+    }
+}
@@ -1,18 +1,36 @@
 //! encode library code
 
 use crate::const_::*;
+use crate::const_enc;
+use crate::decode::{Format, LenType, Token};
 use crate::num::*;
 use core::ops::Deref;
 
 /// MessagePack Rust variable-size byte array result
 #[derive(Debug, Clone, Copy)]
-pub struct VarBytes(VbPriv);
+pub struct VarBytes(pub(crate) VbPriv);
+
+impl VarBytes {
+    /// Access the encoded bytes, usable in `const` contexts
+    pub const fn as_bytes(&self) -> &[u8] {
+        use VbPriv::*;
+        match &self.0 {
+            B1(b) => b,
+            B2(b) => b,
+            B3(b) => b,
+            B4(b) => b,
+            B5(b) => b,
+            B6(b) => b,
+            B9(b) => b,
+        }
+    }
+}
 
 impl core::ops::Deref for VarBytes {
     type Target = [u8];
 
     fn deref(&self) -> &Self::Target {
-        &self.0
+        self.as_bytes()
     }
 }
 
@@ -29,7 +47,7 @@ impl core::borrow::Borrow<[u8]> for VarBytes {
 }
 
 #[derive(Debug, Clone, Copy)]
-enum VbPriv {
+pub(crate) enum VbPriv {
     /// 1-length byte array
     B1([u8; 1]),
 
@@ -72,198 +90,905 @@ _bf! {
     VbPriv::B9 => 9,
 }
 
-impl core::ops::Deref for VbPriv {
+/// Msgpack bytes for a single `Token`, see `Encoder::enc_token()`
+#[derive(Debug, Clone, Copy)]
+pub struct TokenBytes<'lt>(TbPriv<'lt>);
+
+#[derive(Debug, Clone, Copy)]
+enum TbPriv<'lt> {
+    Head(VarBytes),
+    Data(&'lt [u8]),
+}
+
+impl core::ops::Deref for TokenBytes<'_> {
     type Target = [u8];
 
     fn deref(&self) -> &Self::Target {
-        use VbPriv::*;
-        match self {
-            B1(b) => b,
-            B2(b) => b,
-            B3(b) => b,
-            B4(b) => b,
-            B5(b) => b,
-            B6(b) => b,
-            B9(b) => b,
+        match &self.0 {
+            TbPriv::Head(b) => b,
+            TbPriv::Data(d) => d,
         }
     }
 }
 
-/// MessagePack Rust Encoder
-pub struct Encoder;
+impl core::convert::AsRef<[u8]> for TokenBytes<'_> {
+    fn as_ref(&self) -> &[u8] {
+        self.deref()
+    }
+}
 
-impl Default for Encoder {
-    fn default() -> Self {
-        Self
+/// Determines how an Encoder writes float values
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub enum FloatPolicy {
+    /// Floats are written with the width they were given,
+    /// preserving negative zero and NaN payloads. This is the default
+    #[default]
+    Preserve,
+
+    /// Floats are written in their most compact lossless form,
+    /// e.g. `42.0_f64` is written as the integer `42`
+    /// (see `Num::compact()`)
+    Compact,
+}
+
+/// The msgpack spec revision an Encoder / Decoder targets
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub enum Compat {
+    /// The current spec, with separate str / bin types and ext
+    #[default]
+    Current,
+
+    /// The pre-2013 spec, for peers that predate the str / bin split.
+    /// Strings are written as fixstr / str16 / str32 ("raw") only,
+    /// bin data is written as raw, and ext is not supported.
+    /// Raw data is decoded as str
+    LegacyRawStr,
+
+    /// As `LegacyRawStr`, but raw data is decoded as bin
+    LegacyRawBin,
+}
+
+impl Compat {
+    /// True for either of the pre-2013 legacy modes
+    pub fn is_legacy(&self) -> bool {
+        *self != Compat::Current
     }
 }
 
+/// MessagePack Rust Encoder
+#[derive(Default)]
+pub struct Encoder {
+    float_policy: FloatPolicy,
+    compat: Compat,
+}
+
 impl Encoder {
     /// Default constructor for Encoder
     pub fn new() -> Self {
-        Self
+        Self::default()
+    }
+
+    /// Construct an Encoder that writes floats according to `float_policy`
+    pub fn with_float_policy(float_policy: FloatPolicy) -> Self {
+        Self {
+            float_policy,
+            ..Self::default()
+        }
+    }
+
+    /// Set the spec revision this Encoder writes, see `Compat`
+    pub fn with_compat(mut self, compat: Compat) -> Self {
+        self.compat = compat;
+        self
+    }
+
+    /// The spec revision this Encoder writes
+    pub fn compat(&self) -> Compat {
+        self.compat
     }
 
     /// Encode msgpack bytes for `nil`
     pub fn enc_nil(&mut self) -> VarBytes {
-        [C_NIL].into()
+        const_enc::enc_nil()
     }
 
     /// Encode msgpack bytes for `bool`
     pub fn enc_bool(&mut self, b: bool) -> VarBytes {
-        if b {
-            [C_TRUE].into()
-        } else {
-            [C_FALSE].into()
-        }
+        const_enc::enc_bool(b)
     }
 
     /// Encode msgpack bytes for msgpack `Num` type
     pub fn enc_num<N: Into<Num>>(&mut self, n: N) -> VarBytes {
-        let i = match n.into() {
-            Num::F32(f) => {
-                let mut out = [C_F32, 0, 0, 0, 0];
-                out[1..5].copy_from_slice(&f.to_be_bytes());
-                return out.into();
-            }
-            Num::F64(f) => {
-                let mut out = [C_F64, 0, 0, 0, 0, 0, 0, 0, 0];
-                out[1..9].copy_from_slice(&f.to_be_bytes());
-                return out.into();
-            }
-            Num::Signed(i) => i as i128,
-            Num::Unsigned(u) => u as i128,
+        let n = match self.float_policy {
+            FloatPolicy::Preserve => n.into(),
+            FloatPolicy::Compact => n.into().compact(),
         };
-
-        #[allow(clippy::manual_range_contains)]
-        if i >= 0 && i < 128 {
-            [i as u8].into()
-        } else if i > -32 && i < 0 {
-            [i as i8 as u8].into()
-        } else if i >= u8::MIN as i128 && i <= u8::MAX as i128 {
-            [C_U8, i as u8].into()
-        } else if i >= i8::MIN as i128 && i <= i8::MAX as i128 {
-            [C_I8, i as u8].into()
-        } else if i >= u16::MIN as i128 && i <= u16::MAX as i128 {
-            let mut out = [C_U16, 0, 0];
-            out[1..3].copy_from_slice(&(i as u16).to_be_bytes());
-            out.into()
-        } else if i >= i16::MIN as i128 && i <= i16::MAX as i128 {
-            let mut out = [C_I16, 0, 0];
-            out[1..3].copy_from_slice(&(i as i16).to_be_bytes());
-            out.into()
-        } else if i >= u32::MIN as i128 && i <= u32::MAX as i128 {
-            let mut out = [C_U32, 0, 0, 0, 0];
-            out[1..5].copy_from_slice(&(i as u32).to_be_bytes());
-            out.into()
-        } else if i >= i32::MIN as i128 && i <= i32::MAX as i128 {
-            let mut out = [C_I32, 0, 0, 0, 0];
-            out[1..5].copy_from_slice(&(i as i32).to_be_bytes());
-            out.into()
-        } else if i >= u64::MIN as i128 && i <= u64::MAX as i128 {
-            let mut out = [C_U64, 0, 0, 0, 0, 0, 0, 0, 0];
-            out[1..9].copy_from_slice(&(i as u64).to_be_bytes());
-            out.into()
-        } else if i >= i64::MIN as i128 && i <= i64::MAX as i128 {
-            let mut out = [C_I64, 0, 0, 0, 0, 0, 0, 0, 0];
-            out[1..9].copy_from_slice(&(i as i64).to_be_bytes());
-            out.into()
-        } else {
-            unreachable!()
+        match n {
+            Num::F32(f) => enc_be(C_F32, f.to_be_bytes()),
+            Num::F64(f) => enc_be(C_F64, f.to_be_bytes()),
+            Num::Signed(i) => const_enc::enc_i64(i),
+            Num::Unsigned(u) => const_enc::enc_u64(u),
         }
     }
 
     /// Encode msgpack bytes for arbitrary binary byte length.
     /// There is no encode function for the bytes themselves,
     /// just copy them directly into your buffer
+    /// In legacy compat mode, this is a raw (str) length
     pub fn enc_bin_len(&mut self, len: u32) -> VarBytes {
-        if len < 256 {
-            [C_BIN8, len as u8].into()
-        } else if len < 65536 {
-            let mut out = [C_BIN16, 0, 0];
-            out[1..3].copy_from_slice(&(len as u16).to_be_bytes());
-            out.into()
-        } else {
-            let mut out = [C_BIN32, 0, 0, 0, 0];
-            out[1..5].copy_from_slice(&len.to_be_bytes());
-            out.into()
+        if self.compat.is_legacy() {
+            return const_enc::enc_raw_len(len);
         }
+        const_enc::enc_bin_len(len)
     }
 
     /// Encode msgpack bytes for utf8 string data byte length.
     /// There is no encode function for the bytes themselves,
     /// just copy them directly into your buffer (`as_bytes()`)
+    /// In legacy compat mode, `str 8` is never used
     pub fn enc_str_len(&mut self, len: u32) -> VarBytes {
-        if len < 32 {
-            [C_FIXSTR0 | (len as u8 & 0x1f)].into()
-        } else if len < 256 {
-            [C_STR8, len as u8].into()
-        } else if len < 65536 {
-            let mut out = [C_STR16, 0, 0];
-            out[1..3].copy_from_slice(&(len as u16).to_be_bytes());
-            out.into()
-        } else {
-            let mut out = [C_STR32, 0, 0, 0, 0];
-            out[1..5].copy_from_slice(&len.to_be_bytes());
-            out.into()
+        if self.compat.is_legacy() {
+            return const_enc::enc_raw_len(len);
         }
+        const_enc::enc_str_len(len)
     }
 
     /// Encode msgpack bytes for array marker / length
     pub fn enc_arr_len(&mut self, len: u32) -> VarBytes {
-        if len < 16 {
-            [C_FIXARR0 | (len as u8 & 0x0f)].into()
-        } else if len < 65536 {
-            let mut out = [C_ARR16, 0, 0];
-            out[1..3].copy_from_slice(&(len as u16).to_be_bytes());
-            out.into()
-        } else {
-            let mut out = [C_ARR32, 0, 0, 0, 0];
-            out[1..5].copy_from_slice(&len.to_be_bytes());
-            out.into()
-        }
+        const_enc::enc_arr_len(len)
     }
 
     /// Encode msgpack bytes for map marker / length.
     /// This should be the number of key/value pairs in the map
     pub fn enc_map_len(&mut self, len: u32) -> VarBytes {
-        if len < 16 {
-            [C_FIXMAP0 | (len as u8 & 0x0f)].into()
-        } else if len < 65536 {
-            let mut out = [C_MAP16, 0, 0];
-            out[1..3].copy_from_slice(&(len as u16).to_be_bytes());
-            out.into()
-        } else {
-            let mut out = [C_MAP32, 0, 0, 0, 0];
-            out[1..5].copy_from_slice(&len.to_be_bytes());
-            out.into()
-        }
+        const_enc::enc_map_len(len)
+    }
+
+    /// Encode msgpack bytes for array marker / length, always using
+    /// the 5 byte `array 32` format. This allows a header to be reserved
+    /// before the item count is known, and corrected later with `patch_len`
+    pub fn enc_arr_len_fixed32(&mut self, len: u32) -> VarBytes {
+        enc_be(C_ARR32, len.to_be_bytes())
+    }
+
+    /// Encode msgpack bytes for map marker / length, always using
+    /// the 5 byte `map 32` format. This allows a header to be reserved
+    /// before the pair count is known, and corrected later with `patch_len`
+    pub fn enc_map_len_fixed32(&mut self, len: u32) -> VarBytes {
+        enc_be(C_MAP32, len.to_be_bytes())
     }
 
     /// Encode msgpack bytes for arbitrary msgpack ext byte length.
     /// There is no encode function for the bytes themselves,
-    /// just copy them directly into your buffer
+    /// just copy them directly into your buffer.
+    /// Legacy compat mode has no ext type, callers that may be
+    /// configured for it should use `try_enc_ext_len` instead
     pub fn enc_ext_len(&mut self, len: u32, t: i8) -> VarBytes {
-        if len == 1 {
-            [C_FIXEXT1, t as u8].into()
-        } else if len == 2 {
-            [C_FIXEXT2, t as u8].into()
-        } else if len == 4 {
-            [C_FIXEXT4, t as u8].into()
-        } else if len == 8 {
-            [C_FIXEXT8, t as u8].into()
-        } else if len == 16 {
-            [C_FIXEXT16, t as u8].into()
-        } else if len < 256 {
-            [C_EXT8, len as u8, t as u8].into()
-        } else if len < 65536 {
-            let mut out = [C_EXT16, 0, 0, t as u8];
-            out[1..3].copy_from_slice(&(len as u16).to_be_bytes());
-            out.into()
+        const_enc::enc_ext_len(len, t)
+    }
+
+    /// As `enc_ext_len`, but returns `Err(ExtUnsupported)` if this
+    /// encoder is in legacy compat mode
+    pub fn try_enc_ext_len(
+        &mut self,
+        len: u32,
+        t: i8,
+    ) -> Result<VarBytes, ExtUnsupported> {
+        if self.compat.is_legacy() {
+            return Err(ExtUnsupported);
+        }
+        Ok(self.enc_ext_len(len, t))
+    }
+
+    /// Encode a decoded `Token` back into msgpack bytes.
+    /// `Bin` and `BinCont` payload data is passed through as-is.
+    /// Ext headers return `Err(ExtUnsupported)` in legacy compat mode
+    pub fn enc_token<'lt>(
+        &mut self,
+        token: Token<'lt>,
+    ) -> Result<TokenBytes<'lt>, ExtUnsupported> {
+        Ok(TokenBytes(match token {
+            Token::Bin(data) | Token::BinCont(data, _) => TbPriv::Data(data),
+            Token::Len(LenType::Bin, len) => {
+                TbPriv::Head(self.enc_bin_len(len))
+            }
+            Token::Len(LenType::Str, len) => {
+                TbPriv::Head(self.enc_str_len(len))
+            }
+            Token::Len(LenType::Arr, len) => {
+                TbPriv::Head(self.enc_arr_len(len))
+            }
+            Token::Len(LenType::Map, len) => {
+                TbPriv::Head(self.enc_map_len(len))
+            }
+            Token::Len(LenType::Ext(t), len) => {
+                TbPriv::Head(self.try_enc_ext_len(len, t)?)
+            }
+            Token::Nil => TbPriv::Head(self.enc_nil()),
+            Token::Bool(b) => TbPriv::Head(self.enc_bool(b)),
+            Token::Num(n) => TbPriv::Head(self.enc_num(n)),
+        }))
+    }
+
+    /// Encode msgpack bytes for `nil`, using the given wire format.
+    /// `Format::Reserved` will produce the reserved marker (`0xc1`),
+    /// any other format produces the standard `nil` marker
+    pub fn enc_nil_with_format(&mut self, f: Format) -> VarBytes {
+        match f {
+            Format::Reserved => [C_RES].into(),
+            _ => self.enc_nil(),
+        }
+    }
+
+    /// Encode msgpack bytes for msgpack `Num` type, using the given wire
+    /// format. If the number cannot be losslessly represented in that
+    /// format, the default `enc_num` encoding is used instead
+    pub fn enc_num_with_format<N: Into<Num>>(
+        &mut self,
+        n: N,
+        f: Format,
+    ) -> VarBytes {
+        let n = n.into();
+        match f {
+            Format::PosFixInt if n.fits::<u8>() && n.to::<u8>() < 128 => {
+                [n.to::<u8>()].into()
+            }
+            Format::NegFixInt
+                if n.fits::<i8>() && (-32..0).contains(&n.to::<i8>()) =>
+            {
+                [n.to::<i8>() as u8].into()
+            }
+            Format::U8 if n.fits::<u8>() => [C_U8, n.to::<u8>()].into(),
+            Format::I8 if n.fits::<i8>() => [C_I8, n.to::<i8>() as u8].into(),
+            Format::U16 if n.fits::<u16>() => {
+                enc_be(C_U16, n.to::<u16>().to_be_bytes())
+            }
+            Format::I16 if n.fits::<i16>() => {
+                enc_be(C_I16, n.to::<i16>().to_be_bytes())
+            }
+            Format::U32 if n.fits::<u32>() => {
+                enc_be(C_U32, n.to::<u32>().to_be_bytes())
+            }
+            Format::I32 if n.fits::<i32>() => {
+                enc_be(C_I32, n.to::<i32>().to_be_bytes())
+            }
+            Format::U64 if n.fits::<u64>() => {
+                enc_be(C_U64, n.to::<u64>().to_be_bytes())
+            }
+            Format::I64 if n.fits::<i64>() => {
+                enc_be(C_I64, n.to::<i64>().to_be_bytes())
+            }
+            Format::F32 => match n {
+                Num::F32(f) => enc_be(C_F32, f.to_be_bytes()),
+                n if n.fits::<f32>() => {
+                    enc_be(C_F32, n.to::<f32>().to_be_bytes())
+                }
+                n => self.enc_num(n),
+            },
+            Format::F64 => match n {
+                Num::F32(f) => enc_be(C_F64, (f as f64).to_be_bytes()),
+                Num::F64(f) => enc_be(C_F64, f.to_be_bytes()),
+                n if n.fits::<f64>() => {
+                    enc_be(C_F64, n.to::<f64>().to_be_bytes())
+                }
+                n => self.enc_num(n),
+            },
+            _ => self.enc_num(n),
+        }
+    }
+
+    /// Encode msgpack bytes for arbitrary binary byte length, using the
+    /// given wire format. If the length cannot be represented in that
+    /// format, the default `enc_bin_len` encoding is used instead
+    pub fn enc_bin_len_with_format(&mut self, len: u32, f: Format) -> VarBytes {
+        if self.compat.is_legacy() {
+            return self.enc_bin_len(len);
+        }
+        match f {
+            Format::Bin8 if len < 256 => [C_BIN8, len as u8].into(),
+            Format::Bin16 if len < 65536 => {
+                enc_be(C_BIN16, (len as u16).to_be_bytes())
+            }
+            Format::Bin32 => enc_be(C_BIN32, len.to_be_bytes()),
+            _ => self.enc_bin_len(len),
+        }
+    }
+
+    /// Encode msgpack bytes for utf8 string data byte length, using the
+    /// given wire format. If the length cannot be represented in that
+    /// format, the default `enc_str_len` encoding is used instead
+    pub fn enc_str_len_with_format(&mut self, len: u32, f: Format) -> VarBytes {
+        match f {
+            Format::FixStr if len < 32 => [C_FIXSTR0 | len as u8].into(),
+            Format::Str8 if len < 256 && !self.compat.is_legacy() => {
+                [C_STR8, len as u8].into()
+            }
+            Format::Str16 if len < 65536 => {
+                enc_be(C_STR16, (len as u16).to_be_bytes())
+            }
+            Format::Str32 => enc_be(C_STR32, len.to_be_bytes()),
+            _ => self.enc_str_len(len),
+        }
+    }
+
+    /// Encode msgpack bytes for array marker / length, using the
+    /// given wire format. If the length cannot be represented in that
+    /// format, the default `enc_arr_len` encoding is used instead
+    pub fn enc_arr_len_with_format(&mut self, len: u32, f: Format) -> VarBytes {
+        match f {
+            Format::FixArr if len < 16 => [C_FIXARR0 | len as u8].into(),
+            Format::Arr16 if len < 65536 => {
+                enc_be(C_ARR16, (len as u16).to_be_bytes())
+            }
+            Format::Arr32 => enc_be(C_ARR32, len.to_be_bytes()),
+            _ => self.enc_arr_len(len),
+        }
+    }
+
+    /// Encode msgpack bytes for map marker / length, using the
+    /// given wire format. If the length cannot be represented in that
+    /// format, the default `enc_map_len` encoding is used instead
+    pub fn enc_map_len_with_format(&mut self, len: u32, f: Format) -> VarBytes {
+        match f {
+            Format::FixMap if len < 16 => [C_FIXMAP0 | len as u8].into(),
+            Format::Map16 if len < 65536 => {
+                enc_be(C_MAP16, (len as u16).to_be_bytes())
+            }
+            Format::Map32 => enc_be(C_MAP32, len.to_be_bytes()),
+            _ => self.enc_map_len(len),
+        }
+    }
+
+    /// Encode msgpack bytes for arbitrary msgpack ext byte length, using the
+    /// given wire format. If the length cannot be represented in that
+    /// format, the default `enc_ext_len` encoding is used instead.
+    /// Returns `Err(ExtUnsupported)` in legacy compat mode
+    pub fn enc_ext_len_with_format(
+        &mut self,
+        len: u32,
+        t: i8,
+        f: Format,
+    ) -> Result<VarBytes, ExtUnsupported> {
+        if self.compat.is_legacy() {
+            return Err(ExtUnsupported);
+        }
+        Ok(match f {
+            Format::Ext8 if len < 256 => [C_EXT8, len as u8, t as u8].into(),
+            Format::Ext16 if len < 65536 => {
+                let mut out = [C_EXT16, 0, 0, t as u8];
+                out[1..3].copy_from_slice(&(len as u16).to_be_bytes());
+                out.into()
+            }
+            Format::Ext32 => {
+                let mut out = [C_EXT32, 0, 0, 0, 0, t as u8];
+                out[1..5].copy_from_slice(&len.to_be_bytes());
+                out.into()
+            }
+            _ => self.enc_ext_len(len, t),
+        })
+    }
+}
+
+/// encode a marker followed by big-endian bytes
+fn enc_be<const N: usize>(marker: u8, bytes: [u8; N]) -> VarBytes {
+    let mut out = [0; 9];
+    out[0] = marker;
+    out[1..=N].copy_from_slice(&bytes);
+    match N {
+        2 => [out[0], out[1], out[2]].into(),
+        4 => [out[0], out[1], out[2], out[3], out[4]].into(),
+        _ => out.into(),
+    }
+}
+
+/// Error indicating a buffer does not begin with a
+/// 32 bit array or map header, and so cannot be patched
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct InvalidHeader;
+
+impl core::fmt::Display for InvalidHeader {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.write_str("expected an array 32 or map 32 header")
+    }
+}
+
+/// Overwrite the length of an `array 32` or `map 32` header
+/// (as written by `enc_arr_len_fixed32` / `enc_map_len_fixed32`)
+/// at the start of `header`. The buffer is left untouched
+/// if it does not begin with such a header
+pub fn patch_len(header: &mut [u8], len: u32) -> Result<(), InvalidHeader> {
+    match header.first() {
+        Some(&C_ARR32) | Some(&C_MAP32) if header.len() >= 5 => {
+            header[1..5].copy_from_slice(&len.to_be_bytes());
+            Ok(())
+        }
+        _ => Err(InvalidHeader),
+    }
+}
+
+/// Error indicating there was not enough room left in a buffer
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Full;
+
+impl core::fmt::Display for Full {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.write_str("buffer full")
+    }
+}
+
+/// Error indicating ext data was encoded in legacy compat mode,
+/// which has no ext type
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ExtUnsupported;
+
+impl core::fmt::Display for ExtUnsupported {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.write_str("ext is not supported in legacy compat mode")
+    }
+}
+
+/// Errors reported by `SliceWriter::write_ext` / `write_ext_len`
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum WriteExtError {
+    /// There was not enough room left in the buffer
+    Full,
+
+    /// The writer's encoder is in legacy compat mode
+    ExtUnsupported,
+}
+
+impl core::fmt::Display for WriteExtError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            WriteExtError::Full => core::fmt::Display::fmt(&Full, f),
+            WriteExtError::ExtUnsupported => {
+                core::fmt::Display::fmt(&ExtUnsupported, f)
+            }
+        }
+    }
+}
+
+impl From<Full> for WriteExtError {
+    fn from(_: Full) -> Self {
+        WriteExtError::Full
+    }
+}
+
+impl From<ExtUnsupported> for WriteExtError {
+    fn from(_: ExtUnsupported) -> Self {
+        WriteExtError::ExtUnsupported
+    }
+}
+
+/// Writes msgpack headers and payloads into a caller supplied buffer.
+/// Each `write_*` call either writes the entire item or, if there is
+/// not enough room left, returns `Err(Full)` and writes nothing
+pub struct SliceWriter<'a> {
+    buf: &'a mut [u8],
+    cursor: usize,
+    enc: Encoder,
+}
+
+impl<'a> SliceWriter<'a> {
+    /// Construct a new SliceWriter over the given buffer
+    pub fn new(buf: &'a mut [u8]) -> Self {
+        Self::with_encoder(buf, Encoder::new())
+    }
+
+    /// Construct a new SliceWriter using a specifically configured encoder
+    pub fn with_encoder(buf: &'a mut [u8], enc: Encoder) -> Self {
+        Self {
+            buf,
+            cursor: 0,
+            enc,
+        }
+    }
+
+    /// The number of bytes written so far
+    pub fn written(&self) -> usize {
+        self.cursor
+    }
+
+    /// The number of bytes still available in the buffer
+    pub fn remaining(&self) -> usize {
+        self.buf.len() - self.cursor
+    }
+
+    /// The bytes written so far
+    pub fn as_slice(&self) -> &[u8] {
+        &self.buf[..self.cursor]
+    }
+
+    /// Consume this writer, returning the bytes written
+    pub fn into_slice(self) -> &'a [u8] {
+        &self.buf[..self.cursor]
+    }
+
+    /// Write raw bytes to the buffer, e.g. payload data following
+    /// a `write_*_len` call
+    pub fn write_raw(&mut self, data: &[u8]) -> Result<(), Full> {
+        self.write_parts(&[], data)
+    }
+
+    /// Write a msgpack `nil`
+    pub fn write_nil(&mut self) -> Result<(), Full> {
+        let header = self.enc.enc_nil();
+        self.write_parts(&header, &[])
+    }
+
+    /// Write a msgpack `bool`
+    pub fn write_bool(&mut self, b: bool) -> Result<(), Full> {
+        let header = self.enc.enc_bool(b);
+        self.write_parts(&header, &[])
+    }
+
+    /// Write a msgpack `Num` type
+    pub fn write_num<N: Into<Num>>(&mut self, n: N) -> Result<(), Full> {
+        let header = self.enc.enc_num(n);
+        self.write_parts(&header, &[])
+    }
+
+    /// Write a complete msgpack `str` (header and utf8 bytes)
+    pub fn write_str(&mut self, s: &str) -> Result<(), Full> {
+        let header = self.enc.enc_str_len(payload_len(s.as_bytes())?);
+        self.write_parts(&header, s.as_bytes())
+    }
+
+    /// Write a complete msgpack `bin` (header and data)
+    pub fn write_bin(&mut self, data: &[u8]) -> Result<(), Full> {
+        let header = self.enc.enc_bin_len(payload_len(data)?);
+        self.write_parts(&header, data)
+    }
+
+    /// Write a complete msgpack `ext` (header and data).
+    /// Fails with `ExtUnsupported` in legacy compat mode
+    pub fn write_ext(
+        &mut self,
+        t: i8,
+        data: &[u8],
+    ) -> Result<(), WriteExtError> {
+        let header = self.enc.try_enc_ext_len(payload_len(data)?, t)?;
+        Ok(self.write_parts(&header, data)?)
+    }
+
+    /// Write a msgpack `str` header, the utf8 bytes must
+    /// follow via `write_raw`
+    pub fn write_str_len(&mut self, len: u32) -> Result<(), Full> {
+        let header = self.enc.enc_str_len(len);
+        self.write_parts(&header, &[])
+    }
+
+    /// Write a msgpack `bin` header, the data must
+    /// follow via `write_raw`
+    pub fn write_bin_len(&mut self, len: u32) -> Result<(), Full> {
+        let header = self.enc.enc_bin_len(len);
+        self.write_parts(&header, &[])
+    }
+
+    /// Write a msgpack `ext` header, the data must
+    /// follow via `write_raw`.
+    /// Fails with `ExtUnsupported` in legacy compat mode
+    pub fn write_ext_len(
+        &mut self,
+        len: u32,
+        t: i8,
+    ) -> Result<(), WriteExtError> {
+        let header = self.enc.try_enc_ext_len(len, t)?;
+        Ok(self.write_parts(&header, &[])?)
+    }
+
+    /// Write a msgpack array header, `len` items must follow
+    pub fn write_arr_len(&mut self, len: u32) -> Result<(), Full> {
+        let header = self.enc.enc_arr_len(len);
+        self.write_parts(&header, &[])
+    }
+
+    /// Write a msgpack map header, `len` key / value pairs must follow
+    pub fn write_map_len(&mut self, len: u32) -> Result<(), Full> {
+        let header = self.enc.enc_map_len(len);
+        self.write_parts(&header, &[])
+    }
+
+    /// Reserve a 5 byte array header with a length of zero.
+    /// Returns the offset of the header, to be passed to `patch_len`
+    /// once the item count is known
+    pub fn reserve_arr_len(&mut self) -> Result<usize, Full> {
+        let offset = self.cursor;
+        let header = self.enc.enc_arr_len_fixed32(0);
+        self.write_parts(&header, &[])?;
+        Ok(offset)
+    }
+
+    /// Reserve a 5 byte map header with a length of zero.
+    /// Returns the offset of the header, to be passed to `patch_len`
+    /// once the pair count is known
+    pub fn reserve_map_len(&mut self) -> Result<usize, Full> {
+        let offset = self.cursor;
+        let header = self.enc.enc_map_len_fixed32(0);
+        self.write_parts(&header, &[])?;
+        Ok(offset)
+    }
+
+    /// Set the length of a header previously reserved at `offset`
+    pub fn patch_len(
+        &mut self,
+        offset: usize,
+        len: u32,
+    ) -> Result<(), InvalidHeader> {
+        if offset >= self.cursor {
+            return Err(InvalidHeader);
+        }
+        patch_len(&mut self.buf[offset..self.cursor], len)
+    }
+
+    /// the whole buffer, and the cursor, for in-place rewriting
+    pub(crate) fn parts_mut(&mut self) -> (&mut [u8], &mut usize) {
+        (self.buf, &mut self.cursor)
+    }
+
+    /// write both parts, or nothing if they will not fit
+    fn write_parts(&mut self, header: &[u8], data: &[u8]) -> Result<(), Full> {
+        let len = header.len() + data.len();
+        if len > self.remaining() {
+            return Err(Full);
+        }
+        let mid = self.cursor + header.len();
+        self.buf[self.cursor..mid].copy_from_slice(header);
+        self.buf[mid..mid + data.len()].copy_from_slice(data);
+        self.cursor += len;
+        Ok(())
+    }
+}
+
+/// payloads longer than u32::MAX cannot be represented in msgpack,
+/// and certainly will not fit in the buffer
+fn payload_len(data: &[u8]) -> Result<u32, Full> {
+    if data.len() > u32::MAX as usize {
+        return Err(Full);
+    }
+    Ok(data.len() as u32)
+}
+
+/// Errors reported by a CheckedEncoder
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum CheckError {
+    /// An item was written after the top-level value was already complete
+    Overfilled,
+
+    /// The message was finished while containers still expected items
+    Underfilled,
+
+    /// An item was written, or the message was finished,
+    /// before the current bin / str / ext payload was complete
+    UnterminatedPayload,
+
+    /// More payload bytes were written than the bin / str / ext
+    /// header declared, or payload bytes were written with no header
+    PayloadOverflow,
+
+    /// Containers were nested deeper than the CheckedEncoder stack allows
+    TooDeep,
+
+    /// Ext was written by an Encoder in legacy compat mode
+    ExtUnsupported,
+}
+
+impl core::fmt::Display for CheckError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        core::fmt::Debug::fmt(self, f)
+    }
+}
+
+/// An Encoder wrapper that tracks container item counts and payload
+/// lengths, erroring rather than producing corrupt output.
+/// `N` is the maximum container nesting depth.
+/// A CheckedEncoder validates a single top-level value,
+/// call `reset()` to begin another
+pub struct CheckedEncoder<const N: usize> {
+    enc: Encoder,
+
+    /// remaining item count for each open container
+    stack: [u64; N],
+    depth: usize,
+
+    /// remaining bytes of the current bin / str / ext payload
+    payload: u32,
+
+    /// a complete top-level value has been written
+    done: bool,
+}
+
+impl<const N: usize> Default for CheckedEncoder<N> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<const N: usize> CheckedEncoder<N> {
+    /// Default constructor for CheckedEncoder
+    pub fn new() -> Self {
+        Self::with_encoder(Encoder::new())
+    }
+
+    /// Construct a CheckedEncoder using a specifically configured encoder
+    pub fn with_encoder(enc: Encoder) -> Self {
+        Self {
+            enc,
+            stack: [0; N],
+            depth: 0,
+            payload: 0,
+            done: false,
+        }
+    }
+
+    /// Returns true if exactly one complete top-level value
+    /// has been written
+    pub fn is_complete(&self) -> bool {
+        self.done
+    }
+
+    /// Returns `Ok(())` if a complete top-level value has been written,
+    /// otherwise describes what is missing
+    pub fn finish(&self) -> Result<(), CheckError> {
+        if self.done {
+            Ok(())
+        } else if self.payload > 0 {
+            Err(CheckError::UnterminatedPayload)
         } else {
-            let mut out = [C_EXT32, 0, 0, 0, 0, t as u8];
-            out[1..5].copy_from_slice(&len.to_be_bytes());
-            out.into()
+            Err(CheckError::Underfilled)
+        }
+    }
+
+    /// Discard all state, allowing a new top-level value to be written
+    pub fn reset(&mut self) {
+        self.depth = 0;
+        self.payload = 0;
+        self.done = false;
+    }
+
+    /// Check and encode msgpack bytes for `nil`
+    pub fn enc_nil(&mut self) -> Result<VarBytes, CheckError> {
+        self.check_item()?;
+        self.complete_item();
+        Ok(self.enc.enc_nil())
+    }
+
+    /// Check and encode msgpack bytes for `bool`
+    pub fn enc_bool(&mut self, b: bool) -> Result<VarBytes, CheckError> {
+        self.check_item()?;
+        self.complete_item();
+        Ok(self.enc.enc_bool(b))
+    }
+
+    /// Check and encode msgpack bytes for msgpack `Num` type
+    pub fn enc_num<N2: Into<Num>>(
+        &mut self,
+        n: N2,
+    ) -> Result<VarBytes, CheckError> {
+        self.check_item()?;
+        self.complete_item();
+        Ok(self.enc.enc_num(n))
+    }
+
+    /// Check and encode msgpack bytes for arbitrary binary byte length.
+    /// Exactly `len` bytes must then be passed through `payload()`
+    pub fn enc_bin_len(&mut self, len: u32) -> Result<VarBytes, CheckError> {
+        self.check_item()?;
+        self.start_payload(len);
+        Ok(self.enc.enc_bin_len(len))
+    }
+
+    /// Check and encode msgpack bytes for utf8 string data byte length.
+    /// Exactly `len` bytes must then be passed through `payload()`
+    pub fn enc_str_len(&mut self, len: u32) -> Result<VarBytes, CheckError> {
+        self.check_item()?;
+        self.start_payload(len);
+        Ok(self.enc.enc_str_len(len))
+    }
+
+    /// Check and encode msgpack bytes for msgpack ext byte length.
+    /// Exactly `len` bytes must then be passed through `payload()`
+    pub fn enc_ext_len(
+        &mut self,
+        len: u32,
+        t: i8,
+    ) -> Result<VarBytes, CheckError> {
+        let header = self
+            .enc
+            .try_enc_ext_len(len, t)
+            .map_err(|_| CheckError::ExtUnsupported)?;
+        self.check_item()?;
+        self.start_payload(len);
+        Ok(header)
+    }
+
+    /// Check and encode msgpack bytes for array marker / length.
+    /// Exactly `len` items must follow
+    pub fn enc_arr_len(&mut self, len: u32) -> Result<VarBytes, CheckError> {
+        self.check_container()?;
+        self.start_container(len as u64);
+        Ok(self.enc.enc_arr_len(len))
+    }
+
+    /// Check and encode msgpack bytes for map marker / length.
+    /// Exactly `len` key / value pairs must follow
+    pub fn enc_map_len(&mut self, len: u32) -> Result<VarBytes, CheckError> {
+        self.check_container()?;
+        self.start_container(len as u64 * 2);
+        Ok(self.enc.enc_map_len(len))
+    }
+
+    /// Account for bin / str / ext payload bytes.
+    /// The payload may be passed in multiple chunks.
+    /// Returns the same data, so it can be written directly
+    pub fn payload<'a>(
+        &mut self,
+        data: &'a [u8],
+    ) -> Result<&'a [u8], CheckError> {
+        if data.len() > self.payload as usize {
+            return Err(CheckError::PayloadOverflow);
+        }
+        if data.is_empty() {
+            return Ok(data);
+        }
+        self.payload -= data.len() as u32;
+        if self.payload == 0 {
+            self.complete_item();
+        }
+        Ok(data)
+    }
+
+    // -- private -- //
+
+    fn check_item(&self) -> Result<(), CheckError> {
+        if self.payload > 0 {
+            Err(CheckError::UnterminatedPayload)
+        } else if self.done {
+            Err(CheckError::Overfilled)
+        } else {
+            Ok(())
+        }
+    }
+
+    fn check_container(&self) -> Result<(), CheckError> {
+        self.check_item()?;
+        if self.depth >= N {
+            return Err(CheckError::TooDeep);
+        }
+        Ok(())
+    }
+
+    fn start_payload(&mut self, len: u32) {
+        if len == 0 {
+            self.complete_item();
+        } else {
+            self.payload = len;
+        }
+    }
+
+    fn start_container(&mut self, count: u64) {
+        if count == 0 {
+            self.complete_item();
+        } else {
+            self.stack[self.depth] = count;
+            self.depth += 1;
+        }
+    }
+
+    /// an item is complete, update the container counts
+    fn complete_item(&mut self) {
+        loop {
+            if self.depth == 0 {
+                self.done = true;
+                return;
+            }
+            self.stack[self.depth - 1] -= 1;
+            if self.stack[self.depth - 1] > 0 {
+                return;
+            }
+            // this container is now complete,
+            // which completes an item in its parent
+            self.depth -= 1;
         }
     }
 }
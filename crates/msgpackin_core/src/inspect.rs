@@ -0,0 +1,256 @@
+//! annotated hex dump of encoded MessagePack data
+//!
+//! Disassembles a buffer into a listing with one line per marker:
+//! the byte offset, the raw header bytes, and the marker name with its
+//! length or value, indented by container nesting depth. Str / bin / ext
+//! payloads follow their header, 8 bytes per line with an ascii gutter.
+//! Problems are flagged on lines starting with `!!` - input that ends
+//! mid-value, bytes following the top-level value (which are then
+//! disassembled as further values), and nesting beyond `max_depth`.
+//!
+//! # Example
+//!
+//! ```
+//! use msgpackin_core::inspect::*;
+//!
+//! struct Text<'a>(&'a mut [u8], usize);
+//!
+//! impl core::fmt::Write for Text<'_> {
+//!     fn write_str(&mut self, s: &str) -> core::fmt::Result {
+//!         let end = self.1 + s.len();
+//!         self.0.get_mut(self.1..end).ok_or(core::fmt::Error)?
+//!             .copy_from_slice(s.as_bytes());
+//!         self.1 = end;
+//!         Ok(())
+//!     }
+//! }
+//!
+//! let buf = [
+//!     0x82, 0xa1, b'a', 0xce, 0x00, 0x01, 0x11, 0x70,
+//!     0xa1, b'b', 0x92, 0xc3, 0xc4, 0x02, 0x01, 0xff, 0xc0,
+//! ];
+//! let mut mem = [0; 1024];
+//! let mut out = Text(&mut mem, 0);
+//! inspect(&buf, 1024, &mut out).unwrap();
+//! let len = out.1;
+//! assert_eq!(
+//!     "\
+//! 00000000  82                          fixmap(2)
+//! 00000001  a1                            fixstr len=1
+//! 00000002  61                              |a|
+//! 00000003  ce 00 01 11 70                uint32 = 70000
+//! 00000008  a1                            fixstr len=1
+//! 00000009  62                              |b|
+//! 0000000a  92                            fixarray(2)
+//! 0000000b  c3                              true
+//! 0000000c  c4 02                           bin8 len=2
+//! 0000000e  01 ff                             |..|
+//! 00000010                              !! trailing data, 1 bytes
+//! 00000010  c0                          nil
+//! ",
+//!     core::str::from_utf8(&mem[..len]).unwrap(),
+//! );
+//! ```
+
+use crate::decode::*;
+use core::fmt::Write;
+
+/// raw bytes shown on a header line, enough for a float64 / uint64
+const HEX_COLS: usize = 9;
+
+/// payload bytes shown per line
+const PAYLOAD_COLS: usize = 8;
+
+/// Write an annotated listing of `buf` to `out`, flagging containers
+/// nested deeper than `max_depth`. Only errors from `out` are returned,
+/// problems with the data are written into the listing
+pub fn inspect<W: Write>(
+    buf: &[u8],
+    max_depth: usize,
+    out: &mut W,
+) -> core::fmt::Result {
+    let mut dec = Decoder::new();
+    let mut ins = Inspect {
+        buf,
+        iter: dec.parse(buf),
+        out,
+        max_depth,
+    };
+    if !ins.value(0)? {
+        return Ok(());
+    }
+    let offset = ins.iter.cursor();
+    if offset < buf.len() {
+        ins.line(
+            offset,
+            &[],
+            0,
+            format_args!("!! trailing data, {} bytes", buf.len() - offset),
+        )?;
+    }
+    while ins.iter.cursor() < buf.len() {
+        if !ins.value(0)? {
+            break;
+        }
+    }
+    Ok(())
+}
+
+struct Inspect<'dec, 'buf, 'out, W: Write> {
+    buf: &'buf [u8],
+    iter: TokenIter<'dec, 'buf>,
+    out: &'out mut W,
+    max_depth: usize,
+}
+
+impl<W: Write> Inspect<'_, '_, '_, W> {
+    /// list one complete value, returns `false` if the listing
+    /// cannot continue past it
+    fn value(&mut self, depth: usize) -> Result<bool, core::fmt::Error> {
+        let offset = self.iter.cursor();
+        let (token, format) = match self.iter.next_with_format() {
+            Some((token, Some(format))) => (token, format),
+            _ => {
+                let rest = &self.buf[offset..];
+                self.line(offset, rest, depth, format_args!("!! truncated"))?;
+                return Ok(false);
+            }
+        };
+        let header = &self.buf[offset..self.iter.cursor()];
+        let name = format.name();
+        match token {
+            Token::Nil if format == Format::Reserved => {
+                self.line(
+                    offset,
+                    header,
+                    depth,
+                    format_args!("{} !! decoded as nil", name),
+                )?;
+            }
+            Token::Nil | Token::Bool(_) => {
+                self.line(offset, header, depth, format_args!("{}", name))?;
+            }
+            Token::Num(n) => {
+                self.line(
+                    offset,
+                    header,
+                    depth,
+                    format_args!("{} = {}", name, n),
+                )?;
+            }
+            Token::Len(t, len) => match t {
+                LenType::Arr | LenType::Map => {
+                    self.line(
+                        offset,
+                        header,
+                        depth,
+                        format_args!("{}({})", name, len),
+                    )?;
+                    if depth >= self.max_depth {
+                        let offset = self.iter.cursor();
+                        self.line(
+                            offset,
+                            &[],
+                            depth + 1,
+                            format_args!("!! max depth exceeded"),
+                        )?;
+                        return Ok(false);
+                    }
+                    let count = match t {
+                        LenType::Map => len as u64 * 2,
+                        _ => len as u64,
+                    };
+                    for _ in 0..count {
+                        if !self.value(depth + 1)? {
+                            return Ok(false);
+                        }
+                    }
+                }
+                _ => return self.payload(offset, header, depth, t, len),
+            },
+            // payload tokens only follow a `Len`, handled in `payload`
+            Token::Bin(_) | Token::BinCont(_, _) => unreachable!(),
+        }
+        Ok(true)
+    }
+
+    /// list a str / bin / ext header and its payload data
+    fn payload(
+        &mut self,
+        offset: usize,
+        header: &[u8],
+        depth: usize,
+        t: LenType,
+        len: u32,
+    ) -> Result<bool, core::fmt::Error> {
+        let name = Format::from_marker(header[0]).name();
+        let data_at = self.iter.cursor();
+        let (data, rem) = match self.iter.next() {
+            Some(Token::Bin(data)) => (data, 0),
+            Some(Token::BinCont(data, rem)) => (data, rem),
+            _ => (&[][..], len),
+        };
+        let invalid = t == LenType::Str
+            && rem == 0
+            && core::str::from_utf8(data).is_err();
+        let flag = if invalid { " !! invalid utf8" } else { "" };
+        match t {
+            LenType::Ext(ext_type) => self.line(
+                offset,
+                header,
+                depth,
+                format_args!("{} type={} len={}", name, ext_type, len),
+            )?,
+            _ => self.line(
+                offset,
+                header,
+                depth,
+                format_args!("{} len={}{}", name, len, flag),
+            )?,
+        }
+        for (i, chunk) in data.chunks(PAYLOAD_COLS).enumerate() {
+            let mut ascii = [b'.'; PAYLOAD_COLS];
+            for (a, b) in ascii.iter_mut().zip(chunk) {
+                if b.is_ascii_graphic() || *b == b' ' {
+                    *a = *b;
+                }
+            }
+            // only ascii is written, so this cannot fail
+            let ascii =
+                core::str::from_utf8(&ascii[..chunk.len()]).unwrap_or("");
+            self.line(
+                data_at + i * PAYLOAD_COLS,
+                chunk,
+                depth + 1,
+                format_args!("|{}|", ascii),
+            )?;
+        }
+        if rem > 0 {
+            self.line(
+                self.iter.cursor(),
+                &[],
+                depth + 1,
+                format_args!("!! truncated, {} more bytes expected", rem),
+            )?;
+            return Ok(false);
+        }
+        Ok(true)
+    }
+
+    fn line(
+        &mut self,
+        offset: usize,
+        bytes: &[u8],
+        depth: usize,
+        text: core::fmt::Arguments<'_>,
+    ) -> core::fmt::Result {
+        write!(self.out, "{:08x} ", offset)?;
+        for i in 0..HEX_COLS {
+            match bytes.get(i) {
+                Some(b) => write!(self.out, " {:02x}", b)?,
+                None => self.out.write_str("   ")?,
+            }
+        }
+        writeln!(self.out, "  {:indent$}{}", "", text, indent = depth * 2)
+    }
+}
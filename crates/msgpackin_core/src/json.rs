@@ -0,0 +1,876 @@
+//! streaming transcoding between msgpack and JSON text
+//!
+//! # msgpack to JSON
+//!
+//! `JsonWriter` consumes `Token`s, as produced by `TokenIter`, and writes
+//! JSON text to any `core::fmt::Write` sink as it goes. Payloads may
+//! arrive in chunks, nested containers are tracked on a fixed-size stack.
+//!
+//! - bin data is written as a base64 (or hex) string
+//! - ext data is written as `{"type":<i8>,"data":"<base64 or hex>"}`
+//! - map keys that are not strings are written as the JSON text of the key,
+//!   as a string, e.g. `{"7":true}`
+//! - str payloads that are not valid utf8 are written lossily
+//! - non-finite floats are written according to `NonFinite`
+//!
+//! ```
+//! use msgpackin_core::encode::*;
+//! use msgpackin_core::json::*;
+//!
+//! let mut buf = [0; 32];
+//! let mut w = SliceWriter::new(&mut buf);
+//! w.write_map_len(2).unwrap();
+//! w.write_str("a").unwrap();
+//! w.write_arr_len(2).unwrap();
+//! w.write_num(1).unwrap();
+//! w.write_bin(b"hi").unwrap();
+//! w.write_num(7).unwrap();
+//! w.write_nil().unwrap();
+//! let buf = w.into_slice();
+//!
+//! let mut out = String::new();
+//! let used = to_json::<8, _>(buf, &mut out, JsonConfig::default()).unwrap();
+//! assert_eq!(buf.len(), used);
+//! assert_eq!(r#"{"a":[1,"aGk="],"7":null}"#, out);
+//! ```
+//!
+//! # JSON to msgpack
+//!
+//! `from_json()` parses JSON text, writing msgpack to a `SliceWriter` as
+//! it goes, without building any intermediate tree.
+//!
+//! ```
+//! use msgpackin_core::encode::*;
+//! use msgpackin_core::json::*;
+//!
+//! let mut buf = [0; 32];
+//! let mut w = SliceWriter::new(&mut buf);
+//! from_json::<8>(r#"{"a": [1, 2.5, 3e2], "b": null}"#, &mut w).unwrap();
+//!
+//! let mut expect = [0; 32];
+//! let mut e = SliceWriter::new(&mut expect);
+//! e.write_map_len(2).unwrap();
+//! e.write_str("a").unwrap();
+//! e.write_arr_len(3).unwrap();
+//! e.write_num(1).unwrap();
+//! e.write_num(2.5).unwrap();
+//! e.write_num(300).unwrap();
+//! e.write_str("b").unwrap();
+//! e.write_nil().unwrap();
+//! assert_eq!(e.as_slice(), w.as_slice());
+//! ```
+
+use crate::decode::*;
+use crate::encode::*;
+use crate::num::*;
+use core::fmt::Write;
+
+/// Error produced by `JsonWriter`
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum JsonError {
+    /// The output sink returned an error
+    Fmt,
+
+    /// The input ended in the middle of a value
+    Truncated,
+
+    /// Containers were nested deeper than the writer stack allows
+    TooDeep,
+
+    /// A non-finite float was found with `NonFinite::Error`
+    NonFinite,
+}
+
+impl core::fmt::Display for JsonError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        core::fmt::Debug::fmt(self, f)
+    }
+}
+
+impl From<core::fmt::Error> for JsonError {
+    fn from(_: core::fmt::Error) -> Self {
+        JsonError::Fmt
+    }
+}
+
+/// How bin and ext data are written as JSON strings
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub enum BinEncoding {
+    /// standard base64 with padding
+    #[default]
+    Base64,
+
+    /// lowercase hex
+    Hex,
+}
+
+/// How NaN and infinite floats, which JSON cannot express, are written
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub enum NonFinite {
+    /// write `null`
+    #[default]
+    Null,
+
+    /// write the strings `"NaN"`, `"Infinity"` or `"-Infinity"`
+    Str,
+
+    /// fail with `JsonError::NonFinite`
+    Error,
+}
+
+/// Options for `JsonWriter`
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+#[non_exhaustive]
+pub struct JsonConfig {
+    /// how bin and ext data are written
+    pub bin: BinEncoding,
+
+    /// how NaN and infinite floats are written
+    pub non_finite: NonFinite,
+}
+
+/// Transcode the first value in `buf` to JSON text.
+/// `N` is the maximum container nesting depth.
+/// Returns the number of bytes of `buf` consumed
+pub fn to_json<const N: usize, W: Write>(
+    buf: &[u8],
+    out: &mut W,
+    config: JsonConfig,
+) -> Result<usize, JsonError> {
+    let mut json = JsonWriter::<N>::new(config);
+    let mut dec = Decoder::new();
+    let mut iter = dec.parse(buf);
+    while !json.is_complete() {
+        match iter.next() {
+            Some(token) => json.token(token, out)?,
+            None => return Err(JsonError::Truncated),
+        }
+    }
+    Ok(iter.cursor())
+}
+
+/// Streaming msgpack token to JSON text writer, see module docs.
+/// `N` is the maximum container nesting depth
+pub struct JsonWriter<const N: usize> {
+    config: JsonConfig,
+    stack: [Frame; N],
+    depth: usize,
+
+    /// the payload in progress, if any
+    payload: Option<Payload>,
+
+    /// bytes held back across payload chunks: an incomplete utf8
+    /// sequence, or base64 input not yet making up a 3 byte group
+    carry: [u8; 4],
+    carry_len: usize,
+
+    /// depth of a non-string map key being written as a string
+    key: Option<usize>,
+
+    /// a top-level value has been written
+    done: bool,
+}
+
+#[derive(Clone, Copy, Default)]
+struct Frame {
+    is_map: bool,
+
+    /// items written, and items remaining (two per map entry)
+    pos: u64,
+    remaining: u64,
+}
+
+#[derive(Clone, Copy)]
+enum Payload {
+    Str,
+    Bin,
+    Ext,
+}
+
+impl<const N: usize> JsonWriter<N> {
+    /// Construct a new JsonWriter
+    pub fn new(config: JsonConfig) -> Self {
+        Self {
+            config,
+            stack: [Frame::default(); N],
+            depth: 0,
+            payload: None,
+            carry: [0; 4],
+            carry_len: 0,
+            key: None,
+            done: false,
+        }
+    }
+
+    /// True once a complete top-level value has been written.
+    /// Further tokens will begin a new value, separated by a newline
+    pub fn is_complete(&self) -> bool {
+        self.done && self.depth == 0 && self.payload.is_none()
+    }
+
+    /// Write the JSON text for the next token
+    pub fn token<W: Write>(
+        &mut self,
+        token: Token<'_>,
+        out: &mut W,
+    ) -> Result<(), JsonError> {
+        if let Some(kind) = self.payload {
+            let (data, last) = match token {
+                Token::Bin(data) => (data, true),
+                Token::BinCont(data, _) => (data, false),
+                _ => return Err(JsonError::Truncated),
+            };
+            let mut out = Out {
+                w: out,
+                key: self.key.is_some(),
+            };
+            match kind {
+                Payload::Str => self.str_chunk(&mut out, data, last)?,
+                Payload::Bin | Payload::Ext => {
+                    self.bin_chunk(&mut out, data, last)?
+                }
+            }
+            if last {
+                self.payload = None;
+                out.write_str(match kind {
+                    Payload::Ext => "\"}",
+                    _ => "\"",
+                })?;
+                self.value_done(out.w)?;
+            }
+            return Ok(());
+        }
+
+        if self.is_complete() {
+            out.write_char('\n')?;
+            self.done = false;
+        }
+
+        // separators, and quoting for non-string keys
+        if self.depth > 0 {
+            // frames are closed as soon as their last item is written
+            let frame = &mut self.stack[self.depth - 1];
+            let sep = match (frame.pos, frame.is_map, frame.pos % 2) {
+                (0, _, _) => None,
+                (_, true, 1) => Some(':'),
+                _ => Some(','),
+            };
+            let is_key = frame.is_map && frame.pos % 2 == 0;
+            frame.pos += 1;
+            frame.remaining -= 1;
+            let mut out = Out {
+                w: &mut *out,
+                key: self.key.is_some(),
+            };
+            if let Some(sep) = sep {
+                out.write_char(sep)?;
+            }
+            let is_str = matches!(
+                token,
+                Token::Len(LenType::Str, _) | Token::Len(LenType::Bin, _)
+            );
+            if is_key && !is_str && self.key.is_none() {
+                out.write_char('"')?;
+                self.key = Some(self.depth);
+            }
+        }
+
+        let mut o = Out {
+            w: &mut *out,
+            key: self.key.is_some(),
+        };
+        match token {
+            Token::Nil => o.write_str("null")?,
+            Token::Bool(b) => o.write_str(if b { "true" } else { "false" })?,
+            Token::Num(n) => self.num(&mut o, n)?,
+            Token::Len(LenType::Arr, len) | Token::Len(LenType::Map, len) => {
+                let is_map = matches!(token, Token::Len(LenType::Map, _));
+                if self.depth == N {
+                    return Err(JsonError::TooDeep);
+                }
+                o.write_char(if is_map { '{' } else { '[' })?;
+                self.stack[self.depth] = Frame {
+                    is_map,
+                    pos: 0,
+                    remaining: if is_map { len as u64 * 2 } else { len as u64 },
+                };
+                self.depth += 1;
+                if len > 0 {
+                    return Ok(());
+                }
+            }
+            Token::Len(t, _) => {
+                self.payload = Some(match t {
+                    LenType::Str => Payload::Str,
+                    LenType::Ext(t) => {
+                        write!(o, "{{\"type\":{},\"data\":", t)?;
+                        Payload::Ext
+                    }
+                    _ => Payload::Bin,
+                });
+                o.write_char('"')?;
+                return Ok(());
+            }
+            Token::Bin(_) | Token::BinCont(_, _) => {
+                return Err(JsonError::Truncated)
+            }
+        }
+        self.value_done(out)
+    }
+
+    /// close any completed containers, and any non-string key quoting
+    fn value_done<W: Write>(&mut self, out: &mut W) -> Result<(), JsonError> {
+        loop {
+            if self.key == Some(self.depth) {
+                self.key = None;
+                out.write_char('"')?;
+            }
+            if self.depth == 0 {
+                self.done = true;
+                return Ok(());
+            }
+            let frame = &self.stack[self.depth - 1];
+            if frame.remaining > 0 {
+                return Ok(());
+            }
+            let close = if frame.is_map { '}' } else { ']' };
+            self.depth -= 1;
+            Out {
+                w: &mut *out,
+                key: self.key.is_some(),
+            }
+            .write_char(close)?;
+        }
+    }
+
+    fn num<W: Write>(&self, out: &mut W, n: Num) -> Result<(), JsonError> {
+        let f = match n {
+            Num::Unsigned(u) => return Ok(write!(out, "{}", u)?),
+            Num::Signed(i) => return Ok(write!(out, "{}", i)?),
+            Num::F32(f) if f.is_finite() => return Ok(write!(out, "{:?}", f)?),
+            Num::F64(f) if f.is_finite() => return Ok(write!(out, "{:?}", f)?),
+            Num::F32(f) => f as f64,
+            Num::F64(f) => f,
+        };
+        let s = if f.is_nan() {
+            "\"NaN\""
+        } else if f > 0.0 {
+            "\"Infinity\""
+        } else {
+            "\"-Infinity\""
+        };
+        match self.config.non_finite {
+            NonFinite::Null => out.write_str("null")?,
+            NonFinite::Str => out.write_str(s)?,
+            NonFinite::Error => return Err(JsonError::NonFinite),
+        }
+        Ok(())
+    }
+
+    fn str_chunk<W: Write>(
+        &mut self,
+        out: &mut W,
+        mut data: &[u8],
+        last: bool,
+    ) -> Result<(), JsonError> {
+        // complete any sequence split by the previous chunk
+        while self.carry_len > 0 && !data.is_empty() {
+            self.carry[self.carry_len] = data[0];
+            self.carry_len += 1;
+            data = &data[1..];
+            match core::str::from_utf8(&self.carry[..self.carry_len]) {
+                Ok(s) => {
+                    escape(out, s)?;
+                    self.carry_len = 0;
+                }
+                Err(e) if e.error_len().is_none() && self.carry_len < 4 => (),
+                Err(_) => {
+                    // the first byte can never start a valid sequence now,
+                    // re-process the rest along with this chunk
+                    out.write_char(char::REPLACEMENT_CHARACTER)?;
+                    let (rest, len) = (self.carry, self.carry_len);
+                    self.carry_len = 0;
+                    self.str_chunk(out, &rest[1..len], false)?;
+                }
+            }
+        }
+        loop {
+            let err = match core::str::from_utf8(data) {
+                Ok(s) => break escape(out, s)?,
+                Err(err) => err,
+            };
+            let (valid, rest) = data.split_at(err.valid_up_to());
+            if let Ok(valid) = core::str::from_utf8(valid) {
+                escape(out, valid)?;
+            }
+            match err.error_len() {
+                Some(len) => {
+                    out.write_char(char::REPLACEMENT_CHARACTER)?;
+                    data = &rest[len..];
+                }
+                // a sequence split at the end of this chunk
+                None if !last => {
+                    self.carry[..rest.len()].copy_from_slice(rest);
+                    self.carry_len = rest.len();
+                    break;
+                }
+                None => {
+                    out.write_char(char::REPLACEMENT_CHARACTER)?;
+                    break;
+                }
+            }
+        }
+        if last && self.carry_len > 0 {
+            self.carry_len = 0;
+            out.write_char(char::REPLACEMENT_CHARACTER)?;
+        }
+        Ok(())
+    }
+
+    fn bin_chunk<W: Write>(
+        &mut self,
+        out: &mut W,
+        mut data: &[u8],
+        last: bool,
+    ) -> Result<(), JsonError> {
+        if self.config.bin == BinEncoding::Hex {
+            return Ok(write_bin(out, data, BinEncoding::Hex)?);
+        }
+        while self.carry_len > 0 && self.carry_len < 3 && !data.is_empty() {
+            self.carry[self.carry_len] = data[0];
+            self.carry_len += 1;
+            data = &data[1..];
+        }
+        if self.carry_len == 3 {
+            base64(out, &self.carry[..3])?;
+            self.carry_len = 0;
+        }
+        let whole = data.len() - data.len() % 3;
+        base64(out, &data[..whole])?;
+        let rest = &data[whole..];
+        self.carry[self.carry_len..self.carry_len + rest.len()]
+            .copy_from_slice(rest);
+        self.carry_len += rest.len();
+        if last {
+            base64(out, &self.carry[..self.carry_len])?;
+            self.carry_len = 0;
+        }
+        Ok(())
+    }
+}
+
+/// fmt::Write that escapes everything written while inside a non-string
+/// map key, so the key's JSON text becomes a JSON string
+struct Out<'w, W: Write> {
+    w: &'w mut W,
+    key: bool,
+}
+
+impl<W: Write> Write for Out<'_, W> {
+    fn write_str(&mut self, s: &str) -> core::fmt::Result {
+        if self.key {
+            escape(self.w, s)
+        } else {
+            self.w.write_str(s)
+        }
+    }
+}
+
+/// write `s` as the content of a JSON string
+fn escape<W: Write>(out: &mut W, s: &str) -> core::fmt::Result {
+    let mut start = 0;
+    for (i, b) in s.bytes().enumerate() {
+        let esc = match b {
+            b'"' => "\\\"",
+            b'\\' => "\\\\",
+            b'\n' => "\\n",
+            b'\r' => "\\r",
+            b'\t' => "\\t",
+            0x00..=0x1f => "",
+            _ => continue,
+        };
+        out.write_str(&s[start..i])?;
+        if esc.is_empty() {
+            write!(out, "\\u{:04x}", b)?;
+        } else {
+            out.write_str(esc)?;
+        }
+        start = i + 1;
+    }
+    out.write_str(&s[start..])
+}
+
+/// Write `data` as base64 or hex text, as used for bin data in JSON
+/// strings (without the surrounding quotes)
+pub fn write_bin<W: Write>(
+    out: &mut W,
+    data: &[u8],
+    encoding: BinEncoding,
+) -> core::fmt::Result {
+    match encoding {
+        BinEncoding::Base64 => base64(out, data),
+        BinEncoding::Hex => {
+            for b in data {
+                write!(out, "{:02x}", b)?;
+            }
+            Ok(())
+        }
+    }
+}
+
+const B64: &[u8; 64] =
+    b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+/// write standard base64 for `data`, padding a final partial group
+fn base64<W: Write>(out: &mut W, data: &[u8]) -> core::fmt::Result {
+    let mut buf = [0; 64];
+    for block in data.chunks(48) {
+        let mut len = 0;
+        for group in block.chunks(3) {
+            let n = (group[0] as u32) << 16
+                | (*group.get(1).unwrap_or(&0) as u32) << 8
+                | *group.get(2).unwrap_or(&0) as u32;
+            for i in 0..4 {
+                buf[len + i] = if i <= group.len() {
+                    B64[(n >> (18 - 6 * i) & 0x3f) as usize]
+                } else {
+                    b'='
+                };
+            }
+            len += 4;
+        }
+        // only ascii was written above
+        out.write_str(
+            core::str::from_utf8(&buf[..len]).map_err(|_| core::fmt::Error)?,
+        )?;
+    }
+    Ok(())
+}
+
+/// Error produced by `from_json()`
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum JsonParseError {
+    /// The output buffer is too small
+    Full,
+
+    /// Containers were nested deeper than `from_json()` allows
+    TooDeep,
+
+    /// The input is not valid JSON, at this byte offset
+    Syntax(usize),
+}
+
+impl core::fmt::Display for JsonParseError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        core::fmt::Debug::fmt(self, f)
+    }
+}
+
+impl From<Full> for JsonParseError {
+    fn from(_: Full) -> Self {
+        JsonParseError::Full
+    }
+}
+
+/// Parse `json`, which must contain a single JSON value, writing it as
+/// msgpack to `w`. `N` is the maximum container nesting depth.
+///
+/// Numbers without a fraction or exponent, and any others that are
+/// integral and fit an i64, are written as integers. Container headers are
+/// reserved while items are written, then patched and compacted once the
+/// item count is known. On error, partial output may have been written
+pub fn from_json<const N: usize>(
+    json: &str,
+    w: &mut SliceWriter<'_>,
+) -> Result<(), JsonParseError> {
+    let mut p = Parser {
+        src: json.as_bytes(),
+        at: 0,
+    };
+    let mut stack = [(false, 0_usize, 0_u32); N];
+    let mut depth = 0;
+
+    // the first item of a container may instead close it
+    let mut first = false;
+    loop {
+        p.skip_ws();
+        let is_map = depth > 0 && stack[depth - 1].0;
+        let close = if is_map { b'}' } else { b']' };
+        if first && p.peek() == Some(close) {
+            p.at += 1;
+            depth -= 1;
+            let (is_map, header_at, _) = stack[depth];
+            compact(w, header_at, is_map, 0);
+        } else {
+            if depth > 0 {
+                let count = &mut stack[depth - 1].2;
+                *count = count.checked_add(1).ok_or(JsonParseError::Full)?;
+            }
+            if is_map {
+                if p.peek() != Some(b'"') {
+                    return Err(p.err());
+                }
+                p.string(w)?;
+                p.skip_ws();
+                p.expect(b':')?;
+                p.skip_ws();
+            }
+            match p.peek() {
+                Some(b'[') | Some(b'{') => {
+                    if depth == N {
+                        return Err(JsonParseError::TooDeep);
+                    }
+                    let is_map = p.peek() == Some(b'{');
+                    p.at += 1;
+                    let header_at = if is_map {
+                        w.reserve_map_len()?
+                    } else {
+                        w.reserve_arr_len()?
+                    };
+                    stack[depth] = (is_map, header_at, 0);
+                    depth += 1;
+                    first = true;
+                    continue;
+                }
+                Some(b'"') => p.string(w)?,
+                Some(b't') => p.literal(b"true", w, |w| w.write_bool(true))?,
+                Some(b'f') => {
+                    p.literal(b"false", w, |w| w.write_bool(false))?
+                }
+                Some(b'n') => p.literal(b"null", w, |w| w.write_nil())?,
+                _ => p.number(w)?,
+            }
+        }
+        first = false;
+
+        // close containers, until one has more items
+        loop {
+            if depth == 0 {
+                p.skip_ws();
+                return match p.peek() {
+                    None => Ok(()),
+                    Some(_) => Err(p.err()),
+                };
+            }
+            p.skip_ws();
+            let (is_map, header_at, count) = stack[depth - 1];
+            if p.peek() == Some(b',') {
+                p.at += 1;
+                break;
+            }
+            p.expect(if is_map { b'}' } else { b']' })?;
+            depth -= 1;
+            compact(w, header_at, is_map, count);
+        }
+    }
+}
+
+/// replace a reserved 5 byte header with the smallest one for `count`
+fn compact(w: &mut SliceWriter<'_>, at: usize, is_map: bool, count: u32) {
+    let mut enc = Encoder::new();
+    let header = if is_map {
+        enc.enc_map_len(count)
+    } else {
+        enc.enc_arr_len(count)
+    };
+    let (buf, cursor) = w.parts_mut();
+    buf.copy_within(at + 5..*cursor, at + header.len());
+    buf[at..at + header.len()].copy_from_slice(&header);
+    *cursor -= 5 - header.len();
+}
+
+struct Parser<'a> {
+    src: &'a [u8],
+    at: usize,
+}
+
+impl Parser<'_> {
+    fn err(&self) -> JsonParseError {
+        JsonParseError::Syntax(self.at)
+    }
+
+    fn peek(&self) -> Option<u8> {
+        self.src.get(self.at).copied()
+    }
+
+    fn skip_ws(&mut self) {
+        while let Some(b' ' | b'\t' | b'\n' | b'\r') = self.peek() {
+            self.at += 1;
+        }
+    }
+
+    fn expect(&mut self, b: u8) -> Result<(), JsonParseError> {
+        if self.peek() != Some(b) {
+            return Err(self.err());
+        }
+        self.at += 1;
+        Ok(())
+    }
+
+    fn literal<F>(
+        &mut self,
+        lit: &[u8],
+        w: &mut SliceWriter<'_>,
+        f: F,
+    ) -> Result<(), JsonParseError>
+    where
+        F: FnOnce(&mut SliceWriter<'_>) -> Result<(), Full>,
+    {
+        if !self.src[self.at..].starts_with(lit) {
+            return Err(self.err());
+        }
+        self.at += lit.len();
+        Ok(f(w)?)
+    }
+
+    fn number(
+        &mut self,
+        w: &mut SliceWriter<'_>,
+    ) -> Result<(), JsonParseError> {
+        let start = self.at;
+        let digits = |p: &mut Self| {
+            let from = p.at;
+            while let Some(b'0'..=b'9') = p.peek() {
+                p.at += 1;
+            }
+            p.at - from
+        };
+        if self.peek() == Some(b'-') {
+            self.at += 1;
+        }
+        let int_at = self.at;
+        match digits(self) {
+            0 => return Err(self.err()),
+            n if n > 1 && self.src[int_at] == b'0' => {
+                return Err(JsonParseError::Syntax(int_at))
+            }
+            _ => (),
+        }
+        let mut integral = true;
+        if self.peek() == Some(b'.') {
+            self.at += 1;
+            integral = false;
+            if digits(self) == 0 {
+                return Err(self.err());
+            }
+        }
+        if let Some(b'e' | b'E') = self.peek() {
+            self.at += 1;
+            integral = false;
+            if let Some(b'+' | b'-') = self.peek() {
+                self.at += 1;
+            }
+            if digits(self) == 0 {
+                return Err(self.err());
+            }
+        }
+        // only ascii was consumed above
+        let text = core::str::from_utf8(&self.src[start..self.at])
+            .map_err(|_| JsonParseError::Syntax(start))?;
+        if integral {
+            if let Ok(i) = text.parse::<i64>() {
+                return Ok(w.write_num(i)?);
+            }
+            if let Ok(u) = text.parse::<u64>() {
+                return Ok(w.write_num(u)?);
+            }
+        }
+        let f = text
+            .parse::<f64>()
+            .map_err(|_| JsonParseError::Syntax(start))?;
+        // -2^63 and 2^63 are exact as f64
+        let min = i64::MIN as f64;
+        if (min..-min).contains(&f) && f == (f as i64) as f64 {
+            return Ok(w.write_num(f as i64)?);
+        }
+        Ok(w.write_num(f)?)
+    }
+
+    /// write a JSON string, measuring the unescaped length first
+    fn string(
+        &mut self,
+        w: &mut SliceWriter<'_>,
+    ) -> Result<(), JsonParseError> {
+        self.at += 1;
+        let start = self.at;
+        let mut len = 0_usize;
+        let end = unescape(self.src, start, |b| {
+            len += b.len();
+            Ok(())
+        })?;
+        let len = u32::try_from(len).map_err(|_| JsonParseError::Full)?;
+        w.write_str_len(len)?;
+        unescape(self.src, start, |b| w.write_raw(b))?;
+        self.at = end + 1;
+        Ok(())
+    }
+}
+
+/// unescape the JSON string content starting at `at`, passing the utf8
+/// bytes to `f`. Returns the offset of the closing quote
+fn unescape<F>(
+    src: &[u8],
+    mut at: usize,
+    mut f: F,
+) -> Result<usize, JsonParseError>
+where
+    F: FnMut(&[u8]) -> Result<(), Full>,
+{
+    let hex4 = |at: usize| -> Result<u32, JsonParseError> {
+        let digits = src.get(at..at + 4).ok_or(JsonParseError::Syntax(at))?;
+        let mut n = 0;
+        for d in digits {
+            let v = (*d as char)
+                .to_digit(16)
+                .ok_or(JsonParseError::Syntax(at))?;
+            n = n << 4 | v;
+        }
+        Ok(n)
+    };
+    let mut run = at;
+    loop {
+        match src.get(at) {
+            None => return Err(JsonParseError::Syntax(at)),
+            Some(b'"') => {
+                f(&src[run..at])?;
+                return Ok(at);
+            }
+            Some(0x00..=0x1f) => return Err(JsonParseError::Syntax(at)),
+            Some(b'\\') => {
+                f(&src[run..at])?;
+                let esc = src.get(at + 1).ok_or(JsonParseError::Syntax(at))?;
+                let c = match esc {
+                    b'"' => '"',
+                    b'\\' => '\\',
+                    b'/' => '/',
+                    b'b' => '\u{8}',
+                    b'f' => '\u{c}',
+                    b'n' => '\n',
+                    b'r' => '\r',
+                    b't' => '\t',
+                    b'u' => {
+                        let mut n = hex4(at + 2)?;
+                        at += 4;
+                        if (0xd800..0xdc00).contains(&n)
+                            && src.get(at + 2..at + 4) == Some(b"\\u")
+                        {
+                            let lo = hex4(at + 4)?;
+                            if (0xdc00..0xe000).contains(&lo) {
+                                n = 0x10000
+                                    + ((n - 0xd800) << 10 | (lo - 0xdc00));
+                                at += 6;
+                            }
+                        }
+                        // lone surrogates cannot be represented in utf8
+                        char::from_u32(n).unwrap_or(char::REPLACEMENT_CHARACTER)
+                    }
+                    _ => return Err(JsonParseError::Syntax(at + 1)),
+                };
+                f(c.encode_utf8(&mut [0; 4]).as_bytes())?;
+                at += 2;
+                run = at;
+            }
+            Some(_) => at += 1,
+        }
+    }
+}
@@ -5,13 +5,18 @@
 //!
 //! This crate:
 //! - is written in pure Rust
-//! - has no dependencies
+//! - has no required dependencies - the optional `serde` feature enables
+//!   no-alloc serde serialization into / deserialization from byte slices
+//!   (see the `ser` and `de` modules)
 //! - is always `#![no_std]` - there is no feature flag to enable std lib
 //! - never imports the `alloc` crate - there is no feature flag to do so
-//! - is infallible - there are no error types / Result types
+//! - has an infallible core Encoder and Decoder - there are no error types
+//!   / Result types on the base encode / decode apis
 //!   - the one accommodation to make this happen is that the msgpack
 //!     "reserved" marker (`0xc1`) will be decoded as if it were a `Nil`
 //!     marker (`0xc0`)
+//!   - helpers layered on top, such as `SliceWriter`, report errors
+//!     like running out of buffer space
 //!
 //! # Example
 //!
@@ -24,32 +29,23 @@
 //!
 //! // this is a no_std, no alloc crate, everything must be on the stack
 //! let mut buf: [u8; 15] = [0; 15];
-//! let mut cur = 0;
 //!
 //! {
-//!     // small helper closure to write consecutive data to our buffer
-//!     let mut write = |data: &[u8]| {
-//!         buf[cur..cur + data.len()].copy_from_slice(data);
-//!         cur += data.len();
-//!     };
-//!
-//!     // construct a new encoder
-//!     let mut enc = Encoder::new();
+//!     // construct a new writer over our buffer
+//!     let mut w = SliceWriter::new(&mut buf);
 //!
 //!     // write the bytes marking an array msgpack type of length 2
-//!     write(&enc.enc_arr_len(2));
-//!
-//!     // write the length of the string we are trying to encode
-//!     write(&enc.enc_str_len(S1.as_bytes().len() as u32));
+//!     w.write_arr_len(2).unwrap();
 //!
-//!     // write the actual string bytes
-//!     write(S1.as_bytes());
+//!     // write the first string (length header and utf8 bytes)
+//!     w.write_str(S1).unwrap();
 //!
-//!     // write the second string length
-//!     write(&enc.enc_str_len(S2.as_bytes().len() as u32));
+//!     // write the second string
+//!     w.write_str(S2).unwrap();
 //!
-//!     // write the second string bytes
-//!     write(S2.as_bytes());
+//!     // our buffer is now full
+//!     assert_eq!(15, w.written());
+//!     assert_eq!(Err(Full), w.write_nil());
 //! }
 //!
 //! // make sure we wrote the correct bytes to the buffer
@@ -84,5 +80,25 @@ pub mod num;
 pub mod decode;
 pub mod encode;
 
+pub mod const_enc;
+
+pub mod canonical;
+pub mod dom;
+pub mod inspect;
+pub mod json;
+pub mod payload;
+pub mod query;
+pub mod transform;
+pub mod utf8;
+
+#[cfg(feature = "serde")]
+const EXT_STRUCT_NAME: &str = "_ExtStruct";
+
+#[cfg(feature = "serde")]
+pub mod ser;
+
+#[cfg(feature = "serde")]
+pub mod de;
+
 #[cfg(test)]
 mod test;
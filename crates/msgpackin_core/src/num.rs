@@ -5,7 +5,10 @@ pub trait NumTo<T> {
     /// Will this Num instance fit in the target type?
     /// Fits is defined as lossless conversion.
     /// E.g. an `f64` value of `42.0` will fit in all rust data types,
-    /// but a `u16` value of `256` will not fit in a `u8`
+    /// but a `u16` value of `256` will not fit in a `u8`.
+    /// A negative value never fits in an unsigned type, and an unsigned
+    /// value does not fit in a signed type if the cast would wrap
+    /// negative
     fn fits(&self) -> bool;
 
     /// Convert this Num instance into the destination type.
@@ -86,7 +89,10 @@ impl Num {
     /// Will this Num instance fit in the target type?
     /// Fits is defined as lossless conversion.
     /// E.g. an `f64` value of `42.0` will fit in all rust data types,
-    /// but a `u16` value of `256` will not fit in a `u8`
+    /// but a `u16` value of `256` will not fit in a `u8`.
+    /// A negative value never fits in an unsigned type, and an unsigned
+    /// value does not fit in a signed type if the cast would wrap
+    /// negative
     pub fn fits<T>(&self) -> bool
     where
         Self: NumTo<T>,
@@ -104,31 +110,66 @@ impl Num {
     {
         NumTo::to(self)
     }
+
+    /// Returns true if this Num instance is backed by float storage
+    pub fn is_float(&self) -> bool {
+        matches!(self, Num::F32(_) | Num::F64(_))
+    }
+
+    /// Get the most compact lossless representation of this Num instance.
+    /// Floats with integral values become integers (e.g. `42.0` becomes
+    /// `Unsigned(42)`) and `f64` values that are exactly representable
+    /// as `f32` become `F32`. Negative zero remains a float,
+    /// and NaN values are left untouched so their payloads are preserved
+    pub fn compact(&self) -> Num {
+        match *self {
+            Num::F32(f) => {
+                if let Some(n) = compact_int(f as f64) {
+                    n
+                } else {
+                    Num::F32(f)
+                }
+            }
+            Num::F64(f) => {
+                if let Some(n) = compact_int(f) {
+                    n
+                } else if f as f32 as f64 == f {
+                    Num::F32(f as f32)
+                } else {
+                    Num::F64(f)
+                }
+            }
+            n => n,
+        }
+    }
+}
+
+/// if a float is exactly representable as an integer, return that integer
+fn compact_int(f: f64) -> Option<Num> {
+    // 2^64 and -2^63, exactly representable as f64
+    const U64_END: f64 = 18446744073709551616.0;
+    const I64_MIN: f64 = -9223372036854775808.0;
+
+    if f == 0.0 && f.is_sign_negative() {
+        None
+    } else if (0.0..U64_END).contains(&f) && f as u64 as f64 == f {
+        Some(Num::Unsigned(f as u64))
+    } else if (I64_MIN..0.0).contains(&f) && f as i64 as f64 == f {
+        Some(Num::Signed(f as i64))
+    } else {
+        None
+    }
 }
 
 impl From<f32> for Num {
     fn from(t: f32) -> Self {
-        if t >= 0.0 && t as u64 as f32 == t {
-            Num::Unsigned(t as u64)
-        } else if t as i64 as f32 == t {
-            Num::Signed(t as i64)
-        } else {
-            Num::F32(t)
-        }
+        Num::F32(t)
     }
 }
 
 impl From<f64> for Num {
     fn from(t: f64) -> Self {
-        if t >= 0.0 && t as u64 as f64 == t {
-            Num::Unsigned(t as u64)
-        } else if t as i64 as f64 == t {
-            Num::Signed(t as i64)
-        } else if t as f32 as f64 == t {
-            Num::F32(t as f32)
-        } else {
-            Num::F64(t)
-        }
+        Num::F64(t)
     }
 }
 
@@ -152,8 +193,16 @@ macro_rules! num_to {
                 match self {
                     Num::F32(f) => *f as $t as f32 == *f,
                     Num::F64(f) => *f as $t as f64 == *f,
-                    Num::Signed(i) => *i as $t as i64 == *i,
-                    Num::Unsigned(u) => *u as $t as u64 == *u,
+                    // the sign check catches wrapping casts
+                    // e.g. `-1_i64 as u64 as i64 == -1_i64`
+                    Num::Signed(i) => {
+                        *i as $t as i64 == *i
+                            && (*i < 0) == ((*i as $t) < <$t>::default())
+                    }
+                    Num::Unsigned(u) => {
+                        *u as $t as u64 == *u
+                            && (*u as $t) >= <$t>::default()
+                    }
                 }
             }
 
@@ -183,7 +232,7 @@ impl NumTo<u128> for Num {
         match self {
             Num::F32(f) => *f as u128 as f32 == *f,
             Num::F64(f) => *f as u128 as f64 == *f,
-            Num::Signed(i) => *i as u128 as i64 == *i,
+            Num::Signed(i) => *i >= 0,
             Num::Unsigned(u) => *u as u128 as u64 == *u,
         }
     }
@@ -219,4 +268,51 @@ mod num_tests {
 
         test_to!(u8 u16 u32 u64 u128 usize i8 i16 i32 i64 i128 isize f32 f64);
     }
+
+    #[test]
+    fn test_float_fidelity() {
+        assert!(matches!(Num::from(42.0_f64), Num::F64(f) if f == 42.0));
+        assert!(matches!(Num::from(0.5_f64), Num::F64(f) if f == 0.5));
+        assert!(matches!(Num::from(42.0_f32), Num::F32(f) if f == 42.0));
+        assert!(
+            matches!(Num::from(-0.0_f64), Num::F64(f) if f.is_sign_negative())
+        );
+        let nan = f64::from_bits(0x7ff8_0000_0000_beef);
+        assert!(matches!(
+            Num::from(nan),
+            Num::F64(f) if f.to_bits() == nan.to_bits()
+        ));
+    }
+
+    #[test]
+    fn test_compact() {
+        assert!(matches!(Num::from(42.0_f64).compact(), Num::Unsigned(42)));
+        assert!(matches!(Num::from(-42.0_f32).compact(), Num::Signed(-42)));
+        assert!(
+            matches!(Num::from(0.5_f64).compact(), Num::F32(f) if f == 0.5)
+        );
+        assert!(matches!(Num::from(0.1_f64).compact(), Num::F64(_)));
+        assert!(matches!(
+            Num::from(-0.0_f64).compact(),
+            Num::F32(f) if f == 0.0 && f.is_sign_negative()
+        ));
+        assert!(matches!(Num::from(f64::NAN).compact(), Num::F64(_)));
+        assert!(matches!(
+            Num::from(18446744073709551616.0_f64).compact(),
+            Num::F32(_)
+        ));
+        assert!(matches!(Num::from(7_u8).compact(), Num::Unsigned(7)));
+    }
+
+    #[test]
+    fn test_fits_sign() {
+        assert!(!Num::from(-1_i8).fits::<u8>());
+        assert!(!Num::from(-1_i8).fits::<u64>());
+        assert!(!Num::from(-1_i8).fits::<u128>());
+        assert!(!Num::from(u64::MAX).fits::<i64>());
+        assert!(Num::from(u64::MAX).fits::<i128>());
+        assert!(Num::from(-1_i64).fits::<i8>());
+        assert!(Num::from(-1_i64).fits::<f32>());
+        assert!(Num::from(42_u64).fits::<u8>());
+    }
 }
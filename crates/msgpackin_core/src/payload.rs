@@ -0,0 +1,109 @@
+//! accumulate chunked str / bin / ext payloads into a caller buffer
+//!
+//! A payload that arrives across several buffers (e.g. a 200 byte string
+//! in 64 byte packets) is decoded as a series of `Token::BinCont`
+//! fragments. `PayloadBuf` collects them into caller-supplied storage,
+//! across as many `parse` calls as needed.
+//!
+//! ```
+//! use msgpackin_core::decode::*;
+//! use msgpackin_core::payload::*;
+//!
+//! let packets: [&[u8]; 3] = [&[0xa5, b'h', b'e'], &[b'l', b'l'], &[b'o']];
+//!
+//! let mut storage = [0; 16];
+//! let mut acc = PayloadBuf::new(&mut storage);
+//! let mut dec = Decoder::new();
+//!
+//! let mut iter = dec.parse(packets[0]);
+//! assert_eq!(Some(Token::Len(LenType::Str, 5)), iter.next());
+//! assert_eq!(Accum::NeedMore, acc.begin(&mut iter));
+//! assert_eq!(Accum::NeedMore, acc.resume(&mut dec.parse(packets[1])));
+//! assert_eq!(Accum::Complete(5), acc.resume(&mut dec.parse(packets[2])));
+//! assert_eq!(b"hello", acc.data());
+//! ```
+
+use crate::decode::*;
+
+/// Status of a `PayloadBuf` accumulation
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Accum {
+    /// The full payload, of this length, is available from `data()`
+    Complete(usize),
+
+    /// The current buffer was exhausted, call `resume()`
+    /// with an iterator over the next buffer
+    NeedMore,
+
+    /// The payload does not fit in the caller buffer. The decoder will
+    /// skip the remainder of it, continuing into subsequent `parse` calls
+    /// if necessary, so decoding can simply carry on with the next value
+    TooLarge,
+
+    /// The decoder was not expecting payload data, i.e. `begin()` was
+    /// not called directly after a bin / str / ext `Len` token, or
+    /// `resume()` was called when no payload was in progress
+    NoPayload,
+}
+
+/// Accumulates one str / bin / ext payload at a time into a caller buffer
+pub struct PayloadBuf<'a> {
+    buf: &'a mut [u8],
+    len: usize,
+}
+
+impl<'a> PayloadBuf<'a> {
+    /// Construct a new PayloadBuf over caller storage
+    pub fn new(buf: &'a mut [u8]) -> Self {
+        Self { buf, len: 0 }
+    }
+
+    /// The largest payload that can be accumulated
+    pub fn capacity(&self) -> usize {
+        self.buf.len()
+    }
+
+    /// The bytes accumulated so far. After `Accum::Complete`,
+    /// this is the entire payload
+    pub fn data(&self) -> &[u8] {
+        &self.buf[..self.len]
+    }
+
+    /// Begin accumulating a payload. Call this immediately after `iter`
+    /// yields the `Token::Len` of a bin / str / ext value, the payload
+    /// length is taken from the decoder
+    pub fn begin(&mut self, iter: &mut TokenIter<'_, '_>) -> Accum {
+        self.len = 0;
+        match iter.payload_remaining() {
+            None => Accum::NoPayload,
+            Some(len) if len as usize > self.buf.len() => {
+                iter.skip_value();
+                Accum::TooLarge
+            }
+            Some(_) => self.resume(iter),
+        }
+    }
+
+    /// Continue accumulating from an iterator over the next buffer,
+    /// after `begin()` or `resume()` returned `Accum::NeedMore`
+    pub fn resume(&mut self, iter: &mut TokenIter<'_, '_>) -> Accum {
+        let rem = match iter.payload_remaining() {
+            None => return Accum::NoPayload,
+            Some(rem) => rem as usize,
+        };
+        if rem > self.buf.len() - self.len {
+            iter.skip_value();
+            return Accum::TooLarge;
+        }
+        while iter.payload_remaining().is_some() {
+            let data = match iter.next() {
+                None => return Accum::NeedMore,
+                Some(Token::Bin(data)) | Some(Token::BinCont(data, _)) => data,
+                Some(_) => return Accum::NoPayload,
+            };
+            self.buf[self.len..self.len + data.len()].copy_from_slice(data);
+            self.len += data.len();
+        }
+        Accum::Complete(self.len)
+    }
+}
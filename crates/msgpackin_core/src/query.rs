@@ -0,0 +1,113 @@
+//! zero-allocation path queries over encoded MessagePack bytes
+//!
+//! # Example
+//!
+//! ```
+//! use msgpackin_core::decode::Token;
+//! use msgpackin_core::query::{get, get_token, PathSeg::*};
+//!
+//! // {"users": [{"name": "bob"}]}
+//! let buf = [
+//!     0x81, 0xa5, b'u', b's', b'e', b'r', b's', 0x91, 0x81, 0xa4, b'n',
+//!     b'a', b'm', b'e', 0xa3, b'b', b'o', b'b',
+//! ];
+//!
+//! let name = get(&buf, &[Key("users"), Index(0), Key("name")]);
+//! assert_eq!(Some(&[0xa3, b'b', b'o', b'b'][..]), name);
+//!
+//! let user_count = get_token(&buf, &[Key("users")]);
+//! assert!(matches!(user_count, Some(Token::Len(_, 1))));
+//!
+//! assert_eq!(None, get(&buf, &[Key("users"), Index(1)]));
+//! ```
+
+use crate::decode::*;
+
+/// A single step in a query path
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum PathSeg<'lt> {
+    /// Select the value for the given utf8 string key of a map
+    Key(&'lt str),
+
+    /// Select the item at the given index of an array
+    Index(u32),
+}
+
+/// Locate the value at `path` within `buf`, returning the raw encoded
+/// sub-slice for that value (including its marker). Values other than
+/// those on the path are skipped without being decoded.
+/// Returns `None` if the path does not exist, or if `buf` is truncated
+pub fn get<'buf>(buf: &'buf [u8], path: &[PathSeg<'_>]) -> Option<&'buf [u8]> {
+    let start = find(buf, path)?;
+    let mut dec = Decoder::new();
+    let mut iter = dec.parse(&buf[start..]);
+    if !iter.skip_value() {
+        return None;
+    }
+    Some(&buf[start..start + iter.cursor()])
+}
+
+/// Locate the value at `path` within `buf`, returning the first decoded
+/// token of that value. For containers this is the `Len` token.
+/// For bin / str / ext values this is also the `Len` token,
+/// use `get` if you need access to the payload as well.
+/// Returns `None` if the path does not exist, or if `buf` is truncated
+pub fn get_token<'buf>(
+    buf: &'buf [u8],
+    path: &[PathSeg<'_>],
+) -> Option<Token<'buf>> {
+    let start = find(buf, path)?;
+    let mut dec = Decoder::new();
+    let mut iter = dec.parse(&buf[start..]);
+    iter.next()
+}
+
+/// returns the byte offset of the value at path
+fn find(buf: &[u8], path: &[PathSeg<'_>]) -> Option<usize> {
+    let mut dec = Decoder::new();
+    let mut iter = dec.parse(buf);
+    for seg in path {
+        match (seg, iter.next()?) {
+            (PathSeg::Index(idx), Token::Len(LenType::Arr, len)) => {
+                if *idx >= len {
+                    return None;
+                }
+                for _ in 0..*idx {
+                    if !iter.skip_value() {
+                        return None;
+                    }
+                }
+            }
+            (PathSeg::Key(key), Token::Len(LenType::Map, len)) => {
+                let mut found = false;
+                for _ in 0..len {
+                    if iter.peek_kind()? == Kind::Str {
+                        iter.next()?;
+                        if let Token::Bin(data) = iter.next()? {
+                            if data == key.as_bytes() {
+                                found = true;
+                                break;
+                            }
+                        } else {
+                            return None;
+                        }
+                    } else if !iter.skip_value() {
+                        return None;
+                    }
+                    if !iter.skip_value() {
+                        return None;
+                    }
+                }
+                if !found {
+                    return None;
+                }
+            }
+            _ => return None,
+        }
+    }
+    if iter.cursor() < buf.len() {
+        Some(iter.cursor())
+    } else {
+        None
+    }
+}
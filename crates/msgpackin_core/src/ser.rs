@@ -0,0 +1,547 @@
+//! `feature=serde` no-alloc serde Serializer writing into a byte slice
+//!
+//! Sequences and maps of unknown length are written with a 5 byte
+//! array / map header which is patched once the item count is known.
+//!
+//! ```
+//! # #[cfg(feature = "serde")]
+//! # {
+//! use msgpackin_core::ser::*;
+//!
+//! let mut buf = [0; 16];
+//! let len = to_slice(&("hello", [1, 2, 3]), &mut buf).unwrap();
+//! assert_eq!(b"\x92\xa5hello\x93\x01\x02\x03", &buf[..len]);
+//!
+//! let mut small = [0; 4];
+//! assert_eq!(Err(SerError::Full), to_slice(&"hello", &mut small));
+//! # }
+//! ```
+
+use crate::decode::*;
+use crate::encode::*;
+use crate::EXT_STRUCT_NAME;
+use serde::{ser, Serialize};
+
+/// Error produced by the slice `Serializer`
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SerError {
+    /// The output buffer is too small
+    Full,
+
+    /// A str, bin, seq or map length did not fit in a u32
+    TooLong,
+
+    /// Ext values cannot be written in legacy compat mode
+    ExtUnsupported,
+
+    /// A custom error was reported by a `Serialize` implementation
+    Custom,
+}
+
+impl core::fmt::Display for SerError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        core::fmt::Debug::fmt(self, f)
+    }
+}
+
+impl ser::StdError for SerError {}
+
+impl ser::Error for SerError {
+    fn custom<T: core::fmt::Display>(_msg: T) -> Self {
+        SerError::Custom
+    }
+}
+
+impl From<Full> for SerError {
+    fn from(_: Full) -> Self {
+        SerError::Full
+    }
+}
+
+/// Serialize into `buf`, returning the number of bytes written
+pub fn to_slice<T>(t: &T, buf: &mut [u8]) -> Result<usize, SerError>
+where
+    T: Serialize + ?Sized,
+{
+    let mut ser = Serializer::new(buf);
+    t.serialize(&mut ser)?;
+    Ok(ser.written())
+}
+
+/// Msgpackin no-alloc serde Serializer
+pub struct Serializer<'a> {
+    w: SliceWriter<'a>,
+    legacy: bool,
+}
+
+impl<'a> Serializer<'a> {
+    /// Construct a new Serializer over the given buffer
+    pub fn new(buf: &'a mut [u8]) -> Self {
+        Self::with_encoder(buf, Encoder::new())
+    }
+
+    /// Construct a new Serializer using a specifically configured encoder
+    pub fn with_encoder(buf: &'a mut [u8], enc: Encoder) -> Self {
+        Self {
+            legacy: enc.compat().is_legacy(),
+            w: SliceWriter::with_encoder(buf, enc),
+        }
+    }
+
+    /// The number of bytes written so far
+    pub fn written(&self) -> usize {
+        self.w.written()
+    }
+
+    /// Consume this serializer, returning the bytes written
+    pub fn into_slice(self) -> &'a [u8] {
+        self.w.into_slice()
+    }
+
+    /// if the value written at `start` is an `[i8, bin]` pair,
+    /// rewrite it in place as an ext value (which is never longer)
+    fn rewrite_ext(&mut self, start: usize) {
+        let (buf, cursor) = self.w.parts_mut();
+        let mut dec = Decoder::new();
+        let mut iter = dec.parse(&buf[start..*cursor]).value_tokens();
+        let (t, len) = match (iter.next(), iter.next(), iter.next()) {
+            (
+                Some(ValueToken::Arr(2)),
+                Some(ValueToken::Num(t)),
+                Some(ValueToken::Bin(data)),
+            ) if t.fits::<i8>() && iter.next().is_none() => {
+                (t.to::<i8>(), data.len())
+            }
+            // not an ext pair, leave the plain encoding
+            _ => return,
+        };
+        let header = Encoder::new().enc_ext_len(len as u32, t);
+        let data_at = start + header.len();
+        buf.copy_within(*cursor - len..*cursor, data_at);
+        buf[start..data_at].copy_from_slice(&header);
+        *cursor = data_at + len;
+    }
+}
+
+fn len_u32(len: usize) -> Result<u32, SerError> {
+    u32::try_from(len).map_err(|_| SerError::TooLong)
+}
+
+impl<'s, 'a> ser::Serializer for &'s mut Serializer<'a> {
+    type Ok = ();
+    type Error = SerError;
+    type SerializeSeq = SerializerContainer<'s, 'a>;
+    type SerializeTuple = SerializerContainer<'s, 'a>;
+    type SerializeTupleStruct = SerializerContainer<'s, 'a>;
+    type SerializeTupleVariant = SerializerContainer<'s, 'a>;
+    type SerializeMap = SerializerContainer<'s, 'a>;
+    type SerializeStruct = SerializerContainer<'s, 'a>;
+    type SerializeStructVariant = SerializerContainer<'s, 'a>;
+
+    fn serialize_bool(self, v: bool) -> Result<(), SerError> {
+        Ok(self.w.write_bool(v)?)
+    }
+
+    fn serialize_i8(self, v: i8) -> Result<(), SerError> {
+        Ok(self.w.write_num(v)?)
+    }
+
+    fn serialize_i16(self, v: i16) -> Result<(), SerError> {
+        Ok(self.w.write_num(v)?)
+    }
+
+    fn serialize_i32(self, v: i32) -> Result<(), SerError> {
+        Ok(self.w.write_num(v)?)
+    }
+
+    fn serialize_i64(self, v: i64) -> Result<(), SerError> {
+        Ok(self.w.write_num(v)?)
+    }
+
+    fn serialize_u8(self, v: u8) -> Result<(), SerError> {
+        Ok(self.w.write_num(v)?)
+    }
+
+    fn serialize_u16(self, v: u16) -> Result<(), SerError> {
+        Ok(self.w.write_num(v)?)
+    }
+
+    fn serialize_u32(self, v: u32) -> Result<(), SerError> {
+        Ok(self.w.write_num(v)?)
+    }
+
+    fn serialize_u64(self, v: u64) -> Result<(), SerError> {
+        Ok(self.w.write_num(v)?)
+    }
+
+    fn serialize_f32(self, v: f32) -> Result<(), SerError> {
+        Ok(self.w.write_num(v)?)
+    }
+
+    fn serialize_f64(self, v: f64) -> Result<(), SerError> {
+        Ok(self.w.write_num(v)?)
+    }
+
+    fn serialize_char(self, v: char) -> Result<(), SerError> {
+        self.serialize_str(v.encode_utf8(&mut [0; 4]))
+    }
+
+    fn serialize_str(self, v: &str) -> Result<(), SerError> {
+        self.w.write_str_len(len_u32(v.len())?)?;
+        Ok(self.w.write_raw(v.as_bytes())?)
+    }
+
+    fn serialize_bytes(self, v: &[u8]) -> Result<(), SerError> {
+        self.w.write_bin_len(len_u32(v.len())?)?;
+        Ok(self.w.write_raw(v)?)
+    }
+
+    fn serialize_none(self) -> Result<(), SerError> {
+        Ok(self.w.write_nil()?)
+    }
+
+    fn serialize_some<T>(self, value: &T) -> Result<(), SerError>
+    where
+        T: ?Sized + Serialize,
+    {
+        value.serialize(self)
+    }
+
+    fn serialize_unit(self) -> Result<(), SerError> {
+        Ok(self.w.write_nil()?)
+    }
+
+    fn serialize_unit_struct(
+        self,
+        _name: &'static str,
+    ) -> Result<(), SerError> {
+        Ok(self.w.write_nil()?)
+    }
+
+    fn serialize_unit_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+    ) -> Result<(), SerError> {
+        self.serialize_str(variant)
+    }
+
+    fn serialize_newtype_struct<T>(
+        self,
+        name: &'static str,
+        value: &T,
+    ) -> Result<(), SerError>
+    where
+        T: ?Sized + Serialize,
+    {
+        if name == EXT_STRUCT_NAME {
+            if self.legacy {
+                return Err(SerError::ExtUnsupported);
+            }
+            let start = self.w.written();
+            value.serialize(&mut *self)?;
+            self.rewrite_ext(start);
+            return Ok(());
+        }
+        value.serialize(self)
+    }
+
+    fn serialize_newtype_variant<T>(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+        value: &T,
+    ) -> Result<(), SerError>
+    where
+        T: ?Sized + Serialize,
+    {
+        self.w.write_map_len(1)?;
+        self.serialize_str(variant)?;
+        value.serialize(self)
+    }
+
+    fn serialize_seq(
+        self,
+        len: Option<usize>,
+    ) -> Result<Self::SerializeSeq, SerError> {
+        let reserved = match len {
+            Some(len) => {
+                self.w.write_arr_len(len_u32(len)?)?;
+                None
+            }
+            None => Some(self.w.reserve_arr_len()?),
+        };
+        Ok(SerializerContainer::priv_new(self, reserved))
+    }
+
+    fn serialize_tuple(
+        self,
+        len: usize,
+    ) -> Result<Self::SerializeTuple, SerError> {
+        self.serialize_seq(Some(len))
+    }
+
+    fn serialize_tuple_struct(
+        self,
+        _name: &'static str,
+        len: usize,
+    ) -> Result<Self::SerializeTupleStruct, SerError> {
+        self.serialize_tuple(len)
+    }
+
+    fn serialize_tuple_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+        len: usize,
+    ) -> Result<Self::SerializeTupleVariant, SerError> {
+        self.w.write_map_len(1)?;
+        self.serialize_str(variant)?;
+        self.serialize_tuple(len)
+    }
+
+    fn serialize_map(
+        self,
+        len: Option<usize>,
+    ) -> Result<Self::SerializeMap, SerError> {
+        let reserved = match len {
+            Some(len) => {
+                self.w.write_map_len(len_u32(len)?)?;
+                None
+            }
+            None => Some(self.w.reserve_map_len()?),
+        };
+        Ok(SerializerContainer::priv_new(self, reserved))
+    }
+
+    fn serialize_struct(
+        self,
+        _name: &'static str,
+        len: usize,
+    ) -> Result<Self::SerializeStruct, SerError> {
+        self.serialize_map(Some(len))
+    }
+
+    fn serialize_struct_variant(
+        self,
+        name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+        len: usize,
+    ) -> Result<Self::SerializeStructVariant, SerError> {
+        self.w.write_map_len(1)?;
+        self.serialize_str(variant)?;
+        self.serialize_struct(name, len)
+    }
+
+    fn collect_str<T>(self, value: &T) -> Result<(), SerError>
+    where
+        T: ?Sized + core::fmt::Display,
+    {
+        use core::fmt::Write;
+
+        // measure first, so the str header can precede the data
+        let mut count = Count(0);
+        write!(count, "{}", value).map_err(|_| SerError::Custom)?;
+        self.w.write_str_len(len_u32(count.0)?)?;
+        let mut out = Raw(&mut self.w, None);
+        match write!(out, "{}", value) {
+            Ok(()) => Ok(()),
+            Err(_) => Err(out.1.unwrap_or(SerError::Custom)),
+        }
+    }
+}
+
+/// fmt::Write that only counts bytes
+struct Count(usize);
+
+impl core::fmt::Write for Count {
+    fn write_str(&mut self, s: &str) -> core::fmt::Result {
+        self.0 += s.len();
+        Ok(())
+    }
+}
+
+/// fmt::Write appending raw bytes, recording why a write failed
+struct Raw<'w, 'a>(&'w mut SliceWriter<'a>, Option<SerError>);
+
+impl core::fmt::Write for Raw<'_, '_> {
+    fn write_str(&mut self, s: &str) -> core::fmt::Result {
+        self.0.write_raw(s.as_bytes()).map_err(|e| {
+            self.1 = Some(e.into());
+            core::fmt::Error
+        })
+    }
+}
+
+/// Serializer for containers like arr/map
+pub struct SerializerContainer<'s, 'a> {
+    ser: &'s mut Serializer<'a>,
+
+    /// offset of a reserved header, for containers of unknown length
+    reserved: Option<usize>,
+    count: u32,
+}
+
+impl<'s, 'a> SerializerContainer<'s, 'a> {
+    fn priv_new(ser: &'s mut Serializer<'a>, reserved: Option<usize>) -> Self {
+        Self {
+            ser,
+            reserved,
+            count: 0,
+        }
+    }
+
+    fn item<T>(&mut self, value: &T) -> Result<(), SerError>
+    where
+        T: ?Sized + Serialize,
+    {
+        if self.reserved.is_some() {
+            self.count = self.count.checked_add(1).ok_or(SerError::TooLong)?;
+        }
+        value.serialize(&mut *self.ser)
+    }
+
+    fn end(self) -> Result<(), SerError> {
+        if let Some(offset) = self.reserved {
+            // the header was reserved by this container, so is valid
+            self.ser
+                .w
+                .patch_len(offset, self.count)
+                .map_err(|_| SerError::Custom)?;
+        }
+        Ok(())
+    }
+}
+
+impl ser::SerializeSeq for SerializerContainer<'_, '_> {
+    type Ok = ();
+    type Error = SerError;
+
+    fn serialize_element<T>(&mut self, value: &T) -> Result<(), SerError>
+    where
+        T: ?Sized + Serialize,
+    {
+        self.item(value)
+    }
+
+    fn end(self) -> Result<(), SerError> {
+        SerializerContainer::end(self)
+    }
+}
+
+impl ser::SerializeTuple for SerializerContainer<'_, '_> {
+    type Ok = ();
+    type Error = SerError;
+
+    fn serialize_element<T>(&mut self, value: &T) -> Result<(), SerError>
+    where
+        T: ?Sized + Serialize,
+    {
+        self.item(value)
+    }
+
+    fn end(self) -> Result<(), SerError> {
+        SerializerContainer::end(self)
+    }
+}
+
+impl ser::SerializeTupleStruct for SerializerContainer<'_, '_> {
+    type Ok = ();
+    type Error = SerError;
+
+    fn serialize_field<T>(&mut self, value: &T) -> Result<(), SerError>
+    where
+        T: ?Sized + Serialize,
+    {
+        self.item(value)
+    }
+
+    fn end(self) -> Result<(), SerError> {
+        SerializerContainer::end(self)
+    }
+}
+
+impl ser::SerializeTupleVariant for SerializerContainer<'_, '_> {
+    type Ok = ();
+    type Error = SerError;
+
+    fn serialize_field<T>(&mut self, value: &T) -> Result<(), SerError>
+    where
+        T: ?Sized + Serialize,
+    {
+        self.item(value)
+    }
+
+    fn end(self) -> Result<(), SerError> {
+        SerializerContainer::end(self)
+    }
+}
+
+impl ser::SerializeMap for SerializerContainer<'_, '_> {
+    type Ok = ();
+    type Error = SerError;
+
+    fn serialize_key<T>(&mut self, key: &T) -> Result<(), SerError>
+    where
+        T: ?Sized + Serialize,
+    {
+        self.item(key)
+    }
+
+    fn serialize_value<T>(&mut self, value: &T) -> Result<(), SerError>
+    where
+        T: ?Sized + Serialize,
+    {
+        value.serialize(&mut *self.ser)
+    }
+
+    fn end(self) -> Result<(), SerError> {
+        SerializerContainer::end(self)
+    }
+}
+
+impl ser::SerializeStruct for SerializerContainer<'_, '_> {
+    type Ok = ();
+    type Error = SerError;
+
+    fn serialize_field<T>(
+        &mut self,
+        key: &'static str,
+        value: &T,
+    ) -> Result<(), SerError>
+    where
+        T: ?Sized + Serialize,
+    {
+        ser::SerializeMap::serialize_key(self, key)?;
+        ser::SerializeMap::serialize_value(self, value)
+    }
+
+    fn end(self) -> Result<(), SerError> {
+        SerializerContainer::end(self)
+    }
+}
+
+impl ser::SerializeStructVariant for SerializerContainer<'_, '_> {
+    type Ok = ();
+    type Error = SerError;
+
+    fn serialize_field<T>(
+        &mut self,
+        key: &'static str,
+        value: &T,
+    ) -> Result<(), SerError>
+    where
+        T: ?Sized + Serialize,
+    {
+        ser::SerializeMap::serialize_key(self, key)?;
+        ser::SerializeMap::serialize_value(self, value)
+    }
+
+    fn end(self) -> Result<(), SerError> {
+        SerializerContainer::end(self)
+    }
+}
@@ -1,5 +1,15 @@
+mod canonical_tests;
 mod decode_tests;
+mod dom_tests;
 mod encode_tests;
+mod inspect_tests;
+mod json_tests;
+mod payload_tests;
+mod query_tests;
+#[cfg(feature = "serde")]
+mod serde_tests;
+mod transform_tests;
+mod utf8_tests;
 
 use crate::decode::*;
 use crate::encode::*;
@@ -11,32 +21,23 @@ fn test_lib_doc_demo() {
 
     // this is a no_std, no alloc crate, everything must be on the stack
     let mut buf: [u8; 15] = [0; 15];
-    let mut cur = 0;
 
     {
-        // small helper closure to write consecutive data to our buffer
-        let mut write = |data: &[u8]| {
-            buf[cur..cur + data.len()].copy_from_slice(data);
-            cur += data.len();
-        };
-
-        // construct a new encoder
-        let mut enc = Encoder::new();
+        // construct a new writer over our buffer
+        let mut w = SliceWriter::new(&mut buf);
 
         // write the bytes marking an array msgpack type of length 2
-        write(&enc.enc_arr_len(2));
-
-        // write the length of the string we are trying to encode
-        write(&enc.enc_str_len(S1.as_bytes().len() as u32));
+        w.write_arr_len(2).unwrap();
 
-        // write the actual string bytes
-        write(S1.as_bytes());
+        // write the first string (length header and utf8 bytes)
+        w.write_str(S1).unwrap();
 
-        // write the second string length
-        write(&enc.enc_str_len(S2.as_bytes().len() as u32));
+        // write the second string
+        w.write_str(S2).unwrap();
 
-        // write the second string bytes
-        write(S2.as_bytes());
+        // our buffer is now full
+        assert_eq!(15, w.written());
+        assert_eq!(Err(Full), w.write_nil());
     }
 
     // make sure we wrote the correct bytes to the buffer
@@ -0,0 +1,110 @@
+use crate::canonical::*;
+use crate::encode::Compat;
+
+const FIXTURE_CANONICAL: &[&[u8]] = &[
+    &[0xc0],
+    &[0xc3],
+    &[0x05],
+    &[0xe0],
+    &[0xcc, 0x80],
+    &[0xd0, 0xdf],
+    &[0xcd, 0x01, 0x00],
+    &[0xca, 0x3f, 0xc0, 0x00, 0x00],
+    &[0xcb, 0x40, 0x09, 0x21, 0xfb, 0x54, 0x44, 0x2d, 0x18],
+    &[0xa3, b'a', b'b', b'c'],
+    &[0xc4, 0x01, 0x00],
+    &[0xd4, 0x01, 0x00],
+    &[0x92, 0x01, 0x02],
+    &[0x82, 0xa1, b'a', 0x01, 0xa1, b'b', 0x02],
+];
+
+#[test]
+fn canonical_ok() {
+    for fixture in FIXTURE_CANONICAL {
+        assert_eq!(
+            None,
+            check(fixture, 1024, Compat::Current),
+            "fixture: {:?}",
+            fixture
+        );
+    }
+}
+
+const FIXTURE_NON_CANONICAL: &[(usize, Reason, &[u8])] = &[
+    (0, Reason::Reserved, &[0xc1]),
+    (0, Reason::Int, &[0xce, 0x00, 0x00, 0x00, 0x05]),
+    (0, Reason::Int, &[0xd0, 0x05]),
+    (0, Reason::Int, &[0xd1, 0xff, 0xff]),
+    (0, Reason::Float, &[0xca, 0x42, 0x28, 0x00, 0x00]),
+    (0, Reason::Float, &[0xcb, 0x3f, 0xf8, 0, 0, 0, 0, 0, 0]),
+    (0, Reason::Len, &[0xd9, 0x03, b'a', b'b', b'c']),
+    (0, Reason::Len, &[0xc5, 0x00, 0x01, 0x00]),
+    (0, Reason::Len, &[0xc7, 0x01, 0x01, 0x00]),
+    (1, Reason::Len, &[0x91, 0xdc, 0x00, 0x00]),
+    (
+        4,
+        Reason::KeyOrder,
+        &[0x82, 0xa1, b'b', 0x01, 0xa1, b'a', 0x02],
+    ),
+    (
+        4,
+        Reason::KeyOrder,
+        &[0x82, 0xa1, b'a', 0x01, 0xa1, b'a', 0x02],
+    ),
+    (2, Reason::Truncated, &[0x92, 0x01]),
+    (1, Reason::Truncated, &[0xa3, b'a', b'b']),
+    (1, Reason::Trailing, &[0x01, 0x02]),
+    (2, Reason::Depth, &[0x91, 0x91, 0x91, 0x90]),
+];
+
+#[test]
+fn canonical_violations() {
+    for (offset, reason, fixture) in FIXTURE_NON_CANONICAL {
+        assert_eq!(
+            Some(Violation {
+                offset: *offset,
+                reason: *reason,
+            }),
+            check(fixture, 2, Compat::Current),
+            "fixture: {:?}",
+            fixture,
+        );
+    }
+}
+
+#[test]
+fn canonical_legacy_compat() {
+    // legacy raw strings have no `str 8`, so `str 16` is minimal
+    let mut str16 = [b'a'; 43];
+    str16[..3].copy_from_slice(&[0xda, 0x00, 40]);
+    let mut str8 = [b'a'; 42];
+    str8[..2].copy_from_slice(&[0xd9, 40]);
+    for compat in [Compat::LegacyRawStr, Compat::LegacyRawBin] {
+        assert_eq!(None, check(&str16, 1024, compat));
+        assert_eq!(
+            Some(Violation {
+                offset: 0,
+                reason: Reason::Len,
+            }),
+            check(&str8, 1024, compat),
+        );
+    }
+    assert_eq!(None, check(&str8, 1024, Compat::Current));
+    assert_eq!(
+        Some(Violation {
+            offset: 0,
+            reason: Reason::Len,
+        }),
+        check(&str16, 1024, Compat::Current),
+    );
+
+    // legacy bin data is written as raw
+    assert_eq!(None, check(&[0xa1, 0xff], 1024, Compat::LegacyRawBin));
+    assert_eq!(
+        Some(Violation {
+            offset: 0,
+            reason: Reason::Len,
+        }),
+        check(&[0xc4, 0x01, 0xff], 1024, Compat::LegacyRawBin),
+    );
+}
@@ -9,8 +9,8 @@ fn exec_decode_tests<'a>(fixture: &'a [u8]) -> [Option<Token<'a>>; MAX_TOKS] {
     // first, the straight-forward test
     {
         let mut dec = Decoder::new();
-        let mut iter = dec.parse(fixture);
-        while let Some(token) = iter.next() {
+        let iter = dec.parse(fixture);
+        for token in iter {
             out1[out1_cursor] = Some(token);
             out1_cursor += 1;
         }
@@ -24,8 +24,8 @@ fn exec_decode_tests<'a>(fixture: &'a [u8]) -> [Option<Token<'a>>; MAX_TOKS] {
         let mut dec = Decoder::new();
         let mut start_buf = None;
         for c in 0..fixture.len() {
-            let mut iter = dec.parse(&fixture[c..=c]);
-            while let Some(token) = iter.next() {
+            let iter = dec.parse(&fixture[c..=c]);
+            for token in iter {
                 use Token::*;
                 match token {
                     BinCont(_, _) => {
@@ -68,7 +68,7 @@ fn decode_nil() {
             "expected Some(Nil), got: {:?}",
             res[0],
         );
-        assert!(matches!(res[1], None), "expected None, got: Some(_)");
+        assert!(res[1].is_none(), "expected None, got: Some(_)");
     }
 }
 
@@ -84,7 +84,7 @@ fn decode_bool() {
             fixture.0,
             res[0],
         );
-        assert!(matches!(res[1], None), "expected None, got: Some(_)");
+        assert!(res[1].is_none(), "expected None, got: Some(_)");
     }
 }
 
@@ -116,7 +116,7 @@ fn decode_bin() {
             fixture.0,
             res[1],
         );
-        assert!(matches!(res[2], None), "expected None, got: Some(_)");
+        assert!(res[2].is_none(), "expected None, got: Some(_)");
     }
 }
 
@@ -181,7 +181,7 @@ fn decode_ext() {
             fixture.1,
             res[1],
         );
-        assert!(matches!(res[2], None), "expected None, got: Some(_)");
+        assert!(res[2].is_none(), "expected None, got: Some(_)");
     }
 }
 
@@ -276,7 +276,7 @@ fn decode_str() {
             fixture.0.as_bytes(),
             res[1],
         );
-        assert!(matches!(res[2], None), "expected None, got: Some(_)");
+        assert!(res[2].is_none(), "expected None, got: Some(_)");
     }
 }
 
@@ -295,7 +295,7 @@ fn decode_float_32() {
             fixture.0,
             res[0],
         );
-        assert!(matches!(res[1], None), "expected None, got: Some(_)");
+        assert!(res[1].is_none(), "expected None, got: Some(_)");
     }
 }
 
@@ -317,7 +317,7 @@ fn decode_float_64() {
             fixture.0,
             res[0],
         );
-        assert!(matches!(res[1], None), "expected None, got: Some(_)");
+        assert!(res[1].is_none(), "expected None, got: Some(_)");
     }
 }
 
@@ -332,7 +332,7 @@ fn decode_u8_pos_fixint() {
             u,
             res[0],
         );
-        assert!(matches!(res[1], None), "expected None, got: Some(_)");
+        assert!(res[1].is_none(), "expected None, got: Some(_)");
     }
 }
 
@@ -347,7 +347,7 @@ fn decode_i8_neg_fixint() {
             i,
             res[0],
         );
-        assert!(matches!(res[1], None), "expected None, got: Some(_)");
+        assert!(res[1].is_none(), "expected None, got: Some(_)");
     }
 }
 
@@ -363,7 +363,7 @@ fn decode_u8() {
             u,
             res[0],
         );
-        assert!(matches!(res[1], None), "expected None, got: Some(_)");
+        assert!(res[1].is_none(), "expected None, got: Some(_)");
     }
 }
 
@@ -380,7 +380,7 @@ fn decode_u16() {
             u,
             res[0],
         );
-        assert!(matches!(res[1], None), "expected None, got: Some(_)");
+        assert!(res[1].is_none(), "expected None, got: Some(_)");
     }
 }
 
@@ -397,7 +397,7 @@ fn decode_u32() {
             u,
             res[0],
         );
-        assert!(matches!(res[1], None), "expected None, got: Some(_)");
+        assert!(res[1].is_none(), "expected None, got: Some(_)");
     }
 }
 
@@ -417,7 +417,7 @@ fn decode_u64() {
             u,
             res[0],
         );
-        assert!(matches!(res[1], None), "expected None, got: Some(_)");
+        assert!(res[1].is_none(), "expected None, got: Some(_)");
     }
 }
 
@@ -433,7 +433,7 @@ fn decode_i8() {
             i,
             res[0],
         );
-        assert!(matches!(res[1], None), "expected None, got: Some(_)");
+        assert!(res[1].is_none(), "expected None, got: Some(_)");
     }
 }
 
@@ -450,7 +450,7 @@ fn decode_i16() {
             i,
             res[0],
         );
-        assert!(matches!(res[1], None), "expected None, got: Some(_)");
+        assert!(res[1].is_none(), "expected None, got: Some(_)");
     }
 }
 
@@ -467,7 +467,7 @@ fn decode_i32() {
             i,
             res[0],
         );
-        assert!(matches!(res[1], None), "expected None, got: Some(_)");
+        assert!(res[1].is_none(), "expected None, got: Some(_)");
     }
 }
 
@@ -487,7 +487,7 @@ fn decode_i64() {
             i,
             res[0],
         );
-        assert!(matches!(res[1], None), "expected None, got: Some(_)");
+        assert!(res[1].is_none(), "expected None, got: Some(_)");
     }
 }
 
@@ -538,7 +538,7 @@ fn decode_arr() {
             );
             idx += 1;
         }
-        assert!(matches!(res[idx], None), "expected None, got: Some(_)");
+        assert!(res[idx].is_none(), "expected None, got: Some(_)");
     }
 }
 
@@ -581,6 +581,164 @@ fn decode_map() {
             );
             idx += 1;
         }
-        assert!(matches!(res[idx], None), "expected None, got: Some(_)");
+        assert!(res[idx].is_none(), "expected None, got: Some(_)");
+    }
+}
+
+// [ {"a": [1, 2]}, bin8([1, 2, 3]), str8("hi"), 7 ]
+const FIXTURE_SKIP: &[u8] = &[
+    0x94, 0x81, 0xa1, 0x61, 0x92, 0x01, 0x02, 0xc4, 0x03, 0x01, 0x02, 0x03,
+    0xd9, 0x02, 0x68, 0x69, 0x07,
+];
+
+#[test]
+fn decode_skip_value() {
+    let mut dec = Decoder::new();
+    let mut iter = dec.parse(FIXTURE_SKIP);
+    assert_eq!(Some(Token::Len(LenType::Arr, 4)), iter.next());
+    assert!(iter.skip_value());
+    assert!(iter.skip_value());
+    assert_eq!(Some(Token::Len(LenType::Str, 2)), iter.next());
+    assert!(iter.skip_value());
+    assert_eq!(Some(Token::Num(7_u8.into())), iter.next());
+    assert_eq!(None, iter.next());
+}
+
+#[test]
+fn decode_skip_value_byte_by_byte() {
+    let mut dec = Decoder::new();
+    let mut out = [None; MAX_TOKS];
+    let mut out_cursor = 0;
+    let mut skip_next = true;
+    for c in 0..FIXTURE_SKIP.len() {
+        let mut iter = dec.parse(&FIXTURE_SKIP[c..=c]);
+        if skip_next {
+            skip_next = false;
+            assert!(!iter.skip_value());
+        }
+        for token in iter {
+            out[out_cursor] = Some(token);
+            out_cursor += 1;
+        }
+    }
+    assert!(!dec.is_skipping());
+    assert_eq!(None, out[0]);
+
+    let mut dec = Decoder::new();
+    let mut out_cursor = 0;
+    for c in 0..FIXTURE_SKIP.len() {
+        let mut iter = dec.parse(&FIXTURE_SKIP[c..=c]);
+        while let Some(token) = iter.next() {
+            out[out_cursor] = Some(token);
+            out_cursor += 1;
+            if token == Token::Len(LenType::Arr, 4) {
+                iter.skip_value();
+            }
+        }
     }
+    assert!(!dec.is_skipping());
+    assert_eq!(Some(Token::Len(LenType::Arr, 4)), out[0]);
+    assert_eq!(Some(Token::Len(LenType::Bin, 3)), out[1]);
+}
+
+#[test]
+fn decode_skip_large_bin() {
+    let mut dec = Decoder::new();
+    let mut iter = dec.parse(&[0xc6, 0x00, 0x01, 0x00, 0x00, 0xff, 0xff]);
+    assert!(!iter.skip_value());
+    assert_eq!(None, iter.next());
+    assert!(dec.is_skipping());
+    let mut iter = dec.parse(&[0; 65534]);
+    assert!(iter.peek_kind().is_none());
+    assert!(!dec.is_skipping());
+    let mut iter = dec.parse(&[0xc0]);
+    assert_eq!(Some(Token::Nil), iter.next());
+}
+
+#[test]
+fn decode_peek_kind() {
+    let mut dec = Decoder::new();
+    let mut iter = dec.parse(FIXTURE_SKIP);
+    assert_eq!(Some(Kind::Arr), iter.peek_kind());
+    assert_eq!(Some(Kind::Arr), iter.peek_kind());
+    assert_eq!(Some(Token::Len(LenType::Arr, 4)), iter.next());
+    assert_eq!(Some(Kind::Map), iter.peek_kind());
+    iter.skip_value();
+    assert_eq!(Some(Kind::Bin), iter.peek_kind());
+    assert_eq!(Some(Token::Len(LenType::Bin, 3)), iter.next());
+    assert_eq!(None, iter.peek_kind());
+    iter.skip_value();
+    assert_eq!(Some(Kind::Str), iter.peek_kind());
+    iter.skip_value();
+    assert_eq!(Some(Kind::Num), iter.peek_kind());
+    iter.skip_value();
+    assert_eq!(None, iter.peek_kind());
+
+    // peek across a chunk boundary in the middle of a marker
+    let mut dec = Decoder::new();
+    let mut iter = dec.parse(&[0xcd, 0x01]);
+    assert_eq!(Some(Kind::Num), iter.peek_kind());
+    assert_eq!(None, iter.next());
+    let mut iter = dec.parse(&[0x02]);
+    assert_eq!(Some(Kind::Num), iter.peek_kind());
+    assert_eq!(Some(Token::Num(0x0102_u16.into())), iter.next());
+}
+
+#[test]
+fn decode_legacy_raw() {
+    const FIXTURE: &[u8] = &[0xa2, b'h', b'i', 0xda, 0, 1, b'x'];
+
+    let mut dec = Decoder::with_compat(Compat::LegacyRawBin);
+    let mut iter = dec.parse(FIXTURE);
+    assert_eq!(Some(Kind::Bin), iter.peek_kind());
+    assert_eq!(Some(Token::Len(LenType::Bin, 2)), iter.next());
+    assert_eq!(Some(Token::Bin(b"hi")), iter.next());
+    assert_eq!(Some(Token::Len(LenType::Bin, 1)), iter.next());
+    assert_eq!(Some(Token::Bin(b"x")), iter.next());
+    assert_eq!(None, iter.next());
+
+    let mut dec = Decoder::with_compat(Compat::LegacyRawStr);
+    let mut iter = dec.parse(FIXTURE);
+    assert_eq!(Some(Token::Len(LenType::Str, 2)), iter.next());
+}
+
+#[test]
+fn decode_value_tokens() {
+    const FIXTURE: &[u8] = &[
+        0x94, 0xa2, b'h', b'i', 0xa1, 0xff, 0xc4, 0x02, 1, 2, 0xd4, 0xfe, 7,
+    ];
+
+    let mut dec = Decoder::new();
+    let toks: [Option<ValueToken<'_>>; 6] = {
+        let mut iter = dec.parse(FIXTURE).value_tokens();
+        [(); 6].map(|_| iter.next())
+    };
+    assert_eq!(
+        [
+            Some(ValueToken::Arr(4)),
+            Some(ValueToken::Str("hi")),
+            Some(ValueToken::InvalidStr(&[0xff])),
+            Some(ValueToken::Bin(&[1, 2])),
+            Some(ValueToken::Ext(-2, &[7])),
+            None,
+        ],
+        toks,
+    );
+
+    // chunked payloads produce explicit partial events
+    let mut dec = Decoder::new();
+    let mut iter = dec.parse(&FIXTURE[6..9]).value_tokens();
+    assert_eq!(Some(ValueToken::PayloadStart(LenType::Bin, 2)), iter.next());
+    assert_eq!(Some(ValueToken::Chunk(&[1], 1)), iter.next());
+    assert_eq!(None, iter.next());
+    let mut iter = dec.parse(&FIXTURE[9..]).value_tokens();
+    assert_eq!(Some(ValueToken::Chunk(&[2], 0)), iter.next());
+    assert_eq!(Some(ValueToken::Ext(-2, &[7])), iter.next());
+
+    let mut dec = Decoder::new();
+    let mut iter = dec.parse(&FIXTURE[1..2]).value_tokens();
+    assert_eq!(Some(ValueToken::PayloadStart(LenType::Str, 2)), iter.next());
+    assert_eq!(None, iter.next());
+    let mut iter = dec.parse(&FIXTURE[2..4]).value_tokens();
+    assert_eq!(Some(ValueToken::Chunk(b"hi", 0)), iter.next());
 }
@@ -0,0 +1,86 @@
+use crate::dom::*;
+use crate::query::PathSeg::*;
+
+// {"a": [1, {"b": nil}], 7: bin, "e": ext, "z": {}}
+const FIXTURE_DOM: &[u8] = &[
+    0x84, 0xa1, 0x61, 0x92, 0x01, 0x81, 0xa1, 0x62, 0xc0, 0x07, 0xc4, 0x02,
+    0x01, 0x02, 0xa1, 0x65, 0xd4, 0xff, 0x03, 0xa1, 0x7a, 0x80,
+];
+
+#[test]
+fn dom_navigate() {
+    let mut arena = [Node::Nil; 16];
+    let dom = parse(FIXTURE_DOM, &mut arena, 8).unwrap();
+    assert_eq!(13, dom.len());
+
+    let root = dom.root();
+    assert_eq!(4, root.len());
+    assert_eq!(Node::Map { start: 1, len: 4 }, root.node());
+
+    let keys = [
+        Node::Str("a"),
+        Node::Num(7.into()),
+        Node::Str("e"),
+        Node::Str("z"),
+    ];
+    for (i, (k, _)) in root.entries().enumerate() {
+        assert_eq!(keys[i], k.node());
+    }
+    assert_eq!(0, root.items().count());
+
+    let a = root.get("a").unwrap();
+    let items = [Node::Num(1.into()), Node::Map { start: 11, len: 1 }];
+    for (i, item) in a.items().enumerate() {
+        assert_eq!(items[i], item.node());
+    }
+    assert_eq!(Some(Node::Num(1.into())), a.at(0).map(|n| n.node()));
+    assert!(a.at(2).is_none());
+
+    assert_eq!(
+        Some(Node::Nil),
+        root.path(&[Key("a"), Index(1), Key("b")]).map(|n| n.node())
+    );
+    assert!(root.path(&[Key("a"), Key("b")]).is_none());
+    assert_eq!(Node::Ext(-1, &[3]), root.get("e").unwrap().node());
+    assert!(root.get("z").unwrap().is_empty());
+    assert!(root.get("missing").is_none());
+}
+
+#[test]
+fn dom_errors() {
+    let mut arena = [Node::Nil; 12];
+    assert_eq!(
+        Some(DomError::OutOfNodes),
+        parse(FIXTURE_DOM, &mut arena, 8).err()
+    );
+    let mut arena = [];
+    assert_eq!(
+        Some(DomError::OutOfNodes),
+        parse(FIXTURE_DOM, &mut arena, 8).err()
+    );
+
+    let mut arena = [Node::Nil; 16];
+    assert_eq!(
+        Some(DomError::TooDeep),
+        parse(FIXTURE_DOM, &mut arena, 2).err()
+    );
+    assert_eq!(
+        Some(DomError::Truncated),
+        parse(&FIXTURE_DOM[..FIXTURE_DOM.len() - 1], &mut arena, 8).err()
+    );
+    assert_eq!(
+        Some(DomError::InvalidUtf8),
+        parse(&[0xa1, 0xff], &mut arena, 8).err()
+    );
+
+    // huge declared counts fail fast rather than walking the input
+    assert_eq!(
+        Some(DomError::OutOfNodes),
+        parse(&[0xdd, 0xff, 0xff, 0xff, 0xff], &mut arena, 8).err()
+    );
+    // a max map32 needs 2 * u32::MAX nodes, which overflows 32-bit usize
+    assert_eq!(
+        Some(DomError::OutOfNodes),
+        parse(&[0xdf, 0xff, 0xff, 0xff, 0xff], &mut arena, 8).err()
+    );
+}
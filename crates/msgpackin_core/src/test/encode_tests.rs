@@ -1,3 +1,4 @@
+use crate::const_enc;
 use crate::decode::*;
 use crate::encode::*;
 
@@ -5,13 +6,10 @@ const MAX_TOKS: usize = 32;
 const MAX_ENC_LEN: usize = 1024;
 
 fn check_encode_test(expect: &[u8], result: &[u8]) {
-    fn parse<'a, 'b>(t: &'a mut [Option<Token<'b>>; MAX_TOKS], d: &'b [u8]) {
+    fn parse<'b>(t: &mut [Option<Token<'b>>; MAX_TOKS], d: &'b [u8]) {
         let mut dec = Decoder::new();
-        let mut iter = dec.parse(d);
-        let mut cur = 0;
-        while let Some(token) = iter.next() {
+        for (cur, token) in dec.parse(d).enumerate() {
             t[cur] = Some(token);
-            cur += 1;
         }
     }
 
@@ -84,6 +82,7 @@ fn encode_f32() {
 }
 
 #[test]
+#[allow(clippy::approx_constant)]
 fn encode_f64() {
     let mut enc = Encoder::new();
     let mut buf = TestBuf::new();
@@ -107,7 +106,7 @@ fn encode_pos_fixint() {
 
 #[test]
 fn encode_neg_fixint() {
-    for i in -31..0 {
+    for i in -32..0 {
         let expect = [i as u8];
         let mut enc = Encoder::new();
         let mut buf = TestBuf::new();
@@ -129,7 +128,7 @@ fn encode_u8() {
 
 #[test]
 fn encode_i8() {
-    for i in i8::MIN..=-32 {
+    for i in i8::MIN..=-33 {
         let expect = [0xd0, i as u8];
         let mut enc = Encoder::new();
         let mut buf = TestBuf::new();
@@ -417,3 +416,305 @@ fn encode_map32() {
         check_encode_test(&expect, buf.get());
     }
 }
+
+// non-canonical encodings that must survive a decode / encode round trip
+const FIXTURE_FORMAT: &[u8] = &[
+    0xde, 0x00, 0x03, // map16(3)
+    0xd9, 0x01, b'a', // str8("a")
+    0xdd, 0x00, 0x00, 0x00, 0x05, // arr32(5)
+    0xce, 0x00, 0x00, 0x00, 0x05, // u32(5)
+    0xd1, 0xff, 0xff, // i16(-1)
+    0xcb, 0x40, 0x45, 0, 0, 0, 0, 0, 0, // f64(42.0)
+    0xca, 0x3f, 0xc0, 0x00, 0x00, // f32(1.5)
+    0xc1, // reserved
+    0xa1, b'b', // fixstr("b")
+    0xc5, 0x00, 0x01, 0xff, // bin16([0xff])
+    0xda, 0x00, 0x00, // str16("")
+    0xc7, 0x01, 0x05, 0x00, // ext8(5, [0x00])
+];
+
+#[test]
+fn encode_with_format_round_trip() {
+    let mut enc = Encoder::new();
+    let mut buf = TestBuf::new();
+    let mut dec = Decoder::new();
+    let mut iter = dec.parse(FIXTURE_FORMAT);
+    while let Some((token, format)) = iter.next_with_format() {
+        match (token, format) {
+            (Token::Bin(data), None) => buf.put(data),
+            (Token::Nil, Some(f)) => buf.put(&enc.enc_nil_with_format(f)),
+            (Token::Bool(b), Some(_)) => buf.put(&enc.enc_bool(b)),
+            (Token::Num(n), Some(f)) => buf.put(&enc.enc_num_with_format(n, f)),
+            (Token::Len(LenType::Bin, l), Some(f)) => {
+                buf.put(&enc.enc_bin_len_with_format(l, f))
+            }
+            (Token::Len(LenType::Str, l), Some(f)) => {
+                buf.put(&enc.enc_str_len_with_format(l, f))
+            }
+            (Token::Len(LenType::Arr, l), Some(f)) => {
+                buf.put(&enc.enc_arr_len_with_format(l, f))
+            }
+            (Token::Len(LenType::Map, l), Some(f)) => {
+                buf.put(&enc.enc_map_len_with_format(l, f))
+            }
+            (Token::Len(LenType::Ext(t), l), Some(f)) => {
+                buf.put(&enc.enc_ext_len_with_format(l, t, f).unwrap())
+            }
+            oth => panic!("unexpected: {:?}", oth),
+        }
+    }
+    assert_eq!(FIXTURE_FORMAT, buf.get());
+}
+
+#[test]
+fn encode_with_format_fallback() {
+    let mut enc = Encoder::new();
+    assert_eq!(
+        &[0xcd, 0x01, 0x00],
+        &*enc.enc_num_with_format(256, Format::U8)
+    );
+    assert_eq!(&[0xd0, 0x80], &*enc.enc_num_with_format(-128, Format::U64));
+    assert_eq!(
+        &[0xca, 0x3f, 0xc0, 0x00, 0x00],
+        &*enc.enc_num_with_format(1.5_f32, Format::I32),
+    );
+    assert_eq!(
+        &[0xd9, 0x20],
+        &*enc.enc_str_len_with_format(32, Format::FixStr)
+    );
+    assert_eq!(
+        &[0xdc, 0x00, 0x10],
+        &*enc.enc_arr_len_with_format(16, Format::FixArr)
+    );
+    assert_eq!(&[0xc0], &*enc.enc_nil_with_format(Format::Nil));
+}
+
+#[test]
+fn encode_float_policy() {
+    let mut enc = Encoder::new();
+    assert_eq!(
+        &[0xcb, 0x40, 0x45, 0, 0, 0, 0, 0, 0],
+        &*enc.enc_num(42.0_f64)
+    );
+    assert_eq!(&[0xcb, 0x80, 0, 0, 0, 0, 0, 0, 0], &*enc.enc_num(-0.0_f64));
+    assert_eq!(&[0xca, 0x3f, 0x00, 0x00, 0x00], &*enc.enc_num(0.5_f32));
+
+    let mut enc = Encoder::with_float_policy(FloatPolicy::Compact);
+    assert_eq!(&[0x2a], &*enc.enc_num(42.0_f64));
+    assert_eq!(&[0xca, 0x80, 0x00, 0x00, 0x00], &*enc.enc_num(-0.0_f64));
+    assert_eq!(&[0xca, 0x3f, 0x00, 0x00, 0x00], &*enc.enc_num(0.5_f64));
+}
+
+#[test]
+fn encode_slice_writer() {
+    let mut buf = [0; 8];
+    let mut w = SliceWriter::new(&mut buf);
+    w.write_map_len(1).unwrap();
+    w.write_str("abc").unwrap();
+    assert_eq!(5, w.written());
+
+    // neither the header nor the payload are written if it won't fit
+    assert_eq!(Err(Full), w.write_bin(&[1, 2]));
+    assert_eq!(Err(Full), w.write_num(u32::MAX));
+    assert_eq!(5, w.written());
+    assert_eq!(3, w.remaining());
+
+    w.write_bin_len(1).unwrap();
+    w.write_raw(&[0xff]).unwrap();
+    assert_eq!(0, w.remaining());
+    assert_eq!(Err(Full), w.write_nil());
+    assert_eq!(
+        &[0x81, 0xa3, b'a', b'b', b'c', 0xc4, 0x01, 0xff],
+        w.into_slice()
+    );
+}
+
+#[test]
+fn encode_checked() {
+    let mut enc = CheckedEncoder::<4>::new();
+    let mut buf = TestBuf::new();
+    buf.put(&enc.enc_map_len(2).unwrap());
+    buf.put(&enc.enc_str_len(1).unwrap());
+    assert_eq!(Some(CheckError::UnterminatedPayload), enc.enc_nil().err());
+    buf.put(enc.payload(b"a").unwrap());
+    buf.put(&enc.enc_arr_len(0).unwrap());
+    buf.put(&enc.enc_bin_len(0).unwrap());
+    assert!(!enc.is_complete());
+    assert_eq!(Err(CheckError::Underfilled), enc.finish());
+    buf.put(&enc.enc_ext_len(2, -1).unwrap());
+    buf.put(enc.payload(&[1]).unwrap());
+    assert_eq!(Err(CheckError::PayloadOverflow), enc.payload(&[2, 3]));
+    assert_eq!(Err(CheckError::UnterminatedPayload), enc.finish());
+    buf.put(enc.payload(&[2]).unwrap());
+    assert!(enc.is_complete());
+    assert_eq!(Ok(()), enc.finish());
+    assert_eq!(Some(CheckError::Overfilled), enc.enc_num(3).err());
+    assert_eq!(Err(CheckError::PayloadOverflow), enc.payload(&[0]));
+    check_encode_test(
+        &[0x82, 0xa1, b'a', 0x90, 0xc4, 0x00, 0xd5, 0xff, 0x01, 0x02],
+        buf.get(),
+    );
+
+    enc.reset();
+    assert!(!enc.is_complete());
+    enc.enc_arr_len(1).unwrap();
+    enc.enc_arr_len(1).unwrap();
+    enc.enc_arr_len(1).unwrap();
+    enc.enc_arr_len(1).unwrap();
+    assert_eq!(Some(CheckError::TooDeep), enc.enc_arr_len(1).err());
+    enc.enc_bool(true).unwrap();
+    assert!(enc.is_complete());
+    assert_eq!(Some(CheckError::Overfilled), enc.enc_bool(true).err());
+}
+
+#[test]
+fn encode_fixed32_patch() {
+    let mut enc = Encoder::new();
+    let mut buf = TestBuf::new();
+    buf.put(&enc.enc_arr_len_fixed32(0));
+    buf.put(&enc.enc_map_len_fixed32(0));
+    buf.put(&enc.enc_nil());
+    buf.put(&enc.enc_nil());
+    let data = &mut buf.buf[..buf.cur];
+    patch_len(&mut data[5..], 1).unwrap();
+    patch_len(data, 1).unwrap();
+    assert_eq!(Err(InvalidHeader), patch_len(&mut data[10..], 1));
+    assert_eq!(Err(InvalidHeader), patch_len(&mut data[..4], 1));
+    assert_eq!(&[0xdd, 0, 0, 0, 1, 0xdf, 0, 0, 0, 1, 0xc0, 0xc0], &data[..],);
+
+    let mut buf = [0; 16];
+    let mut w = SliceWriter::new(&mut buf);
+    let arr = w.reserve_arr_len().unwrap();
+    for i in 0..3 {
+        w.write_num(i).unwrap();
+    }
+    w.patch_len(arr, 3).unwrap();
+    assert_eq!(Err(InvalidHeader), w.patch_len(5, 3));
+    assert_eq!(Err(InvalidHeader), w.patch_len(8, 3));
+    check_encode_test(&[0xdd, 0, 0, 0, 3, 0, 1, 2], w.into_slice());
+}
+
+#[test]
+fn encode_const_matches_encoder() {
+    let mut enc = Encoder::new();
+    let ints: &[i64] = &[
+        0,
+        1,
+        127,
+        128,
+        255,
+        256,
+        65535,
+        65536,
+        -1,
+        -32,
+        -33,
+        -128,
+        -129,
+        i16::MIN as i64,
+        i32::MIN as i64,
+        i64::MIN,
+        i64::MAX,
+    ];
+    for i in ints.iter() {
+        assert_eq!(&*enc.enc_num(*i), const_enc::enc_i64(*i).as_bytes());
+    }
+    assert_eq!(&*enc.enc_num(u64::MAX), &*const_enc::enc_u64(u64::MAX));
+    assert_eq!(&*enc.enc_nil(), &*const_enc::enc_nil());
+    assert_eq!(&*enc.enc_bool(true), &*const_enc::enc_bool(true));
+    for len in [0, 1, 2, 4, 8, 15, 16, 31, 32, 255, 256, 65535, 65536] {
+        assert_eq!(&*enc.enc_bin_len(len), &*const_enc::enc_bin_len(len));
+        assert_eq!(&*enc.enc_str_len(len), &*const_enc::enc_str_len(len));
+        assert_eq!(&*enc.enc_arr_len(len), &*const_enc::enc_arr_len(len));
+        assert_eq!(&*enc.enc_map_len(len), &*const_enc::enc_map_len(len));
+        assert_eq!(
+            &*enc.enc_ext_len(len, -3),
+            &*const_enc::enc_ext_len(len, -3)
+        );
+    }
+}
+
+#[test]
+fn encode_msgpack_const() {
+    const NIL: &[u8] = crate::msgpack_const!(nil);
+    assert_eq!(&[0xc0], NIL);
+
+    const DATA: &[u8] = b"\x01\x02";
+    const V: &[u8] = crate::msgpack_const!({
+        "a": [true, false, nil],
+        -200: bin(DATA),
+        "ext": ext(-42, DATA),
+        "nested": { 1: [[], {}] },
+        "big": [
+            0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15, 65536,
+        ],
+    });
+
+    let mut buf = [0; 64];
+    let mut w = SliceWriter::new(&mut buf);
+    w.write_map_len(5).unwrap();
+    w.write_str("a").unwrap();
+    w.write_arr_len(3).unwrap();
+    w.write_bool(true).unwrap();
+    w.write_bool(false).unwrap();
+    w.write_nil().unwrap();
+    w.write_num(-200).unwrap();
+    w.write_bin(DATA).unwrap();
+    w.write_str("ext").unwrap();
+    w.write_ext(-42, DATA).unwrap();
+    w.write_str("nested").unwrap();
+    w.write_map_len(1).unwrap();
+    w.write_num(1).unwrap();
+    w.write_arr_len(2).unwrap();
+    w.write_arr_len(0).unwrap();
+    w.write_map_len(0).unwrap();
+    w.write_str("big").unwrap();
+    w.write_arr_len(17).unwrap();
+    for i in 0..16 {
+        w.write_num(i).unwrap();
+    }
+    w.write_num(65536).unwrap();
+    assert_eq!(w.as_slice(), V);
+}
+
+#[test]
+fn encode_legacy_compat() {
+    let mut enc = Encoder::new().with_compat(Compat::LegacyRawStr);
+    assert_eq!(&[0xa5], &*enc.enc_str_len(5));
+    assert_eq!(&[0xda, 0, 40], &*enc.enc_str_len(40));
+    assert_eq!(&[0xdb, 0, 1, 0, 0], &*enc.enc_str_len(65536));
+    assert_eq!(&[0xa5], &*enc.enc_bin_len(5));
+    assert_eq!(&[0xda, 0, 40], &*enc.enc_bin_len(40));
+    assert_eq!(
+        &[0xda, 0, 40],
+        &*enc.enc_str_len_with_format(40, Format::Str8)
+    );
+    assert_eq!(&[0xa2], &*enc.enc_bin_len_with_format(2, Format::Bin8));
+    assert_eq!(Some(ExtUnsupported), enc.try_enc_ext_len(2, 5).err());
+    assert_eq!(
+        Some(ExtUnsupported),
+        enc.enc_ext_len_with_format(2, 5, Format::FixExt2).err()
+    );
+    assert!(enc.enc_token(Token::Len(LenType::Ext(5), 2)).is_err());
+    assert_eq!(
+        &[0xa2],
+        &*enc.enc_token(Token::Len(LenType::Bin, 2)).unwrap()
+    );
+
+    let mut buf = [0; 16];
+    let mut w = SliceWriter::with_encoder(&mut buf, enc);
+    assert_eq!(Err(WriteExtError::ExtUnsupported), w.write_ext(5, b"ab"));
+    assert_eq!(Err(WriteExtError::ExtUnsupported), w.write_ext_len(2, 5));
+    assert_eq!(0, w.written());
+    let mut w = SliceWriter::new(&mut buf[..3]);
+    assert_eq!(Err(WriteExtError::Full), w.write_ext(5, b"ab"));
+    assert_eq!(Ok(()), w.write_ext(5, b"a"));
+
+    let mut enc = CheckedEncoder::<4>::with_encoder(
+        Encoder::new().with_compat(Compat::LegacyRawBin),
+    );
+    assert_eq!(
+        Some(CheckError::ExtUnsupported),
+        enc.enc_ext_len(1, 1).err()
+    );
+}
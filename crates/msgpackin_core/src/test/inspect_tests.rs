@@ -0,0 +1,120 @@
+use crate::encode::*;
+use crate::inspect::*;
+
+/// fixed capacity fmt::Write sink
+struct Text {
+    buf: [u8; 2048],
+    len: usize,
+}
+
+impl Text {
+    fn new() -> Self {
+        Self {
+            buf: [0; 2048],
+            len: 0,
+        }
+    }
+
+    fn as_str(&self) -> &str {
+        core::str::from_utf8(&self.buf[..self.len]).unwrap()
+    }
+}
+
+impl core::fmt::Write for Text {
+    fn write_str(&mut self, s: &str) -> core::fmt::Result {
+        let end = self.len + s.len();
+        if end > self.buf.len() {
+            return Err(core::fmt::Error);
+        }
+        self.buf[self.len..end].copy_from_slice(s.as_bytes());
+        self.len = end;
+        Ok(())
+    }
+}
+
+fn listing(buf: &[u8], max_depth: usize) -> Text {
+    let mut out = Text::new();
+    inspect(buf, max_depth, &mut out).unwrap();
+    out
+}
+
+#[test]
+fn inspect_all_markers() {
+    let mut buf = [0; 128];
+    let mut w = SliceWriter::new(&mut buf);
+    w.write_arr_len(9).unwrap();
+    w.write_nil().unwrap();
+    w.write_bool(false).unwrap();
+    w.write_num(-3).unwrap();
+    w.write_num(-200).unwrap();
+    w.write_num(u64::MAX).unwrap();
+    w.write_num(1.5_f32).unwrap();
+    w.write_str("hello world!").unwrap();
+    w.write_ext(-42, b"ab").unwrap();
+    w.write_ext(7, b"xyz").unwrap();
+    let len = w.written();
+
+    assert_eq!(
+        "\
+00000000  99                          fixarray(9)
+00000001  c0                            nil
+00000002  c2                            false
+00000003  fd                            negative fixint = -3
+00000004  d1 ff 38                      int16 = -200
+00000007  cf ff ff ff ff ff ff ff ff    uint64 = 18446744073709551615
+00000010  ca 3f c0 00 00                float32 = 1.5
+00000015  ac                            fixstr len=12
+00000016  68 65 6c 6c 6f 20 77 6f         |hello wo|
+0000001e  72 6c 64 21                     |rld!|
+00000022  d5 d6                         fixext2 type=-42 len=2
+00000024  61 62                           |ab|
+00000026  c7 03 07                      ext8 type=7 len=3
+00000029  78 79 7a                        |xyz|
+",
+        listing(&buf[..len], 1024).as_str(),
+    );
+}
+
+#[test]
+fn inspect_flags() {
+    // truncated header
+    assert_eq!(
+        "\
+00000000  82                          fixmap(2)
+00000001  cd 01                         !! truncated
+",
+        listing(&[0x82, 0xcd, 0x01], 1024).as_str(),
+    );
+
+    // the reserved marker, invalid utf8, and a truncated trailing value
+    assert_eq!(
+        "\
+00000000  92                          fixarray(2)
+00000001  c1                            (never used) !! decoded as nil
+00000002  a2                            fixstr len=2 !! invalid utf8
+00000003  ff fe                           |..|
+00000005                              !! trailing data, 2 bytes
+00000005  a3                          fixstr len=3
+00000006  61                            |a|
+00000007                                !! truncated, 2 more bytes expected
+",
+        listing(&[0x92, 0xc1, 0xa2, 0xff, 0xfe, 0xa3, b'a'], 1024).as_str(),
+    );
+
+    // trailing data and depth
+    assert_eq!(
+        "\
+00000000  c3                          true
+00000001                              !! trailing data, 3 bytes
+00000001  91                          fixarray(1)
+00000002  91                            fixarray(1)
+00000003                                  !! max depth exceeded
+",
+        listing(&[0xc3, 0x91, 0x91, 0x90], 1).as_str(),
+    );
+
+    assert_eq!(
+        "00000000                              !! truncated\n",
+        listing(&[], 1024).as_str(),
+    );
+}
@@ -0,0 +1,250 @@
+use crate::decode::*;
+use crate::encode::*;
+use crate::json::*;
+
+/// fixed capacity fmt::Write sink
+struct Text {
+    buf: [u8; 256],
+    len: usize,
+}
+
+impl Text {
+    fn new() -> Self {
+        Self {
+            buf: [0; 256],
+            len: 0,
+        }
+    }
+
+    fn as_str(&self) -> &str {
+        core::str::from_utf8(&self.buf[..self.len]).unwrap()
+    }
+}
+
+impl core::fmt::Write for Text {
+    fn write_str(&mut self, s: &str) -> core::fmt::Result {
+        let end = self.len + s.len();
+        if end > self.buf.len() {
+            return Err(core::fmt::Error);
+        }
+        self.buf[self.len..end].copy_from_slice(s.as_bytes());
+        self.len = end;
+        Ok(())
+    }
+}
+
+fn json(buf: &[u8], config: JsonConfig) -> Result<Text, JsonError> {
+    let mut out = Text::new();
+    assert_eq!(buf.len(), to_json::<4, _>(buf, &mut out, config)?);
+    Ok(out)
+}
+
+// {
+//     "s": "a\"b\\\n\x01é",
+//     1: bin(00 ff 10 20),
+//     ["k", -2]: true,
+//     nil: {},
+//     "ext": ext(-42, "hi"),
+//     "f": [1.5_f64, u64::MAX, []],
+// }
+const FIXTURE_JSON: &[u8] = &[
+    0x86, 0xa1, 0x73, 0xa8, 0x61, 0x22, 0x62, 0x5c, 0x0a, 0x01, 0xc3, 0xa9,
+    0x01, 0xc4, 0x04, 0x00, 0xff, 0x10, 0x20, 0x92, 0xa1, 0x6b, 0xfe, 0xc3,
+    0xc0, 0x80, 0xa3, 0x65, 0x78, 0x74, 0xd5, 0xd6, 0x68, 0x69, 0xa1, 0x66,
+    0x93, 0xcb, 0x3f, 0xf8, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0xcf, 0xff,
+    0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0x90,
+];
+
+const EXPECT: &str = concat!(
+    r#"{"s":"a\"b\\\n\u0001é","1":"AP8QIA==","[\"k\",-2]":true,"#,
+    r#""null":{},"ext":{"type":-42,"data":"aGk="},"#,
+    r#""f":[1.5,18446744073709551615,[]]}"#,
+);
+
+#[test]
+fn json_whole() {
+    let out = json(FIXTURE_JSON, JsonConfig::default()).unwrap();
+    assert_eq!(EXPECT, out.as_str());
+
+    let config = JsonConfig {
+        bin: BinEncoding::Hex,
+        ..Default::default()
+    };
+    let out = json(FIXTURE_JSON, config).unwrap();
+    assert!(out.as_str().contains(r#""1":"00ff1020""#));
+    assert!(out.as_str().contains(r#""data":"6869""#));
+}
+
+#[test]
+fn json_chunked() {
+    // feed the input a byte at a time, splitting every payload
+    let mut out = Text::new();
+    let mut jw = JsonWriter::<4>::new(JsonConfig::default());
+    let mut dec = Decoder::new();
+    for (i, byte) in FIXTURE_JSON.iter().enumerate() {
+        for token in dec.parse(core::slice::from_ref(byte)) {
+            jw.token(token, &mut out).unwrap();
+        }
+        assert_eq!(i + 1 == FIXTURE_JSON.len(), jw.is_complete());
+    }
+    assert_eq!(EXPECT, out.as_str());
+
+    // a second top-level value goes on a new line
+    for token in dec.parse(&[0xc3]) {
+        jw.token(token, &mut out).unwrap();
+    }
+    assert!(out.as_str().ends_with("}\ntrue"));
+}
+
+#[test]
+fn json_lossy_utf8() {
+    // a lone continuation byte, and a truncated two byte sequence
+    let out = json(b"\xa5a\x80b\xc3\xa9", JsonConfig::default()).unwrap();
+    assert_eq!("\"a\u{fffd}bé\"", out.as_str());
+    let out = json(b"\xa2a\xc3", JsonConfig::default()).unwrap();
+    assert_eq!("\"a\u{fffd}\"", out.as_str());
+}
+
+#[test]
+fn json_non_finite() {
+    let mut buf = [0; 32];
+    let mut w = SliceWriter::new(&mut buf);
+    w.write_arr_len(3).unwrap();
+    w.write_num(f64::NAN).unwrap();
+    w.write_num(f64::INFINITY).unwrap();
+    w.write_num(f32::NEG_INFINITY).unwrap();
+    let buf = w.into_slice();
+
+    let out = json(buf, JsonConfig::default()).unwrap();
+    assert_eq!("[null,null,null]", out.as_str());
+
+    let config = JsonConfig {
+        non_finite: NonFinite::Str,
+        ..Default::default()
+    };
+    let out = json(buf, config).unwrap();
+    assert_eq!(r#"["NaN","Infinity","-Infinity"]"#, out.as_str());
+
+    let config = JsonConfig {
+        non_finite: NonFinite::Error,
+        ..Default::default()
+    };
+    assert_eq!(Some(JsonError::NonFinite), json(buf, config).err());
+}
+
+#[test]
+fn json_errors() {
+    let mut out = Text::new();
+    assert_eq!(
+        Err(JsonError::Truncated),
+        to_json::<4, _>(
+            &FIXTURE_JSON[..FIXTURE_JSON.len() - 1],
+            &mut out,
+            JsonConfig::default()
+        )
+    );
+    let mut out = Text::new();
+    assert_eq!(
+        Err(JsonError::TooDeep),
+        to_json::<1, _>(FIXTURE_JSON, &mut out, JsonConfig::default())
+    );
+    assert_eq!(
+        Err(JsonError::Fmt),
+        to_json::<4, _>(FIXTURE_JSON, &mut Refuse, JsonConfig::default())
+    );
+}
+
+/// a sink that is always full
+struct Refuse;
+
+impl core::fmt::Write for Refuse {
+    fn write_str(&mut self, _: &str) -> core::fmt::Result {
+        Err(core::fmt::Error)
+    }
+}
+
+fn parse(json: &str, out: &mut [u8; 128]) -> Result<usize, JsonParseError> {
+    let mut w = SliceWriter::new(out);
+    from_json::<4>(json, &mut w)?;
+    Ok(w.written())
+}
+
+#[test]
+fn json_parse_round_trip() {
+    let mut out = [0; 128];
+    let text = json(FIXTURE_JSON, JsonConfig::default()).unwrap();
+    let out_len = parse(text.as_str(), &mut out).unwrap();
+
+    // bin, ext and non-string keys become strings and objects,
+    // after which the conversion is stable
+    let again = json(&out[..out_len], JsonConfig::default()).unwrap();
+    assert_eq!(text.as_str(), again.as_str());
+}
+
+#[test]
+fn json_parse_values() {
+    let mut out = [0; 128];
+    let json = " [ {} , [], \"\\u00e9\\ud83d\\ude00\\ud800\\n\", -0, 1.0, \
+                1e400, -9223372036854775808, 18446744073709551615, 0.5 ] ";
+    let len = parse(json, &mut out).unwrap();
+
+    let mut dec = Decoder::new();
+    let mut iter = dec.parse(&out[..len]).value_tokens();
+    let mut next = || iter.next().unwrap();
+    assert_eq!(ValueToken::Arr(9), next());
+    assert_eq!(ValueToken::Map(0), next());
+    assert_eq!(ValueToken::Arr(0), next());
+    assert_eq!(ValueToken::Str("é😀\u{fffd}\n"), next());
+    assert_eq!(ValueToken::Num(0.into()), next());
+    assert_eq!(ValueToken::Num(1.into()), next());
+    assert_eq!(ValueToken::Num(f64::INFINITY.into()), next());
+    assert_eq!(ValueToken::Num(i64::MIN.into()), next());
+    assert_eq!(ValueToken::Num(u64::MAX.into()), next());
+    assert_eq!(ValueToken::Num(0.5.into()), next());
+
+    // compacted headers match the encoder's own output
+    let mut expect = [0; 128];
+    let mut w = SliceWriter::new(&mut expect);
+    w.write_map_len(1).unwrap();
+    w.write_str("k").unwrap();
+    w.write_arr_len(16).unwrap();
+    for _ in 0..16 {
+        w.write_bool(true).unwrap();
+    }
+    let json = r#"{"k":[true,true,true,true,true,true,true,true,
+        true,true,true,true,true,true,true,true]}"#;
+    let len = parse(json, &mut out).unwrap();
+    assert_eq!(w.as_slice(), &out[..len]);
+}
+
+#[test]
+fn json_parse_errors() {
+    let mut out = [0; 128];
+    let cases = [
+        ("", 0),
+        ("[1,]", 3),
+        ("[1 2]", 3),
+        ("{\"a\" 1}", 5),
+        ("{1: 2}", 1),
+        ("01", 0),
+        ("1.", 2),
+        ("-", 1),
+        ("tru", 0),
+        ("\"a\\x\"", 3),
+        ("\"a\nb\"", 2),
+        ("\"abc", 4),
+        ("[1] 2", 4),
+        ("[1}", 2),
+    ];
+    for (json, at) in cases {
+        let res = parse(json, &mut out);
+        assert_eq!(Err(JsonParseError::Syntax(at)), res, "{:?}", json);
+    }
+    assert_eq!(Err(JsonParseError::TooDeep), parse("[[[[[]]]]]", &mut out));
+    let mut small = [0; 4];
+    let mut w = SliceWriter::new(&mut small);
+    assert_eq!(
+        Err(JsonParseError::Full),
+        from_json::<4>("\"hello\"", &mut w)
+    );
+}
@@ -0,0 +1,122 @@
+use crate::decode::*;
+use crate::payload::*;
+
+// [ "x" * 200, bin(3), true ]
+const FIXTURE_PAYLOAD: [u8; 209] = {
+    let mut f = [b'x'; 209];
+    // array(3), str8(200)
+    f[0] = 0x93;
+    f[1] = 0xd9;
+    f[2] = 0xc8;
+    // bin8(3) [1, 2, 3], true
+    f[203] = 0xc4;
+    f[204] = 0x03;
+    f[205] = 0x01;
+    f[206] = 0x02;
+    f[207] = 0x03;
+    f[208] = 0xc3;
+    f
+};
+
+#[test]
+fn payload_accumulate_packets() {
+    let mut packets = FIXTURE_PAYLOAD.chunks(64);
+
+    let mut storage = [0; 200];
+    let mut acc = PayloadBuf::new(&mut storage);
+    let mut dec = Decoder::new();
+
+    let mut iter = dec.parse(packets.next().unwrap());
+    assert_eq!(Some(Token::Len(LenType::Arr, 3)), iter.next());
+    assert_eq!(Some(Token::Len(LenType::Str, 200)), iter.next());
+    assert_eq!(Accum::NeedMore, acc.begin(&mut iter));
+    assert_eq!(
+        Accum::NeedMore,
+        acc.resume(&mut dec.parse(packets.next().unwrap()))
+    );
+    assert_eq!(
+        Accum::NeedMore,
+        acc.resume(&mut dec.parse(packets.next().unwrap()))
+    );
+
+    let mut iter = dec.parse(packets.next().unwrap());
+    assert_eq!(Accum::Complete(200), acc.resume(&mut iter));
+    assert_eq!(&[b'x'; 200][..], acc.data());
+
+    assert_eq!(Some(Token::Len(LenType::Bin, 3)), iter.next());
+    assert_eq!(Accum::Complete(3), acc.begin(&mut iter));
+    assert_eq!(&[1, 2, 3], acc.data());
+    assert_eq!(Some(Token::Bool(true)), iter.next());
+    assert_eq!(None, iter.next());
+
+    let mut empty = [];
+    let mut acc = PayloadBuf::new(&mut empty);
+    let mut dec = Decoder::new();
+    let mut iter = dec.parse(&[0xa0, 0xc0]);
+    assert_eq!(Some(Token::Len(LenType::Str, 0)), iter.next());
+    assert_eq!(Accum::Complete(0), acc.begin(&mut iter));
+    assert_eq!(Some(Token::Nil), iter.next());
+}
+
+#[test]
+fn payload_too_large_skips() {
+    let mut packets = FIXTURE_PAYLOAD.chunks(64);
+
+    let mut storage = [0; 16];
+    let mut acc = PayloadBuf::new(&mut storage);
+    let mut dec = Decoder::new();
+
+    let mut iter = dec.parse(packets.next().unwrap());
+    assert_eq!(Some(Token::Len(LenType::Arr, 3)), iter.next());
+    assert_eq!(Some(Token::Len(LenType::Str, 200)), iter.next());
+    assert_eq!(Accum::TooLarge, acc.begin(&mut iter));
+    assert_eq!(None, iter.next());
+    assert!(dec.is_skipping());
+
+    // the rest of the string is skipped, decoding resumes after it
+    let mut tokens = [None; 4];
+    let mut i = 0;
+    for packet in packets {
+        for token in dec.parse(packet) {
+            tokens[i] = Some(token);
+            i += 1;
+        }
+    }
+    assert_eq!(
+        [
+            Some(Token::Len(LenType::Bin, 3)),
+            Some(Token::Bin(&[1, 2, 3])),
+            Some(Token::Bool(true)),
+            None,
+        ],
+        tokens,
+    );
+}
+
+#[test]
+fn payload_misuse() {
+    let mut storage = [0; 4];
+    let mut acc = PayloadBuf::new(&mut storage);
+    let mut dec = Decoder::new();
+
+    // no payload pending
+    let mut iter = dec.parse(&[0xc0, 0xc4, 0x08, 1, 2, 3, 4, 5, 6, 7, 8, 0xc3]);
+    assert_eq!(Accum::NoPayload, acc.begin(&mut iter));
+    assert_eq!(Accum::NoPayload, acc.resume(&mut iter));
+    assert_eq!(Some(Token::Nil), iter.next());
+
+    // the length comes from the decoder, not the caller
+    assert_eq!(Some(Token::Len(LenType::Bin, 8)), iter.next());
+    assert_eq!(Accum::TooLarge, acc.begin(&mut iter));
+    assert_eq!(Some(Token::Bool(true)), iter.next());
+
+    // resume on a new payload that was never begun
+    let mut iter = dec.parse(&[0xc4, 0x02, 1]);
+    assert_eq!(Some(Token::Len(LenType::Bin, 2)), iter.next());
+    assert_eq!(Accum::NeedMore, acc.begin(&mut iter));
+    let mut iter = dec.parse(&[2, 0xc4, 0x04, 1, 2]);
+    assert_eq!(Accum::Complete(2), acc.resume(&mut iter));
+    assert_eq!(Some(Token::Len(LenType::Bin, 4)), iter.next());
+    assert_eq!(Accum::TooLarge, acc.resume(&mut iter));
+    assert!(dec.is_skipping());
+}
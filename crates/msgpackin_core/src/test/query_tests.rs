@@ -0,0 +1,63 @@
+use crate::decode::*;
+use crate::query::{PathSeg::*, *};
+
+// {
+//   "skip": bin8([1, 2, 3]),
+//   7: "int key",
+//   "users": [
+//     {"id": 1, "name": "alice"},
+//     {"id": 2, "name": "bob", "tags": ["a"]},
+//   ],
+// }
+const FIXTURE: &[u8] = &[
+    0x83, 0xa4, b's', b'k', b'i', b'p', 0xc4, 0x03, 0x01, 0x02, 0x03, 0x07,
+    0xa7, b'i', b'n', b't', b' ', b'k', b'e', b'y', 0xa5, b'u', b's', b'e',
+    b'r', b's', 0x92, 0x82, 0xa2, b'i', b'd', 0x01, 0xa4, b'n', b'a', b'm',
+    b'e', 0xa5, b'a', b'l', b'i', b'c', b'e', 0x83, 0xa2, b'i', b'd', 0x02,
+    0xa4, b'n', b'a', b'm', b'e', 0xa3, b'b', b'o', b'b', 0xa4, b't', b'a',
+    b'g', b's', 0x91, 0xa1, b'a',
+];
+
+#[test]
+fn query_get() {
+    assert_eq!(Some(FIXTURE), get(FIXTURE, &[]));
+    assert_eq!(
+        Some(&[0xc4, 0x03, 0x01, 0x02, 0x03][..]),
+        get(FIXTURE, &[Key("skip")]),
+    );
+    assert_eq!(
+        Some(&[0xa3, b'b', b'o', b'b'][..]),
+        get(FIXTURE, &[Key("users"), Index(1), Key("name")]),
+    );
+    assert_eq!(
+        Some(&[0x91, 0xa1, b'a'][..]),
+        get(FIXTURE, &[Key("users"), Index(1), Key("tags")]),
+    );
+}
+
+#[test]
+fn query_get_token() {
+    assert_eq!(
+        Some(Token::Num(2_u8.into())),
+        get_token(FIXTURE, &[Key("users"), Index(1), Key("id")]),
+    );
+    assert_eq!(
+        Some(Token::Len(LenType::Str, 5)),
+        get_token(FIXTURE, &[Key("users"), Index(0), Key("name")]),
+    );
+    assert_eq!(
+        Some(Token::Len(LenType::Arr, 2)),
+        get_token(FIXTURE, &[Key("users")]),
+    );
+}
+
+#[test]
+fn query_miss() {
+    assert_eq!(None, get(FIXTURE, &[Key("nope")]));
+    assert_eq!(None, get(FIXTURE, &[Index(0)]));
+    assert_eq!(None, get(FIXTURE, &[Key("users"), Index(2)]));
+    assert_eq!(None, get(FIXTURE, &[Key("users"), Index(0), Key("tags")]));
+    assert_eq!(None, get(FIXTURE, &[Key("skip"), Index(0)]));
+    assert_eq!(None, get(&FIXTURE[..FIXTURE.len() - 1], &[Key("users")]));
+    assert_eq!(None, get(&[], &[]));
+}
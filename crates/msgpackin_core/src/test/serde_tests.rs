@@ -0,0 +1,212 @@
+use crate::de::*;
+use crate::decode::*;
+use crate::ser::*;
+use serde::{Deserialize, Serialize};
+
+/// serde serializes `&[u8]` as a seq, this writes bin
+#[derive(Debug, PartialEq, Deserialize)]
+#[serde(transparent)]
+struct Bytes<'a>(&'a [u8]);
+
+impl Serialize for Bytes<'_> {
+    fn serialize<S: serde::Serializer>(&self, s: S) -> Result<S::Ok, S::Error> {
+        s.serialize_bytes(self.0)
+    }
+}
+
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+enum E<'a> {
+    Unit,
+    New(u8),
+    Tup(u8, bool),
+    Struct { s: &'a str },
+}
+
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+struct X<'a> {
+    nil: (),
+    bool_: bool,
+    int: i8,
+    big_int: u64,
+    float: f64,
+    char_: char,
+    str_: &'a str,
+    #[serde(borrow)]
+    bin: Bytes<'a>,
+    opt: Option<u16>,
+    none: Option<u16>,
+    arr: [E<'a>; 4],
+}
+
+#[test]
+fn serde_slice_round_trip() {
+    let expect = X {
+        nil: (),
+        bool_: true,
+        int: -42,
+        big_int: u64::MAX,
+        float: 1.5,
+        char_: 'é',
+        str_: "hello",
+        bin: Bytes(b"\x00\x01"),
+        opt: Some(300),
+        none: None,
+        arr: [E::Unit, E::New(7), E::Tup(1, false), E::Struct { s: "x" }],
+    };
+
+    let mut buf = [0; 256];
+    let len = to_slice(&expect, &mut buf).unwrap();
+    let decoded: X<'_> = from_slice(&buf[..len]).unwrap();
+    assert_eq!(expect, decoded);
+
+    // every prefix is either too small to write or too short to read
+    for size in 0..len {
+        assert_eq!(Err(SerError::Full), to_slice(&expect, &mut buf[..size]));
+    }
+    let len = to_slice(&expect, &mut buf).unwrap();
+    for size in 0..len {
+        assert_eq!(Err(DeError::Truncated), from_slice::<X<'_>>(&buf[..size]));
+    }
+}
+
+#[test]
+fn serde_slice_unsized() {
+    struct Odd;
+
+    impl Serialize for Odd {
+        fn serialize<S: serde::Serializer>(
+            &self,
+            s: S,
+        ) -> Result<S::Ok, S::Error> {
+            s.collect_seq((0..10_u8).filter(|n| n % 2 == 1))
+        }
+    }
+
+    let mut buf = [0; 16];
+    let len = to_slice(&Odd, &mut buf).unwrap();
+    assert_eq!(b"\xdd\x00\x00\x00\x05\x01\x03\x05\x07\x09", &buf[..len]);
+    assert_eq!([1, 3, 5, 7, 9], from_slice::<[u8; 5]>(&buf[..len]).unwrap());
+
+    let len = to_slice(&format_args!("{}-{}", 4, "two"), &mut buf).unwrap();
+    assert_eq!("4-two", from_slice::<&str>(&buf[..len]).unwrap());
+    assert_eq!(
+        Err(SerError::Full),
+        to_slice(&format_args!("{}-{}", 4, "two"), &mut buf[..5])
+    );
+}
+
+#[test]
+fn serde_slice_ext() {
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    struct _ExtStruct<'a>(#[serde(borrow)] (i8, Bytes<'a>));
+
+    let mut buf = [0; 16];
+    let len = to_slice(&_ExtStruct((-42, Bytes(b"hello"))), &mut buf).unwrap();
+    let mut dec = Decoder::new();
+    let mut iter = dec.parse(&buf[..len]).value_tokens();
+    assert_eq!(Some(ValueToken::Ext(-42, b"hello")), iter.next());
+    assert_eq!(None, iter.next());
+
+    let dec: _ExtStruct<'_> = from_slice(&buf[..len]).unwrap();
+    assert_eq!(_ExtStruct((-42, Bytes(b"hello"))), dec);
+}
+
+#[test]
+fn serde_slice_errors() {
+    let mut buf = [0; 16];
+    let len = to_slice(&300_u16, &mut buf).unwrap();
+    assert_eq!(Err(DeError::Expected("u8")), from_slice::<u8>(&buf[..len]));
+    assert_eq!(
+        Err(DeError::Expected("str")),
+        from_slice::<&str>(&buf[..len])
+    );
+
+    // unconsumed array / map items are not read as following values
+    assert_eq!(
+        Err(DeError::InvalidLength),
+        from_slice::<((u8, u8), u8)>(&[0x92, 0x93, 1, 2, 3, 4])
+    );
+    assert_eq!(
+        Err(DeError::InvalidLength),
+        from_slice::<(u8,)>(&[0x92, 1, 2])
+    );
+    #[derive(Debug, PartialEq, serde::Deserialize)]
+    struct A {
+        a: u8,
+    }
+    assert_eq!(
+        Ok(A { a: 1 }),
+        from_slice::<A>(&[0x82, 0xa1, b'a', 1, 0xa1, b'b', 2])
+    );
+    assert_eq!(Ok(((1, 2), 3)), from_slice(&[0x92, 0x92, 1, 2, 3]));
+
+    // trailing bytes are left for the caller
+    let mut de = Deserializer::new(b"\x01\x02");
+    assert_eq!(1_u8, u8::deserialize(&mut de).unwrap());
+    assert_eq!(1, de.cursor());
+}
+
+/// counts the array nesting of a value, recursing like an owned
+/// dynamic value type such as `serde_json::Value` would
+#[derive(Debug, PartialEq)]
+struct Depth(usize);
+
+impl<'de> Deserialize<'de> for Depth {
+    fn deserialize<D: serde::Deserializer<'de>>(
+        d: D,
+    ) -> Result<Self, D::Error> {
+        struct V;
+
+        impl<'de> serde::de::Visitor<'de> for V {
+            type Value = Depth;
+
+            fn expecting(
+                &self,
+                f: &mut core::fmt::Formatter<'_>,
+            ) -> core::fmt::Result {
+                f.write_str("nested arrays")
+            }
+
+            fn visit_unit<E>(self) -> Result<Depth, E> {
+                Ok(Depth(0))
+            }
+
+            fn visit_seq<A>(self, mut seq: A) -> Result<Depth, A::Error>
+            where
+                A: serde::de::SeqAccess<'de>,
+            {
+                let mut max = 0;
+                while let Some(Depth(d)) = seq.next_element()? {
+                    max = max.max(d);
+                }
+                Ok(Depth(max + 1))
+            }
+        }
+
+        d.deserialize_any(V)
+    }
+}
+
+#[test]
+fn serde_slice_max_depth() {
+    // untrusted input must not overflow the stack
+    static DEEP: [u8; 1 << 20] = [0x91; 1 << 20];
+    assert_eq!(Err(DeError::TooDeep), from_slice::<Depth>(&DEEP));
+
+    let mut buf = [0xc0; DEFAULT_MAX_DEPTH + 2];
+    buf[..DEFAULT_MAX_DEPTH].fill(0x91);
+    assert_eq!(Ok(Depth(DEFAULT_MAX_DEPTH)), from_slice(&buf));
+    buf[DEFAULT_MAX_DEPTH] = 0x91;
+    assert_eq!(Err(DeError::TooDeep), from_slice::<Depth>(&buf));
+
+    let mut de = Deserializer::new(&[0x91, 0x91, 0xc0]).with_max_depth(1);
+    assert_eq!(Err(DeError::TooDeep), Depth::deserialize(&mut de));
+    let mut de = Deserializer::new(&[0x91, 0x91, 0xc0]).with_max_depth(2);
+    assert_eq!(Ok(Depth(2)), Depth::deserialize(&mut de));
+
+    // enum variant maps count as a level
+    let mut de = Deserializer::new(b"\x81\xa3New\x01").with_max_depth(0);
+    assert_eq!(Err(DeError::TooDeep), E::deserialize(&mut de));
+    let mut de = Deserializer::new(b"\x81\xa3New\x01").with_max_depth(1);
+    assert_eq!(Ok(E::New(1)), E::deserialize(&mut de));
+}
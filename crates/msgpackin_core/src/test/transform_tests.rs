@@ -0,0 +1,159 @@
+use crate::decode::*;
+use crate::encode::*;
+use crate::transform::*;
+
+// [ {"a": 1, "b": [1, 2, 3]}, "x", nil ]
+const FIXTURE_TRANSFORM: &[u8] = &[
+    0x93, 0x82, 0xa1, 0x61, 0x01, 0xa1, 0x62, 0x93, 0x01, 0x02, 0x03, 0xa1,
+    0x78, 0xc0,
+];
+
+fn expect<F: FnOnce(&mut SliceWriter<'_>)>(out: &[u8], f: F) {
+    let mut buf = [0; 64];
+    let mut w = SliceWriter::new(&mut buf);
+    f(&mut w);
+    assert_eq!(w.as_slice(), out);
+}
+
+#[test]
+fn encode_token_round_trip() {
+    let mut out = [0; 64];
+    let mut w = SliceWriter::new(&mut out);
+    let mut enc = Encoder::new();
+    let mut dec = Decoder::new();
+    for token in dec.parse(FIXTURE_TRANSFORM) {
+        w.write_raw(&enc.enc_token(token).unwrap()).unwrap();
+    }
+    assert_eq!(FIXTURE_TRANSFORM, w.as_slice());
+}
+
+#[test]
+fn transform_identity() {
+    let mut out = [0; 64];
+    let out_len =
+        transform::<4, _>(FIXTURE_TRANSFORM, &mut out, |_, _| Action::Keep)
+            .unwrap();
+    assert_eq!(FIXTURE_TRANSFORM, &out[..out_len]);
+}
+
+#[test]
+fn transform_drop_replace_inject() {
+    let mut out = [0; 64];
+    let out_len =
+        transform::<4, _>(FIXTURE_TRANSFORM, &mut out, |item, inj| {
+            match (item.depth, item.role, item.token) {
+                // drop the whole "b" entry, including the nested array
+                (2, Role::Key(_), ValueToken::Str("b")) => {
+                    inj.inject(ValueToken::Str("c"));
+                    inj.inject(ValueToken::Bool(true));
+                    Action::Drop
+                }
+                // replace the value of "a"
+                (2, Role::Value(_, ValueToken::Str("a")), _) => {
+                    Action::Replace(ValueToken::Num(42.into()))
+                }
+                // drop the trailing nil, add an item before "x"
+                (1, Role::Item(2), _) => Action::Drop,
+                (1, Role::Item(1), _) => {
+                    inj.inject(ValueToken::Bin(&[7]));
+                    Action::Keep
+                }
+                _ => Action::Keep,
+            }
+        })
+        .unwrap();
+    expect(&out[..out_len], |w| {
+        w.write_arr_len(3).unwrap();
+        w.write_map_len(2).unwrap();
+        w.write_str("a").unwrap();
+        w.write_num(42).unwrap();
+        w.write_str("c").unwrap();
+        w.write_bool(true).unwrap();
+        w.write_bin(&[7]).unwrap();
+        w.write_str("x").unwrap();
+    });
+}
+
+#[test]
+fn transform_drop_value_and_containers() {
+    let mut out = [0; 64];
+    // dropping a map value drops the entry, replacing a
+    // container drops its items
+    let out_len = transform::<4, _>(FIXTURE_TRANSFORM, &mut out, |item, _| {
+        match item.role {
+            Role::Value(0, _) => Action::Drop,
+            Role::Value(1, _) => Action::Replace(ValueToken::Nil),
+            _ => Action::Keep,
+        }
+    })
+    .unwrap();
+    expect(&out[..out_len], |w| {
+        w.write_arr_len(3).unwrap();
+        w.write_map_len(1).unwrap();
+        w.write_str("b").unwrap();
+        w.write_nil().unwrap();
+        w.write_str("x").unwrap();
+        w.write_nil().unwrap();
+    });
+
+    // headers grow when counts cross format boundaries
+    let mut out = [0; 64];
+    let out_len = transform::<4, _>(&[0x91, 0xc0], &mut out, |item, inj| {
+        if let Role::Item(_) = item.role {
+            for i in 0..20 {
+                inj.inject(ValueToken::Num(i.into()));
+            }
+        }
+        Action::Keep
+    })
+    .unwrap();
+    assert_eq!(&[0xdc, 0, 21, 0], &out[..4]);
+    assert_eq!(24, out_len);
+}
+
+#[test]
+fn transform_errors() {
+    let mut out = [0; 64];
+    let keep = |_: &Item<'_>, _: &mut Inject<'_, '_>| Action::Keep;
+
+    assert_eq!(
+        Err(TransformError::Truncated),
+        transform::<4, _>(
+            &FIXTURE_TRANSFORM[..FIXTURE_TRANSFORM.len() - 2],
+            &mut out,
+            keep
+        )
+    );
+    assert_eq!(
+        Err(TransformError::TooDeep),
+        transform::<2, _>(FIXTURE_TRANSFORM, &mut out, keep)
+    );
+    assert_eq!(
+        Err(TransformError::Full),
+        transform::<4, _>(FIXTURE_TRANSFORM, &mut out[..8], keep)
+    );
+    assert_eq!(
+        Err(TransformError::InvalidToken),
+        transform::<4, _>(FIXTURE_TRANSFORM, &mut out, |_, _| {
+            Action::Replace(ValueToken::Arr(0))
+        })
+    );
+    assert_eq!(
+        Err(TransformError::InvalidToken),
+        transform::<4, _>(FIXTURE_TRANSFORM, &mut out, |item, inj| {
+            if let Role::Value(_, _) = item.role {
+                inj.inject(ValueToken::Nil);
+            }
+            Action::Keep
+        })
+    );
+    assert_eq!(
+        Err(TransformError::OddMap),
+        transform::<4, _>(FIXTURE_TRANSFORM, &mut out, |item, inj| {
+            if let Role::Key(0) = item.role {
+                inj.inject(ValueToken::Nil);
+            }
+            Action::Keep
+        })
+    );
+}
@@ -0,0 +1,73 @@
+use crate::utf8::*;
+
+const FIXTURES: &[&[u8]] = &[
+    b"",
+    b"hello",
+    "caf\u{e9} \u{20ac}uro \u{1f600}!".as_bytes(),
+    b"bad \xff byte",
+    b"\xe2\x82",
+    b"trunc \xf0\x9f\x98",
+    b"overlong \xc0\xaf",
+    b"surrogate \xed\xa0\x80",
+    b"lead \xe2\x28\xa1",
+];
+
+fn expect(data: &[u8]) -> Result<(), Utf8Error> {
+    core::str::from_utf8(data)
+        .map(|_| ())
+        .map_err(|e| Utf8Error {
+            offset: e.valid_up_to(),
+        })
+}
+
+fn validate(chunks: &[&[u8]]) -> Result<(), Utf8Error> {
+    let mut v = Utf8Validator::new();
+    for chunk in chunks {
+        v.push(chunk)?;
+    }
+    v.finish()
+}
+
+#[test]
+fn utf8_whole() {
+    for data in FIXTURES {
+        assert_eq!(expect(data), validate(&[data]));
+    }
+}
+
+#[test]
+fn utf8_split_points() {
+    for data in FIXTURES {
+        for i in 0..=data.len() {
+            for j in i..=data.len() {
+                assert_eq!(
+                    expect(data),
+                    validate(&[&data[..i], &data[i..j], &data[j..]]),
+                    "split {:?} at {}, {}",
+                    data,
+                    i,
+                    j,
+                );
+            }
+        }
+    }
+}
+
+#[test]
+fn utf8_byte_by_byte() {
+    for data in FIXTURES {
+        let mut v = Utf8Validator::new();
+        let mut res = Ok(());
+        for b in data.iter() {
+            res = v.push(core::slice::from_ref(b));
+            if res.is_err() {
+                break;
+            }
+        }
+        let res = res.and_then(|_| v.finish());
+        assert_eq!(expect(data), res);
+        if res.is_ok() {
+            assert_eq!(data.len(), v.len());
+        }
+    }
+}
@@ -0,0 +1,426 @@
+//! streaming msgpack transformation
+//!
+//! `transform()` decodes an encoded buffer one value at a time, hands each
+//! to a user closure which may keep, drop or replace it (and inject new
+//! values before it), and re-encodes the result into an output buffer.
+//! Array and map counts are fixed up to match what was actually written.
+//!
+//! ```
+//! use msgpackin_core::decode::ValueToken;
+//! use msgpackin_core::encode::*;
+//! use msgpackin_core::transform::*;
+//!
+//! // {"user": "bob", "password": "hunter2", "age": 42}
+//! let mut input = [0; 64];
+//! let mut w = SliceWriter::new(&mut input);
+//! w.write_map_len(3).unwrap();
+//! w.write_str("user").unwrap();
+//! w.write_str("bob").unwrap();
+//! w.write_str("password").unwrap();
+//! w.write_str("hunter2").unwrap();
+//! w.write_str("age").unwrap();
+//! w.write_num(42).unwrap();
+//! let input = w.into_slice();
+//!
+//! let mut out = [0; 64];
+//! let len = transform::<8, _>(input, &mut out, |item, _| match item.role {
+//!     // strip the "age" field
+//!     Role::Key(_) if item.token == ValueToken::Str("age") => Action::Drop,
+//!     // rename "user" to "name"
+//!     Role::Key(_) if item.token == ValueToken::Str("user") => {
+//!         Action::Replace(ValueToken::Str("name"))
+//!     }
+//!     // redact the password
+//!     Role::Value(_, ValueToken::Str("password")) => {
+//!         Action::Replace(ValueToken::Str("***"))
+//!     }
+//!     _ => Action::Keep,
+//! })
+//! .unwrap();
+//!
+//! let mut expect = [0; 64];
+//! let mut w = SliceWriter::new(&mut expect);
+//! w.write_map_len(2).unwrap();
+//! w.write_str("name").unwrap();
+//! w.write_str("bob").unwrap();
+//! w.write_str("password").unwrap();
+//! w.write_str("***").unwrap();
+//! assert_eq!(w.as_slice(), &out[..len]);
+//! ```
+
+use crate::decode::*;
+use crate::encode::*;
+
+/// Error produced by `transform()`
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TransformError {
+    /// The output buffer is too small
+    Full,
+
+    /// The input ended in the middle of a value
+    Truncated,
+
+    /// Containers were nested deeper than the transform stack allows
+    TooDeep,
+
+    /// A map would be written with a key that has no value
+    OddMap,
+
+    /// A container or partial payload token was used as a replacement
+    /// or injected, or a value was injected between a map key and value
+    InvalidToken,
+}
+
+impl core::fmt::Display for TransformError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        core::fmt::Debug::fmt(self, f)
+    }
+}
+
+/// The position of a value within its parent
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Role<'buf> {
+    /// A top-level value
+    Root,
+
+    /// The item at this index of an array
+    Item(u32),
+
+    /// The key of the map entry at this index
+    Key(u32),
+
+    /// The value of the map entry at this index, along with the
+    /// (original, input) key token of that entry
+    Value(u32, ValueToken<'buf>),
+}
+
+/// A value presented to the `transform()` closure. For arrays and maps,
+/// `token` is the header, and the items follow as subsequent `Item`s
+/// (unless the container is dropped or replaced)
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Item<'buf> {
+    /// The token introducing this value
+    pub token: ValueToken<'buf>,
+
+    /// Container nesting depth, top-level values are at depth zero
+    pub depth: usize,
+
+    /// The position of this value within its parent
+    pub role: Role<'buf>,
+}
+
+/// What `transform()` should do with an `Item`
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Action<'lt> {
+    /// Write the value as-is
+    Keep,
+
+    /// Drop the value, including any container items. Dropping either
+    /// the key or the value of a map entry drops the whole entry
+    Drop,
+
+    /// Write this (non-container) token in place of the value,
+    /// dropping any container items
+    Replace(ValueToken<'lt>),
+}
+
+/// Handle passed to the `transform()` closure
+/// for writing new values ahead of the current one
+pub struct Inject<'a, 'o> {
+    out: &'a mut Out<'o>,
+    allowed: bool,
+    count: u32,
+    err: Option<TransformError>,
+}
+
+impl Inject<'_, '_> {
+    /// Write a (non-container) value before the current one. In a map,
+    /// inject keys and values in pairs, before a key. Errors are reported
+    /// by `transform()` once the closure returns
+    pub fn inject(&mut self, token: ValueToken<'_>) {
+        if self.err.is_some() {
+            return;
+        }
+        if !self.allowed || is_container(&token) {
+            self.err = Some(TransformError::InvalidToken);
+            return;
+        }
+        match self.out.write(token) {
+            Ok(()) => self.count += 1,
+            Err(e) => self.err = Some(e),
+        }
+    }
+}
+
+/// Transform the encoded values in `input` into `output`, see module docs.
+/// `N` is the maximum container nesting depth.
+/// Returns the number of bytes written to `output`
+pub fn transform<'buf, 'lt, const N: usize, F>(
+    input: &'buf [u8],
+    output: &mut [u8],
+    mut f: F,
+) -> Result<usize, TransformError>
+where
+    F: FnMut(&Item<'buf>, &mut Inject<'_, '_>) -> Action<'lt>,
+{
+    let mut dec = Decoder::new();
+    let mut iter = dec.parse(input).value_tokens();
+    let mut out = Out {
+        buf: output,
+        cursor: 0,
+        enc: Encoder::new(),
+    };
+    let mut stack = [Frame::default(); N];
+    let mut depth = 0;
+
+    loop {
+        // close any containers whose items have all been read
+        while depth > 0 && stack[depth - 1].remaining == 0 {
+            depth -= 1;
+            let frame = &stack[depth];
+            let header = if frame.is_map {
+                if frame.items % 2 != 0 {
+                    return Err(TransformError::OddMap);
+                }
+                out.enc.enc_map_len(frame.items / 2)
+            } else {
+                out.enc.enc_arr_len(frame.items)
+            };
+            out.insert(frame.header_at, &header)?;
+        }
+
+        let token = match iter.next() {
+            None if depth == 0 => return Ok(out.cursor),
+            None => return Err(TransformError::Truncated),
+            Some(ValueToken::PayloadStart(_, _))
+            | Some(ValueToken::Chunk(_, _)) => {
+                return Err(TransformError::Truncated)
+            }
+            Some(token) => token,
+        };
+
+        let role = match depth {
+            0 => Role::Root,
+            _ => {
+                let frame = &mut stack[depth - 1];
+                let pos = (frame.slots - frame.remaining) as u32;
+                frame.remaining -= 1;
+                match (frame.is_map, pos % 2) {
+                    (false, _) => Role::Item(pos),
+                    (true, 0) => {
+                        frame.key = token;
+                        Role::Key(pos / 2)
+                    }
+                    (true, _) => Role::Value(pos / 2, frame.key),
+                }
+            }
+        };
+
+        let item = Item { token, depth, role };
+        let mut inject = Inject {
+            out: &mut out,
+            allowed: !matches!(role, Role::Value(_, _)),
+            count: 0,
+            err: None,
+        };
+        let action = f(&item, &mut inject);
+        if let Some(e) = inject.err {
+            return Err(e);
+        }
+        let injected = inject.count;
+
+        let slots = match token {
+            ValueToken::Arr(n) => n as u64,
+            ValueToken::Map(n) => n as u64 * 2,
+            _ => 0,
+        };
+
+        if depth > 0 {
+            let frame = &mut stack[depth - 1];
+            frame.items += injected;
+            if let Role::Key(_) = role {
+                frame.key_start = out.cursor;
+            }
+        }
+
+        match action {
+            Action::Keep => {
+                if depth > 0 {
+                    stack[depth - 1].items += 1;
+                }
+                if matches!(token, ValueToken::Arr(_) | ValueToken::Map(_)) {
+                    if depth == N {
+                        return Err(TransformError::TooDeep);
+                    }
+                    stack[depth] = Frame {
+                        is_map: matches!(token, ValueToken::Map(_)),
+                        header_at: out.cursor,
+                        slots,
+                        remaining: slots,
+                        ..Default::default()
+                    };
+                    depth += 1;
+                } else {
+                    out.write(token)?;
+                }
+            }
+            Action::Drop => {
+                skip(&mut iter, slots)?;
+                match role {
+                    Role::Key(_) => {
+                        // drop the value of this entry too
+                        skip(&mut iter, 1)?;
+                        stack[depth - 1].remaining -= 1;
+                    }
+                    Role::Value(_, _) => {
+                        // remove the already written key
+                        let frame = &mut stack[depth - 1];
+                        out.cursor = frame.key_start;
+                        frame.items -= 1;
+                    }
+                    _ => (),
+                }
+            }
+            Action::Replace(token) => {
+                if is_container(&token) {
+                    return Err(TransformError::InvalidToken);
+                }
+                skip(&mut iter, slots)?;
+                out.write(token)?;
+                if depth > 0 {
+                    stack[depth - 1].items += 1;
+                }
+            }
+        }
+    }
+}
+
+#[derive(Clone, Copy)]
+struct Frame<'buf> {
+    is_map: bool,
+
+    /// output offset at which the header will be inserted
+    header_at: usize,
+
+    /// input item count (two per map entry), and how many remain
+    slots: u64,
+    remaining: u64,
+
+    /// output item count (two per map entry)
+    items: u32,
+
+    /// the current map entry's input key, and its output offset
+    key: ValueToken<'buf>,
+    key_start: usize,
+}
+
+impl Default for Frame<'_> {
+    fn default() -> Self {
+        Self {
+            is_map: false,
+            header_at: 0,
+            slots: 0,
+            remaining: 0,
+            items: 0,
+            key: ValueToken::Nil,
+            key_start: 0,
+        }
+    }
+}
+
+struct Out<'o> {
+    buf: &'o mut [u8],
+    cursor: usize,
+    enc: Encoder,
+}
+
+impl Out<'_> {
+    fn put(&mut self, head: &[u8], data: &[u8]) -> Result<(), TransformError> {
+        let end = self.cursor + head.len() + data.len();
+        if end > self.buf.len() {
+            return Err(TransformError::Full);
+        }
+        let mid = self.cursor + head.len();
+        self.buf[self.cursor..mid].copy_from_slice(head);
+        self.buf[mid..end].copy_from_slice(data);
+        self.cursor = end;
+        Ok(())
+    }
+
+    /// write a non-container token
+    fn write(&mut self, token: ValueToken<'_>) -> Result<(), TransformError> {
+        let len = |data: &[u8]| {
+            u32::try_from(data.len()).map_err(|_| TransformError::Full)
+        };
+        match token {
+            ValueToken::Nil => {
+                let head = self.enc.enc_nil();
+                self.put(&head, &[])
+            }
+            ValueToken::Bool(b) => {
+                let head = self.enc.enc_bool(b);
+                self.put(&head, &[])
+            }
+            ValueToken::Num(n) => {
+                let head = self.enc.enc_num(n);
+                self.put(&head, &[])
+            }
+            ValueToken::Str(s) => {
+                let head = self.enc.enc_str_len(len(s.as_bytes())?);
+                self.put(&head, s.as_bytes())
+            }
+            ValueToken::InvalidStr(data) => {
+                let head = self.enc.enc_str_len(len(data)?);
+                self.put(&head, data)
+            }
+            ValueToken::Bin(data) => {
+                let head = self.enc.enc_bin_len(len(data)?);
+                self.put(&head, data)
+            }
+            ValueToken::Ext(t, data) => {
+                let head = self.enc.enc_ext_len(len(data)?, t);
+                self.put(&head, data)
+            }
+            _ => Err(TransformError::InvalidToken),
+        }
+    }
+
+    /// insert a container header at `at`, shifting the items after it
+    fn insert(
+        &mut self,
+        at: usize,
+        header: &[u8],
+    ) -> Result<(), TransformError> {
+        let end = self.cursor + header.len();
+        if end > self.buf.len() {
+            return Err(TransformError::Full);
+        }
+        self.buf.copy_within(at..self.cursor, at + header.len());
+        self.buf[at..at + header.len()].copy_from_slice(header);
+        self.cursor = end;
+        Ok(())
+    }
+}
+
+/// tokens that cannot be written on their own
+fn is_container(token: &ValueToken<'_>) -> bool {
+    matches!(
+        token,
+        ValueToken::Arr(_)
+            | ValueToken::Map(_)
+            | ValueToken::PayloadStart(_, _)
+            | ValueToken::Chunk(_, _)
+    )
+}
+
+fn skip(
+    iter: &mut ValueTokenIter<'_, '_>,
+    count: u64,
+) -> Result<(), TransformError> {
+    for _ in 0..count {
+        if !iter.skip_value() {
+            return Err(TransformError::Truncated);
+        }
+    }
+    Ok(())
+}
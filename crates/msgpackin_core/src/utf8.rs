@@ -0,0 +1,129 @@
+//! incremental utf8 validation for chunked str payloads
+//!
+//! A str payload that does not fit in a single buffer is decoded as a
+//! series of `Token::BinCont` fragments, which may split a multi-byte
+//! utf8 sequence. `Utf8Validator` carries partial sequences across
+//! fragments, so strings can be rejected as they are decoded.
+//!
+//! ```
+//! use msgpackin_core::utf8::*;
+//!
+//! // "é" is [0xc3, 0xa9], split across two chunks
+//! let mut v = Utf8Validator::new();
+//! v.push(b"caf\xc3").unwrap();
+//! v.push(b"\xa9").unwrap();
+//! v.finish().unwrap();
+//!
+//! let mut v = Utf8Validator::new();
+//! v.push(b"ok").unwrap();
+//! assert_eq!(Err(Utf8Error { offset: 3 }), v.push(b"!\xff"));
+//! ```
+
+/// Invalid utf8 was found in a str payload
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Utf8Error {
+    /// byte offset into the str payload of the invalid sequence
+    pub offset: usize,
+}
+
+impl core::fmt::Display for Utf8Error {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        write!(f, "invalid utf8 at offset {}", self.offset)
+    }
+}
+
+/// Incremental utf8 validator, see module docs
+#[derive(Debug, Default, Clone, Copy)]
+pub struct Utf8Validator {
+    /// bytes of an incomplete sequence at the end of the last chunk
+    partial: [u8; 4],
+    partial_len: usize,
+
+    /// count of bytes validated, excluding `partial`
+    offset: usize,
+}
+
+impl Utf8Validator {
+    /// Construct a new validator for a single str payload
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Total number of bytes pushed so far
+    pub fn len(&self) -> usize {
+        self.offset + self.partial_len
+    }
+
+    /// True if no bytes have been pushed
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Validate the next chunk of the payload.
+    /// Once this returns an error the validator should be discarded
+    pub fn push(&mut self, mut chunk: &[u8]) -> Result<(), Utf8Error> {
+        if self.partial_len > 0 {
+            let need = seq_len(self.partial[0]) - self.partial_len;
+            let take = core::cmp::min(need, chunk.len());
+            let end = self.partial_len + take;
+            self.partial[self.partial_len..end].copy_from_slice(&chunk[..take]);
+            chunk = &chunk[take..];
+            match core::str::from_utf8(&self.partial[..end]) {
+                Ok(_) => {
+                    self.offset += end;
+                    self.partial_len = 0;
+                }
+                Err(e) if e.error_len().is_none() => {
+                    // still incomplete, so the chunk was exhausted
+                    self.partial_len = end;
+                    return Ok(());
+                }
+                Err(_) => {
+                    return Err(Utf8Error {
+                        offset: self.offset,
+                    })
+                }
+            }
+        }
+        match core::str::from_utf8(chunk) {
+            Ok(_) => {
+                self.offset += chunk.len();
+                Ok(())
+            }
+            Err(e) => {
+                let valid = e.valid_up_to();
+                if e.error_len().is_some() {
+                    return Err(Utf8Error {
+                        offset: self.offset + valid,
+                    });
+                }
+                let rest = &chunk[valid..];
+                self.partial[..rest.len()].copy_from_slice(rest);
+                self.partial_len = rest.len();
+                self.offset += valid;
+                Ok(())
+            }
+        }
+    }
+
+    /// Check that the payload did not end within a multi-byte sequence
+    pub fn finish(&self) -> Result<(), Utf8Error> {
+        if self.partial_len > 0 {
+            Err(Utf8Error {
+                offset: self.offset,
+            })
+        } else {
+            Ok(())
+        }
+    }
+}
+
+/// length of the sequence started by a lead byte that
+/// `from_utf8` has already reported as a valid prefix
+fn seq_len(lead: u8) -> usize {
+    match lead {
+        0xc0..=0xdf => 2,
+        0xe0..=0xef => 3,
+        _ => 4,
+    }
+}